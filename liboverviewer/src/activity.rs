@@ -0,0 +1,249 @@
+//! Overlays showing where players have been
+//!
+//! An activity overlay colors each chunk by when it was last saved, or by how long players have
+//! spent near it, running from blue for the least to red for the most.  Its tiles are laid out
+//! exactly like those of a [`TopDown`] render with the same scale and north direction, so the web
+//! viewer can lay one over the other and turn it on and off.
+//!
+//! [`TopDown`]: ../render/struct.TopDown.html
+
+use image::{ImageBuffer, Rgba};
+use rio;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+
+use asset_manager::Texture;
+use coords::{self, Coord};
+use error::OverviewerError;
+use render::OverlayLayout;
+use render::topdown::TILE_BLOCKS;
+use world::Regionset;
+
+/// What an activity overlay measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Activity {
+    /// When each chunk was last saved, from the region file header
+    LastModified,
+
+    /// How long players have spent near each chunk, from its `InhabitedTime`
+    InhabitedTime,
+}
+
+impl Activity {
+    pub fn from_name(name: &str) -> Option<Activity> {
+        match name {
+            "last_modified" => Some(Activity::LastModified),
+            "inhabited_time" => Some(Activity::InhabitedTime),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Activity::LastModified => "last_modified",
+            Activity::InhabitedTime => "inhabited_time",
+        }
+    }
+
+    /// What the viewer calls the overlay
+    pub fn title(&self) -> &'static str {
+        match *self {
+            Activity::LastModified => "Last modified",
+            Activity::InhabitedTime => "Time spent",
+        }
+    }
+}
+
+/// Draws an activity overlay for one regionset
+pub struct ActivityOverlay {
+    pub activity: Activity,
+    pub layout: OverlayLayout,
+
+    /// The measured value of every chunk, by chunk position
+    values: BTreeMap<(i64, i64), i64>,
+    min: i64,
+    max: i64,
+}
+
+impl ActivityOverlay {
+    /// Measures every chunk in `rset`
+    ///
+    /// Chunks from before 1.6, which don't record their inhabited time, are left out of an
+    /// `InhabitedTime` overlay.  Measuring inhabited times decodes every chunk, so when the
+    /// regionset is being rendered anyway, gather them with [`TopDown::render_regionset_with`]
+    /// and pass them to [`from_values`] instead.
+    ///
+    /// [`TopDown::render_regionset_with`]: ../render/struct.TopDown.html#method.render_regionset_with
+    /// [`from_values`]: #method.from_values
+    pub fn scan<'fs, FS>(rset: &Regionset<'fs, FS>, activity: Activity) -> ActivityOverlay
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read + Seek
+    {
+        let mut values = BTreeMap::new();
+        for info in rset.get_chunks() {
+            let value = match activity {
+                Activity::LastModified => Some(info.mtime as i64),
+                Activity::InhabitedTime => {
+                    rset.get_chunk(info.pos).and_then(|c| c.inhabited_time())
+                }
+            };
+            if let Some(value) = value {
+                values.insert((info.pos.x, info.pos.z), value);
+            }
+        }
+        ActivityOverlay::from_values(activity, values)
+    }
+
+    pub fn from_values(activity: Activity, values: BTreeMap<(i64, i64), i64>) -> ActivityOverlay {
+        let min = values.values().cloned().min().unwrap_or(0);
+        let max = values.values().cloned().max().unwrap_or(0);
+        ActivityOverlay {
+            activity: activity,
+            layout: OverlayLayout::new(),
+            values: values,
+            min: min,
+            max: max,
+        }
+    }
+
+    /// How active the chunk at `pos` is, from 0 for the least in the regionset to 1 for the most
+    ///
+    /// Modification times are spread evenly between the oldest and newest chunk.  Inhabited times
+    /// are on a log scale, since a few chunks around a base have far more than everywhere else.
+    pub fn heat(&self, pos: Coord<coords::Chunk, coords::World>) -> Option<f32> {
+        self.values.get(&(pos.x, pos.z)).map(|&value| match self.activity {
+            Activity::LastModified if self.max > self.min => {
+                (value - self.min) as f32 / (self.max - self.min) as f32
+            }
+            Activity::LastModified => 1.0,
+            Activity::InhabitedTime if self.max > 0 => {
+                ((value.max(0) + 1) as f32).ln() / ((self.max + 1) as f32).ln()
+            }
+            Activity::InhabitedTime => 0.0,
+        })
+    }
+
+    /// Draws a single region, unrotated, with chunks that weren't measured left transparent
+    pub fn render_region(&self, region: Coord<coords::Region, coords::World>) -> Texture {
+        let n = self.layout.pixels_per_block;
+        let size = TILE_BLOCKS as u32 * n;
+        let x0 = region.x * TILE_BLOCKS;
+        let z0 = region.z * TILE_BLOCKS;
+        ImageBuffer::from_fn(size, size, |px, py| {
+            let x = x0 + (px / n) as i64;
+            let z = z0 + (py / n) as i64;
+            if !self.layout.shows(x, z) {
+                return Rgba([0, 0, 0, 0]);
+            }
+            match self.heat(Coord::new(x >> 4, 0, z >> 4)) {
+                Some(heat) => heat_color(heat),
+                None => Rgba([0, 0, 0, 0]),
+            }
+        })
+    }
+
+    /// Writes a PNG tile for every region with a measured chunk into `out_dir`, as
+    /// [`OverlayLayout::write_tiles`] does.  Returns the coordinates of the tiles written.
+    ///
+    /// [`OverlayLayout::write_tiles`]: ../render/struct.OverlayLayout.html#method.write_tiles
+    pub fn render_tiles<W, P>(&self,
+                              out: &W,
+                              out_dir: P)
+                              -> Result<Vec<(i64, i64)>, OverviewerError>
+        where W: rio::FSWrite,
+              P: AsRef<rio::Path>
+    {
+        // shifting floors, so negative chunks find the right region
+        let regions = self.values.keys().map(|&(x, z)| (x >> 5, z >> 5));
+        self.layout.write_tiles(out, out_dir, regions, |region| self.render_region(region))
+    }
+}
+
+/// The overlay color for a heat from 0 to 1: blue, through green and yellow, to red
+pub fn heat_color(heat: f32) -> Rgba<u8> {
+    let stops = [[0.0, 0.0, 255.0], [0.0, 200.0, 0.0], [255.0, 255.0, 0.0], [255.0, 0.0, 0.0]];
+    let t = heat.max(0.0).min(1.0) * (stops.len() - 1) as f32;
+    let i = (t as usize).min(stops.len() - 2);
+    let f = t - i as f32;
+    let mix = |c: usize| (stops[i][c] * (1.0 - f) + stops[i + 1][c] * f).round() as u8;
+    Rgba([mix(0), mix(1), mix(2), 128])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coords::Coord;
+    use fixtures::{TempWorld, WorldBuilder};
+    use image::Rgba;
+    use render::{BlockPalette, NorthDirection, TopDown};
+    use rio::FSRead;
+    use world::{Dimension, Regionset};
+
+    #[test]
+    fn test_heat_color() {
+        assert_eq!(heat_color(0.0), Rgba([0, 0, 255, 128]));
+        assert_eq!(heat_color(1.0), Rgba([255, 0, 0, 128]));
+        assert_eq!(heat_color(2.0 / 3.0), Rgba([255, 255, 0, 128]));
+        assert_eq!(heat_color(-1.0), heat_color(0.0));
+    }
+
+    #[test]
+    fn test_scan() {
+        let mut builder = WorldBuilder::new("Activity");
+        builder.chunk(Dimension::Overworld, 0, 0).timestamp = 1000;
+        builder.chunk(Dimension::Overworld, 0, 0).inhabited_time = 0;
+        builder.chunk(Dimension::Overworld, 1, 0).timestamp = 1500;
+        builder.chunk(Dimension::Overworld, 1, 0).inhabited_time = 9;
+        builder.chunk(Dimension::Overworld, -1, -1).timestamp = 2000;
+        builder.chunk(Dimension::Overworld, -1, -1).inhabited_time = 99;
+        let tmp = TempWorld::new(&builder).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+
+        let modified = ActivityOverlay::scan(&rset, Activity::LastModified);
+        assert_eq!(modified.heat(Coord::new(0, 0, 0)), Some(0.0));
+        assert_eq!(modified.heat(Coord::new(1, 0, 0)), Some(0.5));
+        assert_eq!(modified.heat(Coord::new(-1, 0, -1)), Some(1.0));
+        assert_eq!(modified.heat(Coord::new(5, 0, 5)), None);
+
+        let inhabited = ActivityOverlay::scan(&rset, Activity::InhabitedTime);
+        assert_eq!(inhabited.heat(Coord::new(0, 0, 0)), Some(0.0));
+        assert!((inhabited.heat(Coord::new(1, 0, 0)).unwrap() - 0.5).abs() < 1e-6);
+        assert_eq!(inhabited.heat(Coord::new(-1, 0, -1)), Some(1.0));
+
+        // the same times, picked up while rendering
+        let mut gathered = BTreeMap::new();
+        let renderer = TopDown::new(BlockPalette::builtin());
+        renderer.render_regionset_with(&rset, &tmp.fs, tmp.path(), |pos, chunk| {
+                gathered.insert((pos.x, pos.z), chunk.inhabited_time().unwrap());
+            })
+            .unwrap();
+        assert_eq!(gathered, inhabited.values);
+    }
+
+    #[test]
+    fn test_render_tiles() {
+        let mut values = BTreeMap::new();
+        values.insert((0, 0), 0);
+        values.insert((1, 0), 10);
+        values.insert((-1, -1), 20);
+        let mut overlay = ActivityOverlay::from_values(Activity::LastModified, values);
+
+        let img = overlay.render_region(Coord::new(0, 0, 0));
+        assert_eq!(img.dimensions(), (512, 512));
+        assert_eq!(*img.get_pixel(15, 15), heat_color(0.0));
+        assert_eq!(*img.get_pixel(16, 0), heat_color(0.5));
+        assert_eq!(img.get_pixel(32, 0)[3], 0);
+        let img = overlay.render_region(Coord::new(-1, 0, -1));
+        assert_eq!(*img.get_pixel(511, 511), heat_color(1.0));
+
+        let tmp = TempWorld::new(&WorldBuilder::new("Tiles")).unwrap();
+        overlay.layout.north = NorthDirection::LowerRight;
+        overlay.layout.zoom = 2;
+        let mut tiles = overlay.render_tiles(&tmp.fs, tmp.path()).unwrap();
+        tiles.sort();
+        assert_eq!(tiles, vec![(-1, -1), (0, 0)]);
+        assert!(tmp.fs.exists(tmp.path().join("2.-1.-1.png")));
+        assert!(tmp.fs.exists(tmp.path().join("2.0.0.png")));
+    }
+}
@@ -1,8 +1,58 @@
+use image::{self, ImageBuffer, Rgba};
+use rio;
+use std::io::{BufReader, Read, Seek};
 
-pub struct AssetManager;
+use super::error::OverviewerError;
 
-impl AssetManager {
-    pub fn new() -> AssetManager {
-        unimplemented!()
+/// An RGBA texture loaded from the assets
+pub type Texture = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+/// Loads textures and other assets out of a Minecraft client jar or resource pack
+///
+/// The assets are read from a directory laid out like an extracted client jar, that is, one that
+/// contains `assets/minecraft/textures`.
+pub struct AssetManager<'fs, FS: rio::FSRead<'fs>> {
+    root: rio::PathBuf,
+    fs: &'fs FS,
+}
+
+impl<'fs, FS> AssetManager<'fs, FS> where FS: rio::FSRead<'fs>, FS::ReadFile: Read + Seek {
+    /// Given the path to an extracted client jar or resource pack, construct an AssetManager
+    pub fn new<P: AsRef<rio::Path>>(fs: &'fs FS, p: P) -> Result<AssetManager<'fs, FS>, OverviewerError> {
+        let root = p.as_ref();
+        if !fs.exists(root.join("assets")) {
+            return Err(From::from(format!("Path {:?} does not contain any assets", root)));
+        }
+
+        Ok(AssetManager {
+            root: root.to_owned(),
+            fs: fs,
+        })
+    }
+
+    /// Loads a texture by its name relative to the textures directory, without an extension.
+    ///
+    /// For example, `get_texture("colormap/grass")` or `get_texture("block/stone")`.
+    pub fn get_texture(&self, name: &str) -> Result<Texture, OverviewerError> {
+        let path = self.root.join(format!("assets/minecraft/textures/{}.png", name));
+        let f = try!(self.fs.open(path));
+        let img = try!(image::load(BufReader::new(f), image::PNG));
+        Ok(img.to_rgba())
+    }
+
+    /// Lists the names of all textures in a directory under the textures directory, in the form
+    /// that `get_texture` takes.  For example, `list_textures("block")` might return
+    /// `["block/stone", "block/dirt", ...]`.
+    pub fn list_textures(&self, dir: &str) -> Result<Vec<String>, OverviewerError> {
+        let path = self.root.join(format!("assets/minecraft/textures/{}", dir));
+        let mut names = Vec::new();
+        for entry in try!(self.fs.read_dir(path)) {
+            if let Some(fname) = entry.path().file_name() {
+                if fname.ends_with(".png") {
+                    names.push(format!("{}/{}", dir, fname.trim_right_matches(".png")));
+                }
+            }
+        }
+        Ok(names)
     }
 }
@@ -0,0 +1,163 @@
+//! The biome registry
+//!
+//! Minecraft has stored biomes in several different ways over the years: a numeric ID per column
+//! before 1.15, a numeric ID per 4x4x4 cell from 1.15 to 1.17, and namespaced names in a
+//! per-section palette since 1.18.  No matter how a chunk stores them, biomes are resolved to a
+//! single [`Biome`] from this registry.
+//!
+//! [`Biome`]: struct.Biome.html
+
+/// A single biome, along with the climate values that the game uses for grass and foliage colors
+#[derive(Debug, PartialEq)]
+pub struct Biome {
+    /// Numeric ID used by chunks saved before Minecraft 1.18.  Biomes added in 1.18 or later don't
+    /// have one.
+    pub id: Option<u8>,
+
+    /// Every name this biome has gone by, without the `minecraft:` prefix.  The first name is the
+    /// current one.
+    pub names: &'static [&'static str],
+
+    pub temperature: f32,
+    pub downfall: f32,
+}
+
+impl Biome {
+    /// The current name of this biome, without the `minecraft:` prefix
+    pub fn name(&self) -> &'static str {
+        self.names[0]
+    }
+
+    /// Looks up a biome by its pre-1.18 numeric ID
+    pub fn from_id(id: u8) -> Option<&'static Biome> {
+        BIOMES.iter().find(|b| b.id == Some(id))
+    }
+
+    /// Looks up a biome by name.  The name can be namespaced (`minecraft:plains`) or not, and can
+    /// be a name from before the 1.18 renames
+    pub fn from_name(name: &str) -> Option<&'static Biome> {
+        let name = name.trim_left_matches("minecraft:");
+        BIOMES.iter().find(|b| b.names.contains(&name))
+    }
+}
+
+macro_rules! biomes {
+    ($( $id:expr, [$($name:expr),+], $temp:expr, $downfall:expr; )*) => {
+        &[$( Biome { id: $id, names: &[$($name),+], temperature: $temp, downfall: $downfall } ),*]
+    }
+}
+
+/// Every known biome
+pub static BIOMES: &'static [Biome] = biomes! {
+    Some(0), ["ocean"], 0.5, 0.5;
+    Some(1), ["plains"], 0.8, 0.4;
+    Some(2), ["desert"], 2.0, 0.0;
+    Some(3), ["windswept_hills", "mountains", "extreme_hills"], 0.2, 0.3;
+    Some(4), ["forest"], 0.7, 0.8;
+    Some(5), ["taiga"], 0.25, 0.8;
+    Some(6), ["swamp", "swampland"], 0.8, 0.9;
+    Some(7), ["river"], 0.5, 0.5;
+    Some(8), ["nether_wastes", "nether", "hell"], 2.0, 0.0;
+    Some(9), ["the_end", "sky"], 0.5, 0.5;
+    Some(10), ["frozen_ocean"], 0.0, 0.5;
+    Some(11), ["frozen_river"], 0.0, 0.5;
+    Some(12), ["snowy_plains", "snowy_tundra", "ice_flats"], 0.0, 0.5;
+    Some(13), ["snowy_mountains", "ice_mountains"], 0.0, 0.5;
+    Some(14), ["mushroom_fields", "mushroom_island"], 0.9, 1.0;
+    Some(15), ["mushroom_field_shore", "mushroom_island_shore"], 0.9, 1.0;
+    Some(16), ["beach", "beaches"], 0.8, 0.4;
+    Some(17), ["desert_hills"], 2.0, 0.0;
+    Some(18), ["wooded_hills", "forest_hills"], 0.7, 0.8;
+    Some(19), ["taiga_hills"], 0.25, 0.8;
+    Some(20), ["mountain_edge", "smaller_extreme_hills"], 0.2, 0.3;
+    Some(21), ["jungle"], 0.95, 0.9;
+    Some(22), ["jungle_hills"], 0.95, 0.9;
+    Some(23), ["sparse_jungle", "jungle_edge"], 0.95, 0.8;
+    Some(24), ["deep_ocean"], 0.5, 0.5;
+    Some(25), ["stony_shore", "stone_shore", "stone_beach"], 0.2, 0.3;
+    Some(26), ["snowy_beach", "cold_beach"], 0.05, 0.3;
+    Some(27), ["birch_forest"], 0.6, 0.6;
+    Some(28), ["birch_forest_hills"], 0.6, 0.6;
+    Some(29), ["dark_forest", "roofed_forest"], 0.7, 0.8;
+    Some(30), ["snowy_taiga", "taiga_cold"], -0.5, 0.4;
+    Some(31), ["snowy_taiga_hills", "taiga_cold_hills"], -0.5, 0.4;
+    Some(32), ["old_growth_pine_taiga", "giant_tree_taiga", "redwood_taiga"], 0.3, 0.8;
+    Some(33), ["giant_tree_taiga_hills", "redwood_taiga_hills"], 0.3, 0.8;
+    Some(34), ["windswept_forest", "wooded_mountains", "extreme_hills_with_trees"], 0.2, 0.3;
+    Some(35), ["savanna"], 1.2, 0.0;
+    Some(36), ["savanna_plateau"], 1.0, 0.0;
+    Some(37), ["badlands", "mesa"], 2.0, 0.0;
+    Some(38), ["wooded_badlands", "wooded_badlands_plateau", "mesa_rock"], 2.0, 0.0;
+    Some(39), ["badlands_plateau", "mesa_clear_rock"], 2.0, 0.0;
+    Some(40), ["small_end_islands"], 0.5, 0.5;
+    Some(41), ["end_midlands"], 0.5, 0.5;
+    Some(42), ["end_highlands"], 0.5, 0.5;
+    Some(43), ["end_barrens"], 0.5, 0.5;
+    Some(44), ["warm_ocean"], 0.5, 0.5;
+    Some(45), ["lukewarm_ocean"], 0.5, 0.5;
+    Some(46), ["cold_ocean"], 0.5, 0.5;
+    Some(47), ["deep_warm_ocean"], 0.5, 0.5;
+    Some(48), ["deep_lukewarm_ocean"], 0.5, 0.5;
+    Some(49), ["deep_cold_ocean"], 0.5, 0.5;
+    Some(50), ["deep_frozen_ocean"], 0.5, 0.5;
+    Some(127), ["the_void", "void"], 0.5, 0.5;
+    Some(129), ["sunflower_plains", "mutated_plains"], 0.8, 0.4;
+    Some(130), ["desert_lakes", "mutated_desert"], 2.0, 0.0;
+    Some(131), ["windswept_gravelly_hills", "gravelly_mountains", "mutated_extreme_hills"], 0.2, 0.3;
+    Some(132), ["flower_forest", "mutated_forest"], 0.7, 0.8;
+    Some(133), ["taiga_mountains", "mutated_taiga"], 0.25, 0.8;
+    Some(134), ["swamp_hills", "mutated_swampland"], 0.8, 0.9;
+    Some(140), ["ice_spikes", "mutated_ice_flats"], 0.0, 0.5;
+    Some(149), ["modified_jungle", "mutated_jungle"], 0.95, 0.9;
+    Some(151), ["modified_jungle_edge", "mutated_jungle_edge"], 0.95, 0.8;
+    Some(155), ["old_growth_birch_forest", "tall_birch_forest", "mutated_birch_forest"], 0.6, 0.6;
+    Some(156), ["tall_birch_hills", "mutated_birch_forest_hills"], 0.6, 0.6;
+    Some(157), ["dark_forest_hills", "mutated_roofed_forest"], 0.7, 0.8;
+    Some(158), ["snowy_taiga_mountains", "mutated_taiga_cold"], -0.5, 0.4;
+    Some(160), ["old_growth_spruce_taiga", "giant_spruce_taiga", "mutated_redwood_taiga"], 0.25, 0.8;
+    Some(161), ["giant_spruce_taiga_hills", "mutated_redwood_taiga_hills"], 0.25, 0.8;
+    Some(162), ["modified_gravelly_mountains", "mutated_extreme_hills_with_trees"], 0.2, 0.3;
+    Some(163), ["windswept_savanna", "shattered_savanna", "mutated_savanna"], 1.1, 0.0;
+    Some(164), ["shattered_savanna_plateau", "mutated_savanna_rock"], 1.0, 0.0;
+    Some(165), ["eroded_badlands", "mutated_mesa"], 2.0, 0.0;
+    Some(166), ["modified_wooded_badlands_plateau", "mutated_mesa_rock"], 2.0, 0.0;
+    Some(167), ["modified_badlands_plateau", "mutated_mesa_clear_rock"], 2.0, 0.0;
+    Some(168), ["bamboo_jungle"], 0.95, 0.9;
+    Some(169), ["bamboo_jungle_hills"], 0.95, 0.9;
+    Some(170), ["soul_sand_valley"], 2.0, 0.0;
+    Some(171), ["crimson_forest"], 2.0, 0.0;
+    Some(172), ["warped_forest"], 2.0, 0.0;
+    Some(173), ["basalt_deltas"], 2.0, 0.0;
+    Some(174), ["dripstone_caves"], 0.8, 0.4;
+    Some(175), ["lush_caves"], 0.5, 0.5;
+    None, ["meadow"], 0.5, 0.8;
+    None, ["grove"], -0.2, 0.8;
+    None, ["snowy_slopes"], -0.3, 0.9;
+    None, ["frozen_peaks"], -0.7, 0.9;
+    None, ["jagged_peaks"], -0.7, 0.9;
+    None, ["stony_peaks"], 1.0, 0.3;
+    None, ["deep_dark"], 0.8, 0.4;
+    None, ["mangrove_swamp"], 0.8, 0.9;
+    None, ["cherry_grove"], 0.5, 0.8;
+};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_biome_lookup() {
+        let plains = Biome::from_id(1).unwrap();
+        assert_eq!(plains.name(), "plains");
+        assert_eq!(Biome::from_name("minecraft:plains"), Some(plains));
+
+        // renamed biomes resolve to the same entry under either name
+        let hills = Biome::from_name("minecraft:mountains").unwrap();
+        assert_eq!(hills.name(), "windswept_hills");
+        assert_eq!(Biome::from_id(3), Some(hills));
+
+        assert_eq!(Biome::from_name("cherry_grove").unwrap().id, None);
+        assert_eq!(Biome::from_name("minecraft:not_a_biome"), None);
+        assert_eq!(Biome::from_id(200), None);
+    }
+}
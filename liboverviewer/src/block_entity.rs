@@ -0,0 +1,401 @@
+//! Typed block entities (called tile entities before 1.13)
+//!
+//! Block entities hold whatever a block needs beyond its state: the text on a sign, the contents of
+//! a chest, the patterns on a banner.  [`BlockEntity::from_tag`] decodes the common ones across
+//! the format changes of the last several versions, and keeps the rest as
+//! [`BlockEntityData::Other`].
+//!
+//! [`BlockEntity::from_tag`]: struct.BlockEntity.html#method.from_tag
+//! [`BlockEntityData::Other`]: enum.BlockEntityData.html#variant.Other
+
+use nbtrs::{Tag, Taglike};
+
+use coords::{self, Coord};
+use error::OverviewerError;
+use poi::{normalize_id, text_component};
+
+/// The dye colors, in the order that numbered colors use since 1.13
+pub const DYE_COLORS: [&'static str; 16] = ["white", "orange", "magenta", "light_blue", "yellow",
+                                            "lime", "pink", "gray", "light_gray", "cyan",
+                                            "purple", "blue", "brown", "green", "red", "black"];
+
+/// A block entity, with the fields every one has
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEntity {
+    /// The namespaced ID, like `minecraft:chest`
+    pub id: String,
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+
+    /// Set with an anvil, on containers and a few others
+    pub custom_name: Option<String>,
+    pub data: BlockEntityData,
+}
+
+/// What's known about particular kinds of block entity
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockEntityData {
+    Sign {
+        /// The lines on the front; before 1.20 signs only had a front
+        front: Vec<String>,
+        back: Vec<String>,
+    },
+
+    /// Chests, barrels, shulker boxes, hoppers, dispensers and droppers
+    Container { items: Vec<ItemStack> },
+    Banner {
+        /// Only saved before 1.13; after that it's part of the block name
+        base_color: Option<String>,
+        patterns: Vec<BannerPattern>,
+    },
+    Spawner {
+        /// The ID of the entity that will be spawned
+        entity: Option<String>,
+    },
+    Beacon {
+        /// How many layers of pyramid are under the beacon, from 0 to 4
+        levels: i32,
+    },
+    Other,
+}
+
+/// A stack of items in a container
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStack {
+    pub id: String,
+    pub count: i32,
+    pub slot: Option<i32>,
+    pub custom_name: Option<String>,
+
+    /// What's inside, for shulker boxes and bundles
+    pub contents: Vec<ItemStack>,
+}
+
+/// One layer of a banner's design
+#[derive(Debug, Clone, PartialEq)]
+pub struct BannerPattern {
+    /// The namespaced pattern ID, like `minecraft:stripe_bottom`, or the short code that was used
+    /// before 1.20.5, like `bs`
+    pub pattern: String,
+    pub color: String,
+}
+
+impl BlockEntity {
+    pub fn from_tag(tag: &Tag) -> Result<BlockEntity, OverviewerError> {
+        let id = normalize_id(try!(tag.key("id").as_string()));
+        let data = {
+            let kind = id.trim_left_matches("minecraft:");
+            if kind == "sign" || kind.ends_with("_sign") {
+                sign(tag)
+            } else if kind == "chest" || kind == "trapped_chest" || kind == "barrel" ||
+                      kind.ends_with("shulker_box") || kind == "hopper" ||
+                      kind == "dispenser" || kind == "dropper" {
+                BlockEntityData::Container { items: items(tag.key("Items").as_list()) }
+            } else if kind == "banner" || kind.ends_with("_banner") {
+                banner(tag)
+            } else if kind == "mob_spawner" || kind == "spawner" {
+                // SpawnData held the entity itself before 1.18, and EntityId before 1.9
+                let entity = tag.key("SpawnData")
+                    .key("entity")
+                    .key("id")
+                    .or_else(|_| tag.key("SpawnData").key("id"))
+                    .or_else(|_| tag.key("EntityId"))
+                    .as_string();
+                BlockEntityData::Spawner { entity: entity.ok().map(|e| normalize_id(e)) }
+            } else if kind == "beacon" {
+                BlockEntityData::Beacon { levels: tag.key("Levels").as_i32().unwrap_or(0) }
+            } else {
+                BlockEntityData::Other
+            }
+        };
+
+        Ok(BlockEntity {
+            id: id,
+            x: try!(tag.key("x").as_i32()) as i64,
+            y: try!(tag.key("y").as_i32()) as i64,
+            z: try!(tag.key("z").as_i32()) as i64,
+            custom_name: tag.key("CustomName").as_tag().ok().and_then(nbt_text),
+            data: data,
+        })
+    }
+
+    /// The non-blank lines of a sign, front then back, joined with newlines.  For anything else,
+    /// the custom name.
+    pub fn text(&self) -> Option<String> {
+        match self.data {
+            BlockEntityData::Sign { ref front, ref back } => {
+                let lines: Vec<_> = front.iter()
+                    .chain(back)
+                    .filter(|l| !l.trim().is_empty())
+                    .map(|l| &l[..])
+                    .collect();
+                if lines.is_empty() { None } else { Some(lines.join("\n")) }
+            }
+            _ => self.custom_name.clone(),
+        }
+    }
+
+    pub fn pos(&self) -> Coord<coords::Block, coords::World> {
+        Coord::new(self.x, self.y, self.z)
+    }
+
+    /// Every stack in this block entity whose ID is `id`, including those inside shulker boxes
+    pub fn find_items(&self, id: &str) -> Vec<&ItemStack> {
+        let id = normalize_id(id);
+        let mut found = Vec::new();
+        if let BlockEntityData::Container { ref items } = self.data {
+            for item in items {
+                item.find(&id, &mut found);
+            }
+        }
+        found
+    }
+}
+
+impl ItemStack {
+    pub fn from_tag(tag: &Tag) -> Option<ItemStack> {
+        let id = match tag.key("id").as_string() {
+            Ok(id) => normalize_id(id),
+            Err(_) => return None,
+        };
+
+        // 1.20.5 replaced the free-form tag with item components
+        let component = |name| tag.key("components").key(name);
+        let custom_name = component("minecraft:custom_name")
+            .or_else(|_| tag.key("tag").key("display").key("Name"))
+            .as_tag()
+            .ok()
+            .and_then(nbt_text);
+        let contents = match component("minecraft:container").as_list() {
+            Ok(slots) => {
+                slots.iter()
+                    .filter_map(|s| {
+                        s.key("item").ok().and_then(ItemStack::from_tag).map(|mut item| {
+                            item.slot = s.key("slot").as_i32().ok();
+                            item
+                        })
+                    })
+                    .collect()
+            }
+            Err(_) => {
+                items(component("minecraft:bundle_contents")
+                    .or_else(|_| tag.key("tag").key("BlockEntityTag").key("Items"))
+                    .as_list())
+            }
+        };
+
+        Some(ItemStack {
+            id: id,
+            count: tag.key("count")
+                .as_i32()
+                .or_else(|_| tag.key("Count").as_i8().map(|c| c as i32))
+                .unwrap_or(1),
+            slot: tag.key("Slot").as_i8().ok().map(|s| s as i32),
+            custom_name: custom_name,
+            contents: contents,
+        })
+    }
+
+    fn find<'a>(&'a self, id: &str, found: &mut Vec<&'a ItemStack>) {
+        if self.id == id {
+            found.push(self);
+        }
+        for item in &self.contents {
+            item.find(id, found);
+        }
+    }
+}
+
+fn items<E>(list: Result<&Vec<Tag>, E>) -> Vec<ItemStack> {
+    match list {
+        Ok(list) => list.iter().filter_map(ItemStack::from_tag).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Reads a text component.  Since 1.21.5 they're stored as NBT; before that they were JSON in a
+/// string, and before 1.8 just a string.
+pub fn nbt_text(tag: &Tag) -> Option<String> {
+    fn flatten(tag: &Tag, out: &mut String) {
+        match *tag {
+            Tag::TagString(ref s) => out.push_str(s),
+            Tag::TagList(ref items) => {
+                for item in items {
+                    flatten(item, out);
+                }
+            }
+            Tag::TagCompound(_) => {
+                if let Ok(text) = tag.key("text") {
+                    flatten(text, out);
+                }
+                if let Ok(extra) = tag.key("extra") {
+                    flatten(extra, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let text = match *tag {
+        Tag::TagString(ref s) => text_component(s),
+        _ => {
+            let mut out = String::new();
+            flatten(tag, &mut out);
+            out
+        }
+    };
+    if text.is_empty() { None } else { Some(text) }
+}
+
+fn sign(tag: &Tag) -> BlockEntityData {
+    let lines = |side: &str| -> Vec<String> {
+        match tag.key(side).key("messages").as_list() {
+            Ok(messages) => messages.iter().map(|m| nbt_text(m).unwrap_or_default()).collect(),
+            Err(_) => Vec::new(),
+        }
+    };
+    if tag.key("front_text").is_ok() {
+        BlockEntityData::Sign {
+            front: lines("front_text"),
+            back: lines("back_text"),
+        }
+    } else {
+        BlockEntityData::Sign {
+            front: (1..5)
+                .map(|i| tag.key(&format!("Text{}", i)).ok().and_then(nbt_text).unwrap_or_default())
+                .collect(),
+            back: Vec::new(),
+        }
+    }
+}
+
+fn banner(tag: &Tag) -> BlockEntityData {
+    // before 1.13 colors were numbered the other way round, and the base color was saved here
+    let legacy = tag.key("Base").is_ok();
+    let color_name = |color: &Tag| -> String {
+        match *color {
+            Tag::TagString(ref name) => name.clone(),
+            Tag::TagInt(i) => {
+                let i = if legacy { 15 - i } else { i };
+                DYE_COLORS.get(i as usize).cloned().unwrap_or("white").to_owned()
+            }
+            _ => "white".to_owned(),
+        }
+    };
+
+    let patterns = match tag.key("patterns").or_else(|_| tag.key("Patterns")).as_list() {
+        Ok(list) => {
+            list.iter()
+                .filter_map(|p| {
+                    let pattern = p.key("pattern").or_else(|_| p.key("Pattern")).as_string();
+                    let color = p.key("color").or_else(|_| p.key("Color"));
+                    match (pattern, color) {
+                        (Ok(pattern), Ok(color)) => {
+                            Some(BannerPattern {
+                                pattern: pattern.clone(),
+                                color: color_name(color),
+                            })
+                        }
+                        _ => None,
+                    }
+                })
+                .collect()
+        }
+        Err(_) => Vec::new(),
+    };
+    BlockEntityData::Banner {
+        base_color: tag.key("Base").ok().map(|b| color_name(b)),
+        patterns: patterns,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nbtrs::Tag;
+    use std::collections::HashMap;
+
+    fn compound(fields: Vec<(&str, Tag)>) -> Tag {
+        let fields: HashMap<_, _> = fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect();
+        Tag::TagCompound(fields)
+    }
+
+    fn string(s: &str) -> Tag {
+        Tag::TagString(s.to_owned())
+    }
+
+    fn at(id: &str, mut fields: Vec<(&'static str, Tag)>) -> Tag {
+        fields.push(("id", string(id)));
+        fields.push(("x", Tag::TagInt(1)));
+        fields.push(("y", Tag::TagInt(64)));
+        fields.push(("z", Tag::TagInt(-2)));
+        compound(fields)
+    }
+
+    #[test]
+    fn test_sign() {
+        let legacy = at("Sign",
+                        vec![("Text1", string(r#"{"text":"Hello"}"#)),
+                             ("Text2", string(r#""""#)),
+                             ("Text3", string("world")),
+                             ("Text4", string(""))]);
+        let sign = BlockEntity::from_tag(&legacy).unwrap();
+        assert_eq!(sign.id, "minecraft:sign");
+        assert_eq!(sign.data,
+                   BlockEntityData::Sign {
+                       front: vec!["Hello", "", "world", ""].into_iter().map(|s| s.to_owned()).collect(),
+                       back: vec![],
+                   });
+
+        let messages = |lines: Vec<Tag>| compound(vec![("messages", Tag::TagList(lines))]);
+        // the front is a 1.21.5 NBT component, the back an older JSON one
+        let front = messages(vec![compound(vec![("text", string("Front"))])]);
+        let back = messages(vec![string(r#"{"text":"Back"}"#)]);
+        let modern = at("minecraft:oak_sign", vec![("front_text", front), ("back_text", back)]);
+        assert_eq!(BlockEntity::from_tag(&modern).unwrap().data,
+                   BlockEntityData::Sign {
+                       front: vec!["Front".to_owned()],
+                       back: vec!["Back".to_owned()],
+                   });
+    }
+
+    #[test]
+    fn test_chest() {
+        let shulker = compound(vec![("id", string("minecraft:shulker_box")),
+                                    ("Count", Tag::TagByte(1)),
+                                    ("Slot", Tag::TagByte(3)),
+                                    ("tag",
+                                     compound(vec![("BlockEntityTag",
+                                                    compound(vec![("Items",
+                                                                   Tag::TagList(vec![
+                                        compound(vec![("id", string("minecraft:diamond")),
+                                                      ("Count", Tag::TagByte(64))])]))]))]))]);
+        let chest = at("minecraft:chest",
+                       vec![("Items",
+                             Tag::TagList(vec![shulker,
+                                               compound(vec![("id", string("minecraft:diamond")),
+                                                             ("count", Tag::TagInt(5))])]))]);
+        let chest = BlockEntity::from_tag(&chest).unwrap();
+        let found = chest.find_items("diamond");
+        assert_eq!(found.iter().map(|i| i.count).collect::<Vec<_>>(), vec![64, 5]);
+        assert!(chest.find_items("minecraft:emerald").is_empty());
+    }
+
+    #[test]
+    fn test_banner() {
+        let legacy = at("Banner",
+                        vec![("Base", Tag::TagInt(0)),
+                             ("Patterns",
+                              Tag::TagList(vec![compound(vec![("Pattern", string("bs")),
+                                                              ("Color", Tag::TagInt(1))])]))]);
+        assert_eq!(BlockEntity::from_tag(&legacy).unwrap().data,
+                   BlockEntityData::Banner {
+                       base_color: Some("black".to_owned()),
+                       patterns: vec![BannerPattern {
+                                          pattern: "bs".to_owned(),
+                                          color: "red".to_owned(),
+                                      }],
+                   });
+    }
+}
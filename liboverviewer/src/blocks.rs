@@ -0,0 +1,431 @@
+//! Block states
+//!
+//! Chunks saved by Minecraft 1.13 and later name their blocks (`minecraft:oak_log`) and carry a set
+//! of properties (`axis=y`).  Older chunks store a numeric ID and 4 bits of metadata instead.  Both
+//! are decoded into a [`BlockState`], with legacy IDs translated to their modern names so that
+//! renderers only have to deal with one naming scheme.
+//!
+//! [`BlockState`]: struct.BlockState.html
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// A block, identified by its namespaced name and its properties
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockState {
+    /// Namespaced name, such as `minecraft:stone`
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+const COLORS: [&'static str; 16] = ["white", "orange", "magenta", "light_blue", "yellow", "lime",
+                                    "pink", "gray", "light_gray", "cyan", "purple", "blue",
+                                    "brown", "green", "red", "black"];
+
+const WOODS: [&'static str; 6] = ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+
+impl BlockState {
+    /// Creates a block state with no properties.  The name is namespaced if it isn't already.
+    pub fn new(name: &str) -> BlockState {
+        let name = if name.contains(':') {
+            name.to_owned()
+        } else {
+            format!("minecraft:{}", name)
+        };
+        BlockState {
+            name: name,
+            properties: BTreeMap::new(),
+        }
+    }
+
+    /// The block you get for positions outside of any stored section
+    pub fn air() -> BlockState {
+        BlockState::new("minecraft:air")
+    }
+
+    /// Translates a pre-1.13 block ID and metadata value into a block state
+    ///
+    /// Metadata is only used to pick the block name (for example, which color of wool).  IDs
+    /// that aren't known are named `minecraft:legacy_<id>`.
+    pub fn from_legacy(id: u16, data: u8) -> BlockState {
+        BlockState::new(&legacy_name(id, data))
+    }
+
+    /// The name of this block without the namespace, such as `stone`
+    pub fn short_name(&self) -> &str {
+        match self.name.find(':') {
+            Some(idx) => &self.name[idx + 1..],
+            None => &self.name,
+        }
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(|v| v.as_ref())
+    }
+
+    pub fn is_air(&self) -> bool {
+        match self.short_name() {
+            "air" | "cave_air" | "void_air" => true,
+            _ => false,
+        }
+    }
+
+    /// Whether this block is a full, opaque cube that hides whatever is behind it
+    ///
+    /// This is a name-based approximation, which is good enough for deciding what's visible and
+    /// where light can reach.
+    pub fn is_opaque(&self) -> bool {
+        let name = self.short_name();
+        if self.is_air() || name.contains("glass") || name.ends_with("leaves") {
+            return false;
+        }
+
+        const PARTIAL_SUFFIXES: &'static [&'static str] =
+            &["_slab", "_stairs", "_fence", "_fence_gate", "_wall", "_door", "_trapdoor", "_sign",
+              "_button", "_pressure_plate", "_carpet", "_torch", "_sapling", "_rail", "_banner",
+              "_bed", "_pane", "_flower", "_tulip", "_mushroom", "_coral", "_coral_fan", "_candle",
+              "_head", "_skull", "_bars", "_stem", "_plant", "_roots", "_fungus", "_vines"];
+        if PARTIAL_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+            return false;
+        }
+
+        match name {
+            "water" | "lava" | "ice" | "torch" | "wall_torch" | "rail" | "ladder" | "vine" |
+            "grass" | "short_grass" | "tall_grass" | "fern" | "large_fern" | "dead_bush" |
+            "dandelion" | "poppy" | "blue_orchid" | "allium" | "azure_bluet" | "oxeye_daisy" |
+            "cornflower" | "lily_of_the_valley" | "sunflower" | "lilac" | "rose_bush" | "peony" |
+            "sugar_cane" | "wheat" | "carrots" | "potatoes" | "beetroots" | "nether_wart" |
+            "redstone_wire" | "repeater" | "comparator" | "lever" | "tripwire" | "tripwire_hook" |
+            "snow" | "cactus" | "cake" | "cobweb" | "fire" | "soul_fire" | "lily_pad" |
+            "flower_pot" | "chest" | "trapped_chest" | "ender_chest" | "enchanting_table" |
+            "brewing_stand" | "cauldron" | "hopper" | "anvil" | "end_rod" | "chorus_plant" |
+            "chorus_flower" | "dragon_egg" | "spawner" | "beacon" | "barrier" | "structure_void" |
+            "nether_portal" | "end_portal" | "end_gateway" | "farmland" | "dirt_path" |
+            "grass_path" | "daylight_detector" | "seagrass" | "tall_seagrass" | "kelp" |
+            "kelp_plant" | "bamboo" | "scaffolding" | "lantern" | "soul_lantern" | "chain" |
+            "bubble_column" | "light" => false,
+            _ => true,
+        }
+    }
+}
+
+fn legacy_name(id: u16, data: u8) -> Cow<'static, str> {
+    let color = COLORS[(data & 0xf) as usize];
+    let name = match id {
+        0 => "air",
+        1 => {
+            match data {
+                1 => "granite",
+                2 => "polished_granite",
+                3 => "diorite",
+                4 => "polished_diorite",
+                5 => "andesite",
+                6 => "polished_andesite",
+                _ => "stone",
+            }
+        }
+        2 => "grass_block",
+        3 => {
+            match data {
+                1 => "coarse_dirt",
+                2 => "podzol",
+                _ => "dirt",
+            }
+        }
+        4 => "cobblestone",
+        5 => return format!("{}_planks", WOODS[(data % 6) as usize]).into(),
+        6 => return format!("{}_sapling", WOODS[(data & 7) as usize % 6]).into(),
+        7 => "bedrock",
+        8 | 9 => "water",
+        10 | 11 => "lava",
+        12 => if data == 1 { "red_sand" } else { "sand" },
+        13 => "gravel",
+        14 => "gold_ore",
+        15 => "iron_ore",
+        16 => "coal_ore",
+        17 => return format!("{}_log", WOODS[(data & 3) as usize]).into(),
+        18 => return format!("{}_leaves", WOODS[(data & 3) as usize]).into(),
+        19 => if data == 1 { "wet_sponge" } else { "sponge" },
+        20 => "glass",
+        21 => "lapis_ore",
+        22 => "lapis_block",
+        23 => "dispenser",
+        24 => {
+            match data {
+                1 => "chiseled_sandstone",
+                2 => "cut_sandstone",
+                _ => "sandstone",
+            }
+        }
+        25 => "note_block",
+        26 => "red_bed",
+        27 => "powered_rail",
+        28 => "detector_rail",
+        29 => "sticky_piston",
+        30 => "cobweb",
+        31 => {
+            match data {
+                0 => "dead_bush",
+                2 => "fern",
+                _ => "grass",
+            }
+        }
+        32 => "dead_bush",
+        33 => "piston",
+        34 => "piston_head",
+        35 => return format!("{}_wool", color).into(),
+        36 => "moving_piston",
+        37 => "dandelion",
+        38 => {
+            match data {
+                1 => "blue_orchid",
+                2 => "allium",
+                3 => "azure_bluet",
+                4 => "red_tulip",
+                5 => "orange_tulip",
+                6 => "white_tulip",
+                7 => "pink_tulip",
+                8 => "oxeye_daisy",
+                _ => "poppy",
+            }
+        }
+        39 => "brown_mushroom",
+        40 => "red_mushroom",
+        41 => "gold_block",
+        42 => "iron_block",
+        43 | 44 => "stone_slab",
+        45 => "bricks",
+        46 => "tnt",
+        47 => "bookshelf",
+        48 => "mossy_cobblestone",
+        49 => "obsidian",
+        50 => "torch",
+        51 => "fire",
+        52 => "spawner",
+        53 => "oak_stairs",
+        54 => "chest",
+        55 => "redstone_wire",
+        56 => "diamond_ore",
+        57 => "diamond_block",
+        58 => "crafting_table",
+        59 => "wheat",
+        60 => "farmland",
+        61 | 62 => "furnace",
+        63 => "oak_sign",
+        64 => "oak_door",
+        65 => "ladder",
+        66 => "rail",
+        67 => "cobblestone_stairs",
+        68 => "oak_wall_sign",
+        69 => "lever",
+        70 => "stone_pressure_plate",
+        71 => "iron_door",
+        72 => "oak_pressure_plate",
+        73 | 74 => "redstone_ore",
+        75 | 76 => "redstone_torch",
+        77 => "stone_button",
+        78 => "snow",
+        79 => "ice",
+        80 => "snow_block",
+        81 => "cactus",
+        82 => "clay",
+        83 => "sugar_cane",
+        84 => "jukebox",
+        85 => "oak_fence",
+        86 => "carved_pumpkin",
+        87 => "netherrack",
+        88 => "soul_sand",
+        89 => "glowstone",
+        90 => "nether_portal",
+        91 => "jack_o_lantern",
+        92 => "cake",
+        93 | 94 => "repeater",
+        95 => return format!("{}_stained_glass", color).into(),
+        96 => "oak_trapdoor",
+        97 => "infested_stone",
+        98 => {
+            match data {
+                1 => "mossy_stone_bricks",
+                2 => "cracked_stone_bricks",
+                3 => "chiseled_stone_bricks",
+                _ => "stone_bricks",
+            }
+        }
+        99 => "brown_mushroom_block",
+        100 => "red_mushroom_block",
+        101 => "iron_bars",
+        102 => "glass_pane",
+        103 => "melon",
+        104 => "pumpkin_stem",
+        105 => "melon_stem",
+        106 => "vine",
+        107 => "oak_fence_gate",
+        108 => "brick_stairs",
+        109 => "stone_brick_stairs",
+        110 => "mycelium",
+        111 => "lily_pad",
+        112 => "nether_bricks",
+        113 => "nether_brick_fence",
+        114 => "nether_brick_stairs",
+        115 => "nether_wart",
+        116 => "enchanting_table",
+        117 => "brewing_stand",
+        118 => "cauldron",
+        119 => "end_portal",
+        120 => "end_portal_frame",
+        121 => "end_stone",
+        122 => "dragon_egg",
+        123 | 124 => "redstone_lamp",
+        125 | 126 => "oak_slab",
+        127 => "cocoa",
+        128 => "sandstone_stairs",
+        129 => "emerald_ore",
+        130 => "ender_chest",
+        131 => "tripwire_hook",
+        132 => "tripwire",
+        133 => "emerald_block",
+        134 => "spruce_stairs",
+        135 => "birch_stairs",
+        136 => "jungle_stairs",
+        137 => "command_block",
+        138 => "beacon",
+        139 => "cobblestone_wall",
+        140 => "flower_pot",
+        141 => "carrots",
+        142 => "potatoes",
+        143 => "oak_button",
+        144 => "skeleton_skull",
+        145 => "anvil",
+        146 => "trapped_chest",
+        147 => "light_weighted_pressure_plate",
+        148 => "heavy_weighted_pressure_plate",
+        149 | 150 => "comparator",
+        151 | 178 => "daylight_detector",
+        152 => "redstone_block",
+        153 => "nether_quartz_ore",
+        154 => "hopper",
+        155 => {
+            match data {
+                1 => "chiseled_quartz_block",
+                2 | 3 | 4 => "quartz_pillar",
+                _ => "quartz_block",
+            }
+        }
+        156 => "quartz_stairs",
+        157 => "activator_rail",
+        158 => "dropper",
+        159 => return format!("{}_terracotta", color).into(),
+        160 => return format!("{}_stained_glass_pane", color).into(),
+        161 => return format!("{}_leaves", WOODS[4 + (data & 1) as usize]).into(),
+        162 => return format!("{}_log", WOODS[4 + (data & 1) as usize]).into(),
+        163 => "acacia_stairs",
+        164 => "dark_oak_stairs",
+        165 => "slime_block",
+        166 => "barrier",
+        167 => "iron_trapdoor",
+        168 => {
+            match data {
+                1 => "prismarine_bricks",
+                2 => "dark_prismarine",
+                _ => "prismarine",
+            }
+        }
+        169 => "sea_lantern",
+        170 => "hay_block",
+        171 => return format!("{}_carpet", color).into(),
+        172 => "terracotta",
+        173 => "coal_block",
+        174 => "packed_ice",
+        175 => {
+            match data & 7 {
+                1 => "lilac",
+                2 => "tall_grass",
+                3 => "large_fern",
+                4 => "rose_bush",
+                5 => "peony",
+                _ => "sunflower",
+            }
+        }
+        176 => "white_banner",
+        177 => "white_wall_banner",
+        179 => {
+            match data {
+                1 => "chiseled_red_sandstone",
+                2 => "cut_red_sandstone",
+                _ => "red_sandstone",
+            }
+        }
+        180 => "red_sandstone_stairs",
+        181 | 182 => "red_sandstone_slab",
+        183 => "spruce_fence_gate",
+        184 => "birch_fence_gate",
+        185 => "jungle_fence_gate",
+        186 => "dark_oak_fence_gate",
+        187 => "acacia_fence_gate",
+        188 => "spruce_fence",
+        189 => "birch_fence",
+        190 => "jungle_fence",
+        191 => "dark_oak_fence",
+        192 => "acacia_fence",
+        193 => "spruce_door",
+        194 => "birch_door",
+        195 => "jungle_door",
+        196 => "acacia_door",
+        197 => "dark_oak_door",
+        198 => "end_rod",
+        199 => "chorus_plant",
+        200 => "chorus_flower",
+        201 => "purpur_block",
+        202 => "purpur_pillar",
+        203 => "purpur_stairs",
+        204 | 205 => "purpur_slab",
+        206 => "end_stone_bricks",
+        207 => "beetroots",
+        208 => "dirt_path",
+        209 => "end_gateway",
+        210 => "repeating_command_block",
+        211 => "chain_command_block",
+        212 => "frosted_ice",
+        213 => "magma_block",
+        214 => "nether_wart_block",
+        215 => "red_nether_bricks",
+        216 => "bone_block",
+        217 => "structure_void",
+        218 => "observer",
+        219...234 => return format!("{}_shulker_box", COLORS[(id - 219) as usize]).into(),
+        235...250 => return format!("{}_glazed_terracotta", COLORS[(id - 235) as usize]).into(),
+        251 => return format!("{}_concrete", color).into(),
+        252 => return format!("{}_concrete_powder", color).into(),
+        255 => "structure_block",
+        _ => return format!("legacy_{}", id).into(),
+    };
+    name.into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_legacy_names() {
+        assert_eq!(BlockState::from_legacy(0, 0).name, "minecraft:air");
+        assert_eq!(BlockState::from_legacy(1, 3).name, "minecraft:diorite");
+        assert_eq!(BlockState::from_legacy(35, 14).name, "minecraft:red_wool");
+        assert_eq!(BlockState::from_legacy(162, 1).name, "minecraft:dark_oak_log");
+        assert_eq!(BlockState::from_legacy(185, 0).name, "minecraft:jungle_fence_gate");
+        assert_eq!(BlockState::from_legacy(250, 0).name, "minecraft:black_glazed_terracotta");
+        assert_eq!(BlockState::from_legacy(253, 0).name, "minecraft:legacy_253");
+    }
+
+    #[test]
+    fn test_opacity() {
+        assert!(BlockState::new("stone").is_opaque());
+        assert!(BlockState::new("minecraft:oak_planks").is_opaque());
+        assert!(!BlockState::air().is_opaque());
+        assert!(!BlockState::new("minecraft:oak_leaves").is_opaque());
+        assert!(!BlockState::new("minecraft:red_stained_glass_pane").is_opaque());
+        assert!(!BlockState::new("minecraft:stone_slab").is_opaque());
+        assert!(!BlockState::new("minecraft:water").is_opaque());
+        assert_eq!(BlockState::new("minecraft:water").short_name(), "water");
+    }
+}
@@ -0,0 +1,643 @@
+//! The render configuration file
+//!
+//! A config file is TOML, naming a set of worlds and any number of renders of them:
+//!
+//! ```toml
+//! output_dir = "/srv/map"
+//!
+//! [worlds]
+//! survival = "/srv/minecraft/world"
+//! backup = "/srv/backups/world"
+//!
+//! [renders.day]
+//! world = "survival"
+//! title = "Daytime"
+//! render_mode = "smooth_lighting"
+//! slime_chunks = true
+//! mob_spawns = true
+//! diff_against = "backup"
+//!
+//! [renders.nether]
+//! world = "survival"
+//! dimension = "nether"
+//! north_direction = "lower-right"
+//! crop = [-1000, -1000, 1000, 1000]
+//! image_format = "jpg"
+//! image_quality = 90
+//! markers = ["homes"]
+//! maps = true
+//! overlays = ["inhabited_time"]
+//!
+//! [markers.homes]
+//! title = "Homes"
+//! ids = ["*sign"]
+//! text_prefix = "[home]"
+//! ```
+//!
+//! Every key of a render except `world` is optional.  A top-level `texture_pack` is used by any
+//! render that doesn't name its own.  Relative paths, for worlds, `output_dir` and texture packs
+//! alike, are relative to the config file.  Unknown keys are an error, so that typos don't go
+//! unnoticed.
+//!
+//! Renders list the marker groups they want by name; see [`MarkerFilter`] for what each key of a
+//! marker group means.  `maps = true` lays the world's in-game maps over a render.
+//! `overlays` lists the [`Activity`] overlays to build alongside a render, which can be
+//! `"last_modified"` or `"inhabited_time"`.  `slime_chunks = true` tints the chunks slimes can
+//! spawn in, which only exist in the overworld.  `mob_spawns = true` highlights blocks that are
+//! dark enough for hostile mobs to spawn on at night.  `diff_against` names an older copy of the
+//! world, and adds an overlay of what has changed since.
+//!
+//! `render_mode = "cave"` can tint caves by depth, from red to blue, with
+//! `cave_depth_tint = [min_y, max_y]`.  `render_mode = "cross_section"` slices the world off at
+//! `cross_section_y`, which is sea level if not set, and also below `cross_section_min_y` if that
+//! is set.  The top of the cut is highlighted in `cross_section_highlight`, a `"#rrggbb"` color
+//! that defaults to red, or `false` for no highlight.
+//!
+//! [`Activity`]: ../activity/enum.Activity.html
+//! [`MarkerFilter`]: ../poi/struct.MarkerFilter.html
+
+use std::collections::BTreeMap;
+use std::io::{Read, Seek};
+use rio;
+use toml::Value;
+
+use activity::Activity;
+use error::OverviewerError;
+use heightmap::CropBox;
+use poi::MarkerFilter;
+use render::{parse_color, ImageFormat, NorthDirection, RenderMode};
+use world::Dimension;
+
+/// A whole config file
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Config {
+    pub output_dir: String,
+
+    /// World paths, by name
+    pub worlds: BTreeMap<String, String>,
+
+    /// Renders, sorted by name
+    pub renders: Vec<RenderConfig>,
+}
+
+/// One render of one world
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct RenderConfig {
+    pub name: String,
+
+    /// Human readable name, which defaults to `name`
+    pub title: String,
+
+    /// The name of a world in [`Config::worlds`]
+    ///
+    /// [`Config::worlds`]: struct.Config.html#structfield.worlds
+    pub world: String,
+    pub dimension: Dimension,
+    pub render_mode: RenderMode,
+    pub north_direction: NorthDirection,
+    pub crop: Option<CropBox>,
+    pub image_format: ImageFormat,
+
+    /// How many zoom levels to build.  If not set, just enough to fit the whole world.
+    pub zoom_levels: Option<u32>,
+    pub texture_pack: Option<String>,
+    pub pixels_per_block: u32,
+
+    /// The marker groups to show on this render
+    pub markers: Vec<MarkerFilter>,
+
+    /// Whether to lay the world's in-game map items over the render
+    pub maps: bool,
+
+    /// Activity overlays to draw over the render, which the viewer can turn on and off
+    pub overlays: Vec<Activity>,
+
+    /// The name of an older copy of the world in [`Config::worlds`], to draw an overlay of what
+    /// has changed since
+    ///
+    /// [`Config::worlds`]: struct.Config.html#structfield.worlds
+    pub diff_against: Option<String>,
+
+    /// Whether to tint slime chunks, using the seed from the world's `level.dat`
+    pub slime_chunks: bool,
+
+    /// Whether to highlight blocks that hostile mobs can spawn on
+    pub mob_spawns: bool,
+}
+
+const CONFIG_KEYS: &'static [&'static str] =
+    &["output_dir", "texture_pack", "worlds", "renders", "markers"];
+const MARKER_KEYS: &'static [&'static str] =
+    &["title", "ids", "text_prefix", "text_contains", "icon"];
+const RENDER_KEYS: &'static [&'static str] = &["world",
+                                               "title",
+                                               "dimension",
+                                               "render_mode",
+                                               "cave_depth_tint",
+                                               "cross_section_y",
+                                               "cross_section_min_y",
+                                               "cross_section_highlight",
+                                               "north_direction",
+                                               "crop",
+                                               "image_format",
+                                               "image_quality",
+                                               "zoom_levels",
+                                               "texture_pack",
+                                               "pixels_per_block",
+                                               "markers",
+                                               "maps",
+                                               "overlays",
+                                               "diff_against",
+                                               "slime_chunks",
+                                               "mob_spawns"];
+
+// render keys that only mean something to one render mode, and that mode
+const MODE_KEYS: &'static [(&'static str, &'static str)] =
+    &[("cave_depth_tint", "cave"),
+      ("cross_section_y", "cross_section"),
+      ("cross_section_min_y", "cross_section"),
+      ("cross_section_highlight", "cross_section")];
+
+impl Config {
+    /// Parses the text of a config file
+    pub fn parse(text: &str) -> Result<Config, OverviewerError> {
+        let root = try!(text.parse::<Value>());
+        let root = try!(root.as_table().ok_or("Config must be a table"));
+        try!(check_keys(root, CONFIG_KEYS, "the config file"));
+
+        let output_dir = try!(get_str(root, "output_dir", "the config file"));
+        let output_dir = try!(output_dir.ok_or("output_dir is required")).to_owned();
+        let texture_pack = try!(get_str(root, "texture_pack", "the config file"));
+
+        let mut worlds = BTreeMap::new();
+        if let Some(table) = root.get("worlds") {
+            let table = try!(table.as_table().ok_or("worlds must be a table"));
+            for (name, path) in table {
+                let path = try!(path.as_str()
+                    .ok_or_else(|| format!("The path of world {:?} must be a string", name)));
+                worlds.insert(name.clone(), path.to_owned());
+            }
+        }
+
+        let mut markers = BTreeMap::new();
+        if let Some(table) = root.get("markers") {
+            let table = try!(table.as_table().ok_or("markers must be a table"));
+            for (name, filter) in table {
+                let filter = try!(filter.as_table()
+                    .ok_or_else(|| format!("Marker group {:?} must be a table", name)));
+                markers.insert(name.clone(), try!(marker_filter(name, filter)));
+            }
+        }
+
+        let mut renders = Vec::new();
+        if let Some(table) = root.get("renders") {
+            let table = try!(table.as_table().ok_or("renders must be a table"));
+            for (name, render) in table {
+                let render = try!(render.as_table()
+                    .ok_or_else(|| format!("Render {:?} must be a table", name)));
+                let mut render = try!(RenderConfig::from_table(name, render, &markers));
+                if !worlds.contains_key(&render.world) {
+                    return Err(format!("Render {:?} uses world {:?}, which isn't in [worlds]",
+                                       name,
+                                       render.world)
+                        .into());
+                }
+                if let Some(ref old) = render.diff_against {
+                    if !worlds.contains_key(old) {
+                        return Err(format!("Render {:?} compares against world {:?}, which \
+                                            isn't in [worlds]",
+                                           name,
+                                           old)
+                            .into());
+                    }
+                }
+                if render.texture_pack.is_none() {
+                    render.texture_pack = texture_pack.map(|s| s.to_owned());
+                }
+                renders.push(render);
+            }
+        }
+        if renders.is_empty() {
+            return Err("The config file doesn't have any renders".into());
+        }
+
+        Ok(Config {
+            output_dir: output_dir,
+            worlds: worlds,
+            renders: renders,
+        })
+    }
+
+    /// Reads and parses a config file
+    pub fn load<'fs, FS, P>(fs: &'fs FS, p: P) -> Result<Config, OverviewerError>
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read + Seek,
+              P: AsRef<rio::Path>
+    {
+        let mut text = String::new();
+        try!(try!(fs.open(p)).read_to_string(&mut text));
+        Config::parse(&text)
+    }
+}
+
+impl RenderConfig {
+    fn from_table(name: &str,
+                  table: &BTreeMap<String, Value>,
+                  markers: &BTreeMap<String, MarkerFilter>)
+                  -> Result<RenderConfig, OverviewerError> {
+        let what = format!("render {:?}", name);
+        try!(check_keys(table, RENDER_KEYS, &what));
+
+        let world = try!(try!(get_str(table, "world", &what))
+            .ok_or_else(|| format!("Render {:?} doesn't say which world to render", name)));
+        let title = try!(get_str(table, "title", &what)).unwrap_or(name);
+        let dimension = try!(get_named(table, "dimension", &what, Dimension::from_name));
+        let render_mode = try!(get_named(table, "render_mode", &what, RenderMode::from_name));
+        let render_mode =
+            try!(mode_options(table, &what, render_mode.unwrap_or(RenderMode::Normal)));
+        let north_direction =
+            try!(get_named(table, "north_direction", &what, NorthDirection::from_name));
+        let quality = try!(get_int(table, "image_quality", &what, 1, 100));
+        let image_format = try!(get_named(table, "image_format", &what, |s| match s {
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg(quality.unwrap_or(95) as u8)),
+            _ => None,
+        }))
+            .unwrap_or(ImageFormat::Png);
+        if quality.is_some() && image_format == ImageFormat::Png {
+            return Err(format!("image_quality in {} only applies to image_format = \"jpg\"",
+                               what)
+                .into());
+        }
+
+        let mut filters = Vec::new();
+        for marker in try!(get_str_list(table, "markers", &what)).unwrap_or_else(Vec::new) {
+            filters.push(try!(markers.get(&marker)
+                .cloned()
+                .ok_or_else(|| format!("Unknown marker group {:?} in {}", marker, what))));
+        }
+
+        let mut overlays = Vec::new();
+        for overlay in try!(get_str_list(table, "overlays", &what)).unwrap_or_default() {
+            overlays.push(try!(Activity::from_name(&overlay)
+                .ok_or_else(|| format!("Unknown overlay {:?} in {}", overlay, what))));
+        }
+
+        let crop = match try!(get_int_list(table, "crop", &what)) {
+            Some(corners) => {
+                if corners.len() != 4 {
+                    return Err(format!("crop in {} must be four integers, [x1, z1, x2, z2]", what)
+                        .into());
+                }
+                Some(CropBox::new(corners[0], corners[1], corners[2], corners[3]))
+            }
+            None => None,
+        };
+
+        Ok(RenderConfig {
+            name: name.to_owned(),
+            title: title.to_owned(),
+            world: world.to_owned(),
+            dimension: dimension.unwrap_or(Dimension::Overworld),
+            render_mode: render_mode,
+            north_direction: north_direction.unwrap_or(NorthDirection::UpperLeft),
+            crop: crop,
+            image_format: image_format,
+            zoom_levels: try!(get_int(table, "zoom_levels", &what, 0, 32)).map(|z| z as u32),
+            texture_pack: try!(get_str(table, "texture_pack", &what)).map(|s| s.to_owned()),
+            pixels_per_block: try!(get_int(table, "pixels_per_block", &what, 1, 64))
+                .unwrap_or(1) as u32,
+            markers: filters,
+            maps: try!(get_bool(table, "maps", &what)).unwrap_or(false),
+            overlays: overlays,
+            diff_against: try!(get_str(table, "diff_against", &what)).map(|s| s.to_owned()),
+            slime_chunks: try!(get_bool(table, "slime_chunks", &what)).unwrap_or(false),
+            mob_spawns: try!(get_bool(table, "mob_spawns", &what)).unwrap_or(false),
+        })
+    }
+}
+
+// applies the keys that only go with one render mode, and rejects them for any other mode
+fn mode_options(table: &BTreeMap<String, Value>,
+                what: &str,
+                mut mode: RenderMode)
+                -> Result<RenderMode, OverviewerError> {
+    let allowed: &[&str] = match mode {
+        RenderMode::Cave { ref mut depth_tinting } => {
+            if let Some(range) = try!(get_int_list(table, "cave_depth_tint", what)) {
+                if range.len() != 2 || range[0] >= range[1] {
+                    return Err(format!("cave_depth_tint in {} must be two Y levels, [min_y, \
+                                        max_y], with min_y below max_y",
+                                       what)
+                        .into());
+                }
+                *depth_tinting = Some((range[0], range[1]));
+            }
+            &["cave_depth_tint"]
+        }
+        RenderMode::CrossSection { ref mut max_y, ref mut min_y, ref mut highlight } => {
+            if let Some(y) = try!(get_int(table, "cross_section_y", what, -2048, 2047)) {
+                *max_y = y;
+            }
+            if let Some(y) = try!(get_int(table, "cross_section_min_y", what, -2048, 2047)) {
+                if y > *max_y {
+                    return Err(format!("cross_section_min_y in {} is above cross_section_y",
+                                       what)
+                        .into());
+                }
+                *min_y = Some(y);
+            }
+            match table.get("cross_section_highlight") {
+                Some(&Value::Boolean(false)) => *highlight = None,
+                Some(v) => {
+                    *highlight = Some(try!(v.as_str().and_then(parse_color).ok_or_else(|| {
+                        format!("cross_section_highlight in {} must be a \"#rrggbb\" color or \
+                                 false",
+                                what)
+                    })))
+                }
+                None => {}
+            }
+            &["cross_section_y", "cross_section_min_y", "cross_section_highlight"]
+        }
+        _ => &[],
+    };
+
+    for &(key, mode_name) in MODE_KEYS {
+        if table.contains_key(key) && !allowed.contains(&key) {
+            return Err(format!("{} in {} needs render_mode = {:?}", key, what, mode_name).into());
+        }
+    }
+    Ok(mode)
+}
+
+fn marker_filter(name: &str, table: &BTreeMap<String, Value>) -> Result<MarkerFilter, OverviewerError> {
+    let what = format!("marker group {:?}", name);
+    try!(check_keys(table, MARKER_KEYS, &what));
+
+    let ids = try!(try!(get_str_list(table, "ids", &what))
+        .ok_or_else(|| format!("Marker group {:?} doesn't list any ids", name)));
+    let mut filter = MarkerFilter::new(name, ids);
+    if let Some(title) = try!(get_str(table, "title", &what)) {
+        filter.title = title.to_owned();
+    }
+    filter.text_prefix = try!(get_str(table, "text_prefix", &what)).map(|s| s.to_owned());
+    filter.text_contains = try!(get_str(table, "text_contains", &what)).map(|s| s.to_owned());
+    filter.icon = try!(get_str(table, "icon", &what)).map(|s| s.to_owned());
+    Ok(filter)
+}
+
+fn check_keys(table: &BTreeMap<String, Value>,
+              allowed: &[&str],
+              what: &str)
+              -> Result<(), OverviewerError> {
+    for key in table.keys() {
+        if !allowed.contains(&&key[..]) {
+            return Err(format!("Unknown key {:?} in {}", key, what).into());
+        }
+    }
+    Ok(())
+}
+
+fn get_str<'a>(table: &'a BTreeMap<String, Value>,
+               key: &str,
+               what: &str)
+               -> Result<Option<&'a str>, OverviewerError> {
+    match table.get(key) {
+        Some(v) => {
+            v.as_str()
+                .map(Some)
+                .ok_or_else(|| format!("{} in {} must be a string", key, what).into())
+        }
+        None => Ok(None),
+    }
+}
+
+fn get_str_list(table: &BTreeMap<String, Value>,
+                key: &str,
+                what: &str)
+                -> Result<Option<Vec<String>>, OverviewerError> {
+    match table.get(key) {
+        Some(v) => {
+            let list = v.as_array()
+                .and_then(|a| a.iter().map(|s| s.as_str().map(|s| s.to_owned())).collect());
+            list.map(Some)
+                .ok_or_else(|| format!("{} in {} must be a list of strings", key, what).into())
+        }
+        None => Ok(None),
+    }
+}
+
+fn get_int_list(table: &BTreeMap<String, Value>,
+                key: &str,
+                what: &str)
+                -> Result<Option<Vec<i64>>, OverviewerError> {
+    match table.get(key) {
+        Some(v) => {
+            let list = v.as_array().and_then(|a| a.iter().map(|i| i.as_integer()).collect());
+            list.map(Some)
+                .ok_or_else(|| format!("{} in {} must be a list of integers", key, what).into())
+        }
+        None => Ok(None),
+    }
+}
+
+fn get_bool(table: &BTreeMap<String, Value>,
+            key: &str,
+            what: &str)
+            -> Result<Option<bool>, OverviewerError> {
+    match table.get(key) {
+        Some(v) => {
+            v.as_bool()
+                .map(Some)
+                .ok_or_else(|| format!("{} in {} must be true or false", key, what).into())
+        }
+        None => Ok(None),
+    }
+}
+
+fn get_int(table: &BTreeMap<String, Value>,
+           key: &str,
+           what: &str,
+           min: i64,
+           max: i64)
+           -> Result<Option<i64>, OverviewerError> {
+    match table.get(key).map(|v| v.as_integer()) {
+        Some(Some(i)) if i >= min && i <= max => Ok(Some(i)),
+        Some(_) => {
+            Err(format!("{} in {} must be a number from {} to {}", key, what, min, max).into())
+        }
+        None => Ok(None),
+    }
+}
+
+// looks up a string and converts it with `parse`, which returns None for names it doesn't know
+fn get_named<T, F>(table: &BTreeMap<String, Value>,
+                   key: &str,
+                   what: &str,
+                   parse: F)
+                   -> Result<Option<T>, OverviewerError>
+    where F: Fn(&str) -> Option<T>
+{
+    match try!(get_str(table, key, what)) {
+        Some(name) => {
+            parse(name)
+                .map(Some)
+                .ok_or_else(|| format!("Unknown {} {:?} in {}", key, name, what).into())
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use activity::Activity;
+    use heightmap::CropBox;
+    use render::{ImageFormat, NorthDirection, RenderMode};
+    use world::Dimension;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(r##"
+            output_dir = "/srv/map"
+            texture_pack = "/srv/client"
+
+            [worlds]
+            survival = "/srv/minecraft/world"
+            backup = "/srv/backups/world"
+
+            [renders.day]
+            world = "survival"
+            title = "Daytime"
+            render_mode = "smooth_lighting"
+            diff_against = "backup"
+
+            [renders.nether]
+            world = "survival"
+            dimension = "nether"
+            north_direction = "lower-right"
+            crop = [1000, -1000, -1000, 1000]
+            image_format = "jpg"
+            image_quality = 90
+            zoom_levels = 6
+            texture_pack = "/srv/other"
+            markers = ["homes"]
+            maps = true
+            overlays = ["last_modified", "inhabited_time"]
+            slime_chunks = true
+            mob_spawns = true
+
+            [renders.slice]
+            world = "survival"
+            render_mode = "cross_section"
+            cross_section_y = 12
+            cross_section_min_y = -20
+            cross_section_highlight = "#00ff80"
+
+            [renders.sea]
+            world = "survival"
+            render_mode = "cross_section"
+            cross_section_highlight = false
+
+            [renders.caves]
+            world = "survival"
+            render_mode = "cave"
+            cave_depth_tint = [-64, 62]
+
+            [markers.homes]
+            title = "Homes"
+            ids = ["*sign"]
+            text_prefix = "[home]"
+        "##)
+            .unwrap();
+
+        assert_eq!(config.output_dir, "/srv/map");
+        assert_eq!(config.worlds["survival"], "/srv/minecraft/world");
+        assert_eq!(config.renders.len(), 5);
+
+        assert_eq!(config.renders[0].render_mode,
+                   RenderMode::Cave { depth_tinting: Some((-64, 62)) });
+        let day = &config.renders[1];
+        assert_eq!(day.name, "day");
+        assert_eq!(day.title, "Daytime");
+        assert_eq!(day.dimension, Dimension::Overworld);
+        assert_eq!(day.render_mode, RenderMode::SmoothLighting);
+        assert_eq!(day.image_format, ImageFormat::Png);
+        assert_eq!(day.texture_pack, Some("/srv/client".to_owned()));
+
+        let nether = &config.renders[2];
+        assert_eq!(nether.title, "nether");
+        assert_eq!(nether.dimension, Dimension::Nether);
+        assert_eq!(nether.north_direction, NorthDirection::LowerRight);
+        assert_eq!(nether.crop, Some(CropBox::new(-1000, -1000, 1000, 1000)));
+        assert_eq!(nether.image_format, ImageFormat::Jpeg(90));
+        assert_eq!(nether.zoom_levels, Some(6));
+        assert_eq!(nether.texture_pack, Some("/srv/other".to_owned()));
+        assert_eq!(nether.markers.len(), 1);
+        assert_eq!(nether.markers[0].title, "Homes");
+        assert_eq!(nether.markers[0].ids, vec!["*sign".to_owned()]);
+        assert_eq!(nether.markers[0].text_prefix, Some("[home]".to_owned()));
+        assert!(day.markers.is_empty());
+        assert!(nether.maps);
+        assert!(!day.maps);
+        assert_eq!(nether.overlays, vec![Activity::LastModified, Activity::InhabitedTime]);
+        assert!(day.overlays.is_empty());
+        assert!(nether.slime_chunks);
+        assert!(!day.slime_chunks);
+        assert!(nether.mob_spawns);
+        assert!(!day.mob_spawns);
+        assert_eq!(day.diff_against, Some("backup".to_owned()));
+        assert_eq!(nether.diff_against, None);
+
+        assert_eq!(config.renders[4].render_mode,
+                   RenderMode::CrossSection {
+                       max_y: 12,
+                       min_y: Some(-20),
+                       highlight: Some(0x00ff80),
+                   });
+        assert_eq!(config.renders[3].render_mode,
+                   RenderMode::CrossSection {
+                       max_y: 62,
+                       min_y: None,
+                       highlight: None,
+                   });
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let base = "output_dir = \"out\"\n[worlds]\nw = \"world\"\n[renders.r]\n";
+        assert!(Config::parse(&format!("{}world = \"w\"", base)).is_ok());
+        // unknown world
+        assert!(Config::parse(&format!("{}world = \"x\"", base)).is_err());
+        assert!(Config::parse(&format!("{}world = \"w\"\ndiff_against = \"x\"", base)).is_err());
+        // typo in a key
+        assert!(Config::parse(&format!("{}world = \"w\"\nrendermode = \"night\"", base)).is_err());
+        // bad value
+        assert!(Config::parse(&format!("{}world = \"w\"\nrender_mode = \"bright\"", base)).is_err());
+        assert!(Config::parse(&format!("{}world = \"w\"\ncrop = [1, 2, 3]", base)).is_err());
+        assert!(Config::parse(&format!("{}world = \"w\"\ncrop = [1, 2, \"x\", 3, 4]", base))
+            .is_err());
+        assert!(Config::parse(&format!("{}world = \"w\"\nimage_quality = 80", base)).is_err());
+        let png = format!("{}world = \"w\"\nimage_format = \"png\"\n", base);
+        assert!(Config::parse(&format!("{}image_quality = 80", png)).is_err());
+        assert!(Config::parse(&format!("{}world = \"w\"\nmaps = \"yes\"", base)).is_err());
+        assert!(Config::parse(&format!("{}world = \"w\"\nmarkers = [\"none\"]", base)).is_err());
+        assert!(Config::parse(&format!("{}world = \"w\"\noverlays = [\"slime\"]", base)).is_err());
+        // cross section keys without the mode, or out of order
+        assert!(Config::parse(&format!("{}world = \"w\"\ncross_section_y = 40", base)).is_err());
+        let slice = format!("{}world = \"w\"\nrender_mode = \"cross_section\"\n", base);
+        assert!(Config::parse(&format!("{}cross_section_min_y = 70", slice)).is_err());
+        assert!(Config::parse(&format!("{}cross_section_highlight = \"red\"", slice)).is_err());
+        assert!(Config::parse(&format!("{}cross_section_highlight = true", slice)).is_err());
+        assert!(Config::parse(&format!("{}cave_depth_tint = [0, 60]", slice)).is_err());
+        let caves = format!("{}world = \"w\"\nrender_mode = \"cave\"\n", base);
+        assert!(Config::parse(&format!("{}cave_depth_tint = [0, 60]", caves)).is_ok());
+        assert!(Config::parse(&format!("{}cave_depth_tint = [60, 0]", caves)).is_err());
+        assert!(Config::parse(&format!("{}cave_depth_tint = [0, 30, 60]", caves)).is_err());
+        // not TOML at all
+        assert!(Config::parse("[[[").is_err());
+    }
+}
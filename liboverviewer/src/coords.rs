@@ -149,8 +149,13 @@ impl<M, N: Contained<M>> Contained<Succ<M>> for Succ<N> {}
 ///
 /// The best way to construct a `Coord` is to use the [`coord!`] macro.
 ///
+/// With the `serde-serialize` feature enabled, a `Coord` serializes as a plain `{x, y, z}` map.
+/// The coordinate type and system are not recorded, so the reader must know what it expects.
+///
 /// [`coord!`]: ../macro.coord!.html
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(bound = ""))]
 pub struct Coord<El, In> {
     /// Positive X faces east
     pub x: i64,
@@ -160,6 +165,7 @@ pub struct Coord<El, In> {
 
     /// Positive Z faces south
     pub z: i64,
+    #[cfg_attr(feature = "serde-serialize", serde(skip))]
     phantom: PhantomData<(El, In)>,
 }
 
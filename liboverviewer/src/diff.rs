@@ -0,0 +1,379 @@
+//! Comparing two copies of the same world
+//!
+//! Region files record when each chunk was last saved, so only chunks whose timestamps differ
+//! need to be read.  Those are then compared block by block, and block entity by block entity so
+//! that an emptied chest or a rewritten sign counts, since Minecraft resaves chunks for all sorts
+//! of reasons (mobs wandering about, crops growing) that aren't interesting when looking for what
+//! players did.
+//!
+//! [`diff_regionsets`] finds the changes, and a [`DiffOverlay`] draws them as tiles that line up
+//! with those of a [`TopDown`] render, for the web viewer to lay over it.
+//!
+//! [`diff_regionsets`]: fn.diff_regionsets.html
+//! [`DiffOverlay`]: struct.DiffOverlay.html
+//! [`TopDown`]: ../render/struct.TopDown.html
+
+use image::{ImageBuffer, Rgba};
+use rio;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Read, Seek};
+
+use asset_manager::Texture;
+use block_entity::BlockEntity;
+use blocks::BlockState;
+use coords::{self, Coord};
+use error::OverviewerError;
+use render::OverlayLayout;
+use render::topdown::TILE_BLOCKS;
+use world::{Chunk, Regionset};
+
+/// How a chunk differs between the old world and the new one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Only in the new world, so it has been generated since
+    Added,
+
+    /// Only in the old world, so it has been deleted or pruned since
+    Removed,
+
+    /// In both worlds, with different blocks or block entities
+    Modified,
+}
+
+impl ChangeKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+/// One changed chunk
+#[derive(Debug, Clone)]
+pub struct ChunkDiff {
+    pub pos: Coord<coords::Chunk, coords::World>,
+    pub kind: ChangeKind,
+
+    /// Every block that differs, or whose block entity does, for modified chunks.  Empty for added
+    /// and removed ones.
+    pub blocks: Vec<Coord<coords::Block, coords::World>>,
+}
+
+impl ChunkDiff {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "x": self.pos.x,
+            "z": self.pos.z,
+            "change": self.kind.name(),
+            "blocks": self.blocks.len(),
+        })
+    }
+}
+
+/// Every chunk that differs between `old` and `new`, sorted by position
+///
+/// Chunks saved at the same time in both are assumed to be the same without reading them, and
+/// chunks that have been saved again without any blocks or block entities changing are left out.
+pub fn diff_regionsets<'a, 'b, FA, FB>(old: &Regionset<'a, FA>,
+                                       new: &Regionset<'b, FB>)
+                                       -> Vec<ChunkDiff>
+    where FA: rio::FSRead<'a>,
+          FA::ReadFile: Read + Seek,
+          FB: rio::FSRead<'b>,
+          FB::ReadFile: Read + Seek
+{
+    let old_times: HashMap<(i64, i64), u32> =
+        old.get_chunks().map(|info| ((info.pos.x, info.pos.z), info.mtime)).collect();
+    let new_times: HashMap<(i64, i64), u32> =
+        new.get_chunks().map(|info| ((info.pos.x, info.pos.z), info.mtime)).collect();
+
+    let mut diffs = Vec::new();
+    for (&(x, z), mtime) in &new_times {
+        let pos = Coord::new(x, 0, z);
+        let old_mtime = match old_times.get(&(x, z)) {
+            Some(old_mtime) => old_mtime,
+            None => {
+                diffs.push(ChunkDiff {
+                    pos: pos,
+                    kind: ChangeKind::Added,
+                    blocks: Vec::new(),
+                });
+                continue;
+            }
+        };
+        if old_mtime == mtime {
+            continue;
+        }
+        if let (Some(a), Some(b)) = (old.get_chunk(pos), new.get_chunk(pos)) {
+            let blocks = diff_chunks(pos, &a, &b);
+            if !blocks.is_empty() {
+                diffs.push(ChunkDiff {
+                    pos: pos,
+                    kind: ChangeKind::Modified,
+                    blocks: blocks,
+                });
+            }
+        }
+    }
+    for &(x, z) in old_times.keys() {
+        if !new_times.contains_key(&(x, z)) {
+            diffs.push(ChunkDiff {
+                pos: Coord::new(x, 0, z),
+                kind: ChangeKind::Removed,
+                blocks: Vec::new(),
+            });
+        }
+    }
+
+    diffs.sort_by_key(|d| (d.pos.x, d.pos.z));
+    diffs
+}
+
+/// The positions of every block that differs between two versions of the chunk at `pos`, either
+/// in its state or in its block entity
+pub fn diff_chunks(pos: Coord<coords::Chunk, coords::World>,
+                   old: &Chunk,
+                   new: &Chunk)
+                   -> Vec<Coord<coords::Block, coords::World>> {
+    let mut changed = diff_blocks(pos, old, new);
+
+    let old = block_entities(old);
+    let new = block_entities(new);
+    let mut entities: Vec<&(i64, i64, i64)> =
+        old.keys().chain(new.keys()).filter(|p| old.get(p) != new.get(p)).collect();
+    entities.sort();
+    entities.dedup();
+    for &(x, y, z) in entities {
+        // the block itself may have changed too
+        if !changed.iter().any(|b| (b.x, b.y, b.z) == (x, y, z)) {
+            changed.push(Coord::new(x, y, z));
+        }
+    }
+    changed
+}
+
+fn diff_blocks(pos: Coord<coords::Chunk, coords::World>,
+               old: &Chunk,
+               new: &Chunk)
+               -> Vec<Coord<coords::Block, coords::World>> {
+    let old = section_blocks(old);
+    let new = section_blocks(new);
+    let air = vec![BlockState::air(); 4096];
+
+    // a section missing from one side is all air
+    let mut ys: Vec<i64> = old.keys().chain(new.keys()).cloned().collect();
+    ys.sort();
+    ys.dedup();
+
+    let mut changed = Vec::new();
+    for y in ys {
+        let a = old.get(&y).unwrap_or(&air);
+        let b = new.get(&y).unwrap_or(&air);
+        for index in 0..4096 {
+            if a[index] != b[index] {
+                let (bx, by, bz) = (index & 15, index >> 8, (index >> 4) & 15);
+                let in_chunk = Coord::<coords::Block, coords::Chunk>::new(bx as i64,
+                                                                         y * 16 + by as i64,
+                                                                         bz as i64);
+                changed.push(in_chunk.join(pos));
+            }
+        }
+    }
+    changed
+}
+
+// every section's blocks, by section Y, in YZX order
+fn section_blocks(chunk: &Chunk) -> BTreeMap<i64, Vec<BlockState>> {
+    let mut sections = BTreeMap::new();
+    chunk.for_each_block(|pos, block| {
+        let section = sections.entry(pos.y >> 4).or_insert_with(|| vec![BlockState::air(); 4096]);
+        section[((pos.y & 15) << 8 | pos.z << 4 | pos.x) as usize] = block.clone();
+    });
+    sections
+}
+
+fn block_entities(chunk: &Chunk) -> HashMap<(i64, i64, i64), BlockEntity> {
+    chunk.block_entities().into_iter().map(|b| ((b.x, b.y, b.z), b)).collect()
+}
+
+/// Draws the changes found by [`diff_regionsets`] for one regionset
+///
+/// Added chunks are tinted green and removed ones grey.  In modified chunks, columns with a
+/// changed block are red, and the rest of the chunk is tinted yellow.
+///
+/// [`diff_regionsets`]: fn.diff_regionsets.html
+pub struct DiffOverlay {
+    pub layout: OverlayLayout,
+
+    // how each changed chunk changed, by chunk position, and the columns with a changed block
+    chunks: HashMap<(i64, i64), ChangeKind>,
+    columns: HashSet<(i64, i64)>,
+}
+
+impl DiffOverlay {
+    pub fn new(diffs: &[ChunkDiff]) -> DiffOverlay {
+        DiffOverlay {
+            layout: OverlayLayout::new(),
+            chunks: diffs.iter().map(|d| ((d.pos.x, d.pos.z), d.kind)).collect(),
+            columns: diffs.iter().flat_map(|d| d.blocks.iter().map(|b| (b.x, b.z))).collect(),
+        }
+    }
+
+    /// The overlay color for a block column
+    pub fn color(&self, x: i64, z: i64) -> Rgba<u8> {
+        if !self.layout.shows(x, z) {
+            return Rgba([0, 0, 0, 0]);
+        }
+        match self.chunks.get(&(x >> 4, z >> 4)) {
+            Some(&ChangeKind::Added) => Rgba([0, 200, 0, 96]),
+            Some(&ChangeKind::Removed) => Rgba([128, 128, 128, 128]),
+            Some(&ChangeKind::Modified) if self.columns.contains(&(x, z)) => {
+                Rgba([255, 0, 0, 224])
+            }
+            Some(&ChangeKind::Modified) => Rgba([255, 220, 0, 64]),
+            None => Rgba([0, 0, 0, 0]),
+        }
+    }
+
+    /// Draws a single region, unrotated
+    pub fn render_region(&self, region: Coord<coords::Region, coords::World>) -> Texture {
+        let n = self.layout.pixels_per_block;
+        let size = TILE_BLOCKS as u32 * n;
+        let x0 = region.x * TILE_BLOCKS;
+        let z0 = region.z * TILE_BLOCKS;
+        ImageBuffer::from_fn(size, size, |px, py| {
+            self.color(x0 + (px / n) as i64, z0 + (py / n) as i64)
+        })
+    }
+
+    /// Writes a PNG tile for every region with a changed chunk into `out_dir`, as
+    /// [`OverlayLayout::write_tiles`] does.  Returns the coordinates of the tiles written.
+    ///
+    /// [`OverlayLayout::write_tiles`]: ../render/struct.OverlayLayout.html#method.write_tiles
+    pub fn render_tiles<W, P>(&self,
+                              out: &W,
+                              out_dir: P)
+                              -> Result<Vec<(i64, i64)>, OverviewerError>
+        where W: rio::FSWrite,
+              P: AsRef<rio::Path>
+    {
+        // shifting floors, so negative chunks find the right region
+        let regions = self.chunks.keys().map(|&(x, z)| (x >> 5, z >> 5));
+        self.layout.write_tiles(out, out_dir, regions, |region| self.render_region(region))
+    }
+}
+
+/// Describes `diffs`
+pub fn diff_json(diffs: &[ChunkDiff]) -> Value {
+    json!({ "chunks": diffs.iter().map(|d| d.to_json()).collect::<Vec<_>>() })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blocks::BlockState;
+    use fixtures::{TempWorld, WorldBuilder};
+    use image::Rgba;
+    use nbt::compound;
+    use nbtrs::Tag;
+    use render::NorthDirection;
+    use rio::FSRead;
+    use world::{Dimension, Regionset};
+
+    fn chest(items: Vec<Tag>) -> Tag {
+        compound(vec![("id", Tag::TagString("minecraft:chest".to_owned())),
+                      ("x", Tag::TagInt(50)),
+                      ("y", Tag::TagInt(64)),
+                      ("z", Tag::TagInt(2)),
+                      ("Items", Tag::TagList(items))])
+    }
+
+    fn world(changed: bool) -> WorldBuilder {
+        let mut builder = WorldBuilder::new("Diff");
+        for &cx in &[0, 1, 2, 3] {
+            builder.set_block(Dimension::Overworld, cx * 16, 64, 0, BlockState::new("stone"));
+            builder.chunk(Dimension::Overworld, cx, 0).timestamp = 100;
+        }
+        builder.set_block(Dimension::Overworld, 50, 64, 2, BlockState::new("chest"));
+        if changed {
+            builder.set_block(Dimension::Overworld, 19, 64, 5, BlockState::new("tnt"));
+            builder.set_block(Dimension::Overworld, 16, 64, 0, BlockState::air());
+            builder.chunk(Dimension::Overworld, 1, 0).timestamp = 200;
+            // saved again, but nothing changed
+            builder.chunk(Dimension::Overworld, 2, 0).timestamp = 200;
+            builder.chunk(Dimension::Overworld, 0, -1).timestamp = 200;
+            // emptied, which only shows in the block entity
+            builder.add_block_entity(Dimension::Overworld, 50, 2, chest(vec![]));
+            builder.chunk(Dimension::Overworld, 3, 0).timestamp = 200;
+        } else {
+            let diamond = compound(vec![("id", Tag::TagString("minecraft:diamond".to_owned())),
+                                        ("count", Tag::TagInt(5))]);
+            builder.add_block_entity(Dimension::Overworld, 50, 2, chest(vec![diamond]));
+            builder.chunk(Dimension::Overworld, -1, 0).timestamp = 100;
+        }
+        builder
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = TempWorld::new(&world(false)).unwrap();
+        let new = TempWorld::new(&world(true)).unwrap();
+        let old_rset = Regionset::new(&old.fs, old.path().join("region")).unwrap();
+        let new_rset = Regionset::new(&new.fs, new.path().join("region")).unwrap();
+
+        let diffs = diff_regionsets(&old_rset, &new_rset);
+        let summary: Vec<_> = diffs.iter().map(|d| (d.pos.x, d.pos.z, d.kind)).collect();
+        assert_eq!(summary,
+                   vec![(-1, 0, ChangeKind::Removed),
+                        (0, -1, ChangeKind::Added),
+                        (1, 0, ChangeKind::Modified),
+                        (3, 0, ChangeKind::Modified)]);
+        let mut blocks: Vec<_> = diffs[2].blocks.iter().map(|b| (b.x, b.y, b.z)).collect();
+        blocks.sort();
+        assert_eq!(blocks, vec![(16, 64, 0), (19, 64, 5)]);
+        // the emptied chest
+        let blocks: Vec<_> = diffs[3].blocks.iter().map(|b| (b.x, b.y, b.z)).collect();
+        assert_eq!(blocks, vec![(50, 64, 2)]);
+
+        let overlay = DiffOverlay::new(&diffs);
+        assert_eq!(overlay.color(19, 5), Rgba([255, 0, 0, 224]));
+        assert_eq!(overlay.color(18, 5)[0], 255);
+        assert_eq!(overlay.color(-16, 0)[0], 128);
+        // nothing changed north of the removed chunk
+        assert_eq!(overlay.color(-16, -16)[3], 0);
+    }
+
+    #[test]
+    fn test_render_tiles() {
+        let diffs = vec![ChunkDiff {
+                             pos: Coord::new(-1, 0, 0),
+                             kind: ChangeKind::Removed,
+                             blocks: Vec::new(),
+                         },
+                         ChunkDiff {
+                             pos: Coord::new(1, 0, 0),
+                             kind: ChangeKind::Modified,
+                             blocks: vec![Coord::new(19, 64, 5)],
+                         }];
+        let mut overlay = DiffOverlay::new(&diffs);
+        overlay.layout.pixels_per_block = 2;
+
+        let img = overlay.render_region(Coord::new(0, 0, 0));
+        assert_eq!(img.dimensions(), (1024, 1024));
+        assert_eq!(*img.get_pixel(39, 11), Rgba([255, 0, 0, 224]));
+        assert_eq!(img.get_pixel(0, 0)[3], 0);
+        let img = overlay.render_region(Coord::new(-1, 0, 0));
+        assert_eq!(img.get_pixel(1023, 0)[0], 128);
+
+        let tmp = TempWorld::new(&WorldBuilder::new("Tiles")).unwrap();
+        overlay.layout.north = NorthDirection::LowerRight;
+        overlay.layout.zoom = 3;
+        let mut tiles = overlay.render_tiles(&tmp.fs, tmp.path()).unwrap();
+        tiles.sort();
+        assert_eq!(tiles, vec![(-1, -1), (0, -1)]);
+        assert!(tmp.fs.exists(tmp.path().join("3.0.-1.png")));
+    }
+}
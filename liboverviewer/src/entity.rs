@@ -0,0 +1,261 @@
+//! Typed entities
+//!
+//! Up to Minecraft 1.16 entities are saved in the chunk they're standing in.  From 1.17 they have
+//! their own region files in each dimension's `entities/` directory, laid out just like the block
+//! region files.  [`EntityReader`] reads both, so callers don't need to care which kind of world
+//! they have.
+//!
+//! [`EntityReader`]: struct.EntityReader.html
+
+use nbtrs::{Tag, Taglike};
+use rio;
+use std::io::{Read, Seek};
+
+use coords::{self, Coord};
+use error::OverviewerError;
+use block_entity::nbt_text;
+use poi::normalize_id;
+use world::{Dimension, Regionset, World};
+
+/// The fields that every entity has, plus the details of a few common kinds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    /// The namespaced ID, like `minecraft:villager`
+    pub id: String,
+
+    /// In the usual dashed hex form.  Some very old entities don't have one.
+    pub uuid: Option<String>,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+
+    /// Degrees clockwise from south
+    pub yaw: f32,
+
+    /// Degrees down from horizontal
+    pub pitch: f32,
+    pub custom_name: Option<String>,
+    pub data: EntityData,
+}
+
+/// What's known about particular kinds of entity
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntityData {
+    ItemFrame {
+        /// The ID of the item in the frame, if there is one
+        item: Option<String>,
+
+        /// In eighths of a full turn
+        item_rotation: u8,
+        facing: u8,
+    },
+    Painting {
+        /// The painting's name, like `minecraft:kebab`
+        variant: Option<String>,
+        facing: u8,
+    },
+    ArmorStand {
+        /// Rotations in degrees around X, Y and Z, for each part that isn't in its default pose
+        pose: Vec<(String, [f32; 3])>,
+        small: bool,
+    },
+    Villager {
+        /// Only saved by 1.14 and later
+        profession: Option<String>,
+    },
+    Other,
+}
+
+impl Entity {
+    pub fn from_tag(tag: &Tag) -> Result<Entity, OverviewerError> {
+        let id = normalize_id(try!(tag.key("id").as_string()));
+        let pos = |i| tag.key("Pos").index(i).as_f64();
+        let rotation = |i| tag.key("Rotation").index(i).as_f32().unwrap_or(0.0);
+
+        let data = match &id[..] {
+            "minecraft:item_frame" | "minecraft:glow_item_frame" => {
+                EntityData::ItemFrame {
+                    item: tag.key("Item").key("id").as_string().ok().map(|i| normalize_id(i)),
+                    item_rotation: tag.key("ItemRotation").as_i8().unwrap_or(0) as u8,
+                    facing: facing(tag),
+                }
+            }
+            "minecraft:painting" => {
+                // called Motive before 1.19
+                let variant = tag.key("variant").or_else(|_| tag.key("Motive")).as_string();
+                EntityData::Painting {
+                    variant: variant.ok().map(|v| normalize_id(v)),
+                    facing: facing(tag),
+                }
+            }
+            "minecraft:armor_stand" => {
+                let mut pose = Vec::new();
+                if let Ok(parts) = tag.key("Pose").as_map() {
+                    for (part, angles) in parts {
+                        let angle = |i| angles.index(i).as_f32().unwrap_or(0.0);
+                        pose.push((part.clone(), [angle(0), angle(1), angle(2)]));
+                    }
+                }
+                pose.sort_by(|a, b| a.0.cmp(&b.0));
+                EntityData::ArmorStand {
+                    pose: pose,
+                    small: tag.key("Small").as_i8().unwrap_or(0) != 0,
+                }
+            }
+            "minecraft:villager" => {
+                EntityData::Villager {
+                    profession: tag.key("VillagerData")
+                        .key("profession")
+                        .as_string()
+                        .ok()
+                        .map(|p| normalize_id(p)),
+                }
+            }
+            _ => EntityData::Other,
+        };
+
+        Ok(Entity {
+            id: id,
+            uuid: uuid(tag),
+            x: try!(pos(0)),
+            y: try!(pos(1)),
+            z: try!(pos(2)),
+            yaw: rotation(0),
+            pitch: rotation(1),
+            custom_name: tag.key("CustomName").ok().and_then(nbt_text),
+            data: data,
+        })
+    }
+
+    /// The block the entity is standing in
+    pub fn block_pos(&self) -> Coord<coords::Block, coords::World> {
+        Coord::new(self.x.floor() as i64, self.y.floor() as i64, self.z.floor() as i64)
+    }
+}
+
+// lower case since 1.20.5
+fn facing(tag: &Tag) -> u8 {
+    tag.key("Facing").or_else(|_| tag.key("facing")).as_i8().unwrap_or(0) as u8
+}
+
+// an array of four ints since 1.16, and a pair of longs before that
+fn uuid(tag: &Tag) -> Option<String> {
+    if let Ok(ints) = tag.key("UUID").as_ints() {
+        if ints.len() == 4 {
+            let most = (ints[0] as u32 as u64) << 32 | ints[1] as u32 as u64;
+            let least = (ints[2] as u32 as u64) << 32 | ints[3] as u32 as u64;
+            return Some(format_uuid(most, least));
+        }
+    }
+    match (tag.key("UUIDMost").as_i64(), tag.key("UUIDLeast").as_i64()) {
+        (Ok(most), Ok(least)) => Some(format_uuid(most as u64, least as u64)),
+        _ => None,
+    }
+}
+
+/// Formats the two halves of a UUID as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`
+pub fn format_uuid(most: u64, least: u64) -> String {
+    format!("{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            most >> 32,
+            (most >> 16) & 0xffff,
+            most & 0xffff,
+            least >> 48,
+            least & 0xffff_ffff_ffff)
+}
+
+/// Reads the entities of one dimension, wherever the world keeps them
+pub struct EntityReader<'fs, FS: rio::FSRead<'fs>> {
+    chunks: Regionset<'fs, FS>,
+    entities: Option<Regionset<'fs, FS>>,
+}
+
+impl<'fs, FS> EntityReader<'fs, FS>
+    where FS: rio::FSRead<'fs>,
+          FS::ReadFile: Read + Seek
+{
+    pub fn new(world: &World<'fs, FS>,
+               dim: Dimension)
+               -> Result<EntityReader<'fs, FS>, OverviewerError> {
+        Ok(EntityReader {
+            chunks: try!(world.get_dimension(dim)),
+            entities: world.get_entities(dim),
+        })
+    }
+
+    /// Every entity in one chunk.  Entities that can't be decoded are skipped.
+    pub fn get_chunk_entities(&self, pos: Coord<coords::Chunk, coords::World>) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        for rset in Some(&self.chunks).into_iter().chain(self.entities.as_ref()) {
+            if let Some(chunk) = rset.get_chunk(pos) {
+                entities.extend(chunk.entities());
+            }
+        }
+        entities
+    }
+
+    /// Every entity in the dimension
+    pub fn all(&self) -> Vec<Entity> {
+        self.find(|_| true)
+    }
+
+    /// Every entity in the dimension that `pred` accepts, such as every villager
+    pub fn find<F>(&self, pred: F) -> Vec<Entity>
+        where F: Fn(&Entity) -> bool
+    {
+        let mut found = Vec::new();
+        for rset in Some(&self.chunks).into_iter().chain(self.entities.as_ref()) {
+            for info in rset.get_chunks() {
+                if let Some(chunk) = rset.get_chunk(info.pos) {
+                    found.extend(chunk.entities().into_iter().filter(|e| pred(e)));
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nbtrs::Tag;
+    use std::collections::HashMap;
+
+    fn doubles(v: &[f64]) -> Tag {
+        Tag::TagList(v.iter().map(|&d| Tag::TagDouble(d)).collect())
+    }
+
+    #[test]
+    fn test_format_uuid() {
+        assert_eq!(format_uuid(0x0f8fad5bd9cb469f, 0xa16570867728950e),
+                   "0f8fad5b-d9cb-469f-a165-70867728950e");
+    }
+
+    #[test]
+    fn test_from_tag() {
+        let mut item = HashMap::new();
+        item.insert("id".to_owned(), Tag::TagString("minecraft:map".to_owned()));
+        let mut tag = HashMap::new();
+        tag.insert("id".to_owned(), Tag::TagString("ItemFrame".to_owned()));
+        tag.insert("Pos".to_owned(), doubles(&[1.5, 64.0, -2.5]));
+        tag.insert("Rotation".to_owned(),
+                   Tag::TagList(vec![Tag::TagFloat(90.0), Tag::TagFloat(0.0)]));
+        tag.insert("UUIDMost".to_owned(), Tag::TagLong(0x0f8fad5bd9cb469f));
+        tag.insert("UUIDLeast".to_owned(), Tag::TagLong(0xa16570867728950eu64 as i64));
+        tag.insert("Item".to_owned(), Tag::TagCompound(item));
+        tag.insert("ItemRotation".to_owned(), Tag::TagByte(3));
+        tag.insert("Facing".to_owned(), Tag::TagByte(2));
+
+        let entity = Entity::from_tag(&Tag::TagCompound(tag)).unwrap();
+        assert_eq!(entity.id, "minecraft:item_frame");
+        assert_eq!(entity.uuid, Some("0f8fad5b-d9cb-469f-a165-70867728950e".to_owned()));
+        assert_eq!(entity.yaw, 90.0);
+        assert_eq!(entity.data,
+                   EntityData::ItemFrame {
+                       item: Some("minecraft:map".to_owned()),
+                       item_rotation: 3,
+                       facing: 2,
+                   });
+        let pos = entity.block_pos();
+        assert_eq!((pos.x, pos.y, pos.z), (1, 64, -3));
+    }
+}
@@ -3,6 +3,8 @@ use std::fmt;
 use std::convert::From;
 use std::io;
 use nbtrs;
+use image;
+use toml;
 
 // TODO figure out our set of error
 // This current definition is just a generic container for an error message
@@ -53,3 +55,5 @@ ErrImpl!(&'static str);
 ErrImpl!(String);
 ErrImpl!(io::Error, "IOError");
 ErrImpl!(nbtrs::Error, "NBTError", debug);
+ErrImpl!(image::ImageError, "ImageError");
+ErrImpl!(toml::de::Error, "TOMLError");
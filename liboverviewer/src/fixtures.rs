@@ -0,0 +1,419 @@
+//! Synthetic worlds for tests
+//!
+//! [`WorldBuilder`] lays out a world the way Minecraft 1.20 saves one (level.dat, region files,
+//! player data and maps) from just the blocks, block entities and entities a test cares about.
+//! [`TempWorld`] writes it into a [`MemFS`] and opens it from there, so everything from region
+//! parsing up is exercised exactly as it is for a real world, without touching the disk.  No
+//! external test data is needed.
+//!
+//! This module is only built for the crate's own tests, or with the `test-support` feature.
+//!
+//! ```ignore
+//! use liboverviewer::blocks::BlockState;
+//! use liboverviewer::fixtures::{TempWorld, WorldBuilder};
+//! use liboverviewer::world::Dimension;
+//!
+//! let mut builder = WorldBuilder::new("Test");
+//! builder.set_block(Dimension::Overworld, 10, 64, -3, BlockState::new("stone"));
+//! let tmp = TempWorld::new(&builder).unwrap();
+//! let world = tmp.world().unwrap();
+//! ```
+//!
+//! [`WorldBuilder`]: struct.WorldBuilder.html
+//! [`TempWorld`]: struct.TempWorld.html
+//! [`MemFS`]: ../memfs/struct.MemFS.html
+
+use flate2;
+use nbtrs::Tag;
+use rio;
+use std::collections::BTreeMap;
+use rio::FSWrite;
+use std::io::{self, Cursor, Write};
+
+use blocks::BlockState;
+use coords::{self, Coord};
+use error::OverviewerError;
+use memfs::MemFS;
+use nbt::{compound, write_tag};
+use region::RegionWriter;
+use world::{Dimension, World};
+
+/// The data version written into every chunk and level.dat, that of Minecraft 1.20.1
+pub const DATA_VERSION: i32 = 3465;
+
+/// The lowest section in an overworld chunk, as of 1.18
+pub const MIN_SECTION: i64 = -4;
+
+/// One past the highest section in an overworld chunk, as of 1.18
+pub const MAX_SECTION: i64 = 20;
+
+/// The contents of one chunk
+///
+/// Positions are relative to the chunk in X and Z, and absolute in Y.  Anything not set is air,
+/// with no block light, in `biome`.  Sections are saved without sky light, which reads as full
+/// daylight, unless some sky light in them is set, in which case the rest of them is dark.
+#[derive(Debug, Clone)]
+pub struct ChunkBuilder {
+    blocks: BTreeMap<(i64, i64, i64), BlockState>,
+    block_light: BTreeMap<(i64, i64, i64), u8>,
+    sky_light: BTreeMap<(i64, i64, i64), u8>,
+
+    /// The biome of the whole chunk
+    pub biome: String,
+    pub block_entities: Vec<Tag>,
+
+    /// Only used for worlds from before 1.17; see [`WorldBuilder::add_entity`]
+    ///
+    /// [`WorldBuilder::add_entity`]: struct.WorldBuilder.html#method.add_entity
+    pub entities: Vec<Tag>,
+
+    /// Ticks that players have spent near the chunk
+    pub inhabited_time: i64,
+    pub last_update: i64,
+
+    /// The modification time in the region file header, in seconds since the unix epoch
+    pub timestamp: u32,
+
+    /// Whether to save heightmaps, which chunks that haven't finished generating don't have
+    pub heightmaps: bool,
+}
+
+impl ChunkBuilder {
+    pub fn new() -> ChunkBuilder {
+        ChunkBuilder {
+            blocks: BTreeMap::new(),
+            block_light: BTreeMap::new(),
+            sky_light: BTreeMap::new(),
+            biome: "minecraft:plains".to_owned(),
+            block_entities: Vec::new(),
+            entities: Vec::new(),
+            inhabited_time: 0,
+            last_update: 0,
+            timestamp: 0,
+            heightmaps: true,
+        }
+    }
+
+    pub fn set_block(&mut self, x: i64, y: i64, z: i64, block: BlockState) {
+        self.blocks.insert((x, y, z), block);
+    }
+
+    pub fn set_block_light(&mut self, x: i64, y: i64, z: i64, level: u8) {
+        self.block_light.insert((x, y, z), level);
+    }
+
+    pub fn set_sky_light(&mut self, x: i64, y: i64, z: i64, level: u8) {
+        self.sky_light.insert((x, y, z), level);
+    }
+
+    /// Builds the chunk's NBT, as it would be saved at chunk coordinates `(cx, cz)`
+    pub fn to_tag(&self, cx: i64, cz: i64) -> Tag {
+        let sections = (MIN_SECTION..MAX_SECTION).map(|sy| self.section_tag(sy)).collect();
+
+        // heights count from the bottom of the world, and 0 means an empty column
+        let mut heights = [0; 256];
+        for (&(x, y, z), block) in &self.blocks {
+            let column = (z * 16 + x) as usize;
+            if block.name != "minecraft:air" {
+                heights[column] = ::std::cmp::max(heights[column], y + 1 - MIN_SECTION * 16);
+            }
+        }
+        let heights: Vec<usize> = heights.iter().map(|&h| h as usize).collect();
+        let heightmap = Tag::TagLongArray(pack(&heights, 9));
+
+        let mut chunk = vec![("DataVersion", Tag::TagInt(DATA_VERSION)),
+                             ("xPos", Tag::TagInt(cx as i32)),
+                             ("zPos", Tag::TagInt(cz as i32)),
+                             ("yPos", Tag::TagInt(MIN_SECTION as i32)),
+                             ("Status", Tag::TagString("minecraft:full".to_owned())),
+                             ("InhabitedTime", Tag::TagLong(self.inhabited_time)),
+                             ("LastUpdate", Tag::TagLong(self.last_update)),
+                             ("sections", Tag::TagList(sections)),
+                             ("block_entities", Tag::TagList(self.block_entities.clone())),
+                             ("Entities", Tag::TagList(self.entities.clone()))];
+        if self.heightmaps {
+            chunk.push(("Heightmaps",
+                        compound(vec![("WORLD_SURFACE", heightmap.clone()),
+                                      ("MOTION_BLOCKING", heightmap)])));
+        }
+        compound(chunk)
+    }
+
+    fn section_tag(&self, sy: i64) -> Tag {
+        let mut palette = vec![BlockState::air()];
+        let mut indexes = vec![0; 4096];
+        let block_light = nibbles(&self.block_light, sy);
+        let sky_light = nibbles(&self.sky_light, sy);
+        for (&(x, y, z), block) in &self.blocks {
+            if y >> 4 != sy {
+                continue;
+            }
+            let idx = match palette.iter().position(|b| b == block) {
+                Some(idx) => idx,
+                None => {
+                    palette.push(block.clone());
+                    palette.len() - 1
+                }
+            };
+            indexes[section_index(x, y, z)] = idx;
+        }
+
+        let palette_tags = palette.iter().map(block_state_tag).collect();
+        let mut block_states = vec![("palette", Tag::TagList(palette_tags))];
+        if palette.len() > 1 {
+            let mut bits = 4;
+            while (1 << bits) < palette.len() {
+                bits += 1;
+            }
+            block_states.push(("data", Tag::TagLongArray(pack(&indexes, bits))));
+        }
+
+        let biomes = compound(vec![("palette",
+                                    Tag::TagList(vec![Tag::TagString(self.biome.clone())]))]);
+        let mut section = vec![("Y", Tag::TagByte(sy as i8)),
+                               ("block_states", compound(block_states)),
+                               ("biomes", biomes)];
+        if let Some(light) = block_light {
+            section.push(("BlockLight", Tag::TagByteArray(light)));
+        }
+        if let Some(light) = sky_light {
+            section.push(("SkyLight", Tag::TagByteArray(light)));
+        }
+        compound(section)
+    }
+}
+
+/// A world to be written out by a [`TempWorld`]
+///
+/// [`TempWorld`]: struct.TempWorld.html
+#[derive(Debug, Clone)]
+pub struct WorldBuilder {
+    pub level_name: String,
+    pub spawn: (i64, i64, i64),
+    pub seed: i64,
+    chunks: Vec<((Dimension, i64, i64), ChunkBuilder)>,
+    entities: Vec<((Dimension, i64, i64), Vec<Tag>)>,
+    players: Vec<(String, Tag)>,
+    maps: Vec<(u32, Tag)>,
+
+    /// `(uuid, name)` pairs for `usercache.json`, which is only written if there are any
+    pub user_cache: Vec<(String, String)>,
+}
+
+impl WorldBuilder {
+    pub fn new(level_name: &str) -> WorldBuilder {
+        WorldBuilder {
+            level_name: level_name.to_owned(),
+            spawn: (0, 64, 0),
+            seed: 0,
+            chunks: Vec::new(),
+            entities: Vec::new(),
+            players: Vec::new(),
+            maps: Vec::new(),
+            user_cache: Vec::new(),
+        }
+    }
+
+    /// The chunk at chunk coordinates `(cx, cz)`, which is created empty if it doesn't exist yet
+    pub fn chunk(&mut self, dim: Dimension, cx: i64, cz: i64) -> &mut ChunkBuilder {
+        let key = (dim, cx, cz);
+        let idx = match self.chunks.iter().position(|c| c.0 == key) {
+            Some(idx) => idx,
+            None => {
+                self.chunks.push((key, ChunkBuilder::new()));
+                self.chunks.len() - 1
+            }
+        };
+        &mut self.chunks[idx].1
+    }
+
+    /// Sets the block at a world position, creating its chunk if needed
+    pub fn set_block(&mut self, dim: Dimension, x: i64, y: i64, z: i64, block: BlockState) {
+        let (in_chunk, chunk) = Coord::<coords::Block, coords::World>::new(x, y, z)
+            .split::<coords::Chunk>();
+        self.chunk(dim, chunk.x, chunk.z).set_block(in_chunk.x, y, in_chunk.z, block);
+    }
+
+    /// Adds a block entity to the chunk its `x` and `z` fall in
+    pub fn add_block_entity(&mut self, dim: Dimension, x: i64, z: i64, tag: Tag) {
+        self.chunk(dim, x >> 4, z >> 4).block_entities.push(tag);
+    }
+
+    /// Adds an entity to the separate entity region files used since 1.17
+    pub fn add_entity(&mut self, dim: Dimension, x: f64, z: f64, tag: Tag) {
+        let key = (dim, (x.floor() as i64) >> 4, (z.floor() as i64) >> 4);
+        match self.entities.iter().position(|e| e.0 == key) {
+            Some(idx) => self.entities[idx].1.push(tag),
+            None => self.entities.push((key, vec![tag])),
+        }
+    }
+
+    /// Adds `playerdata/<uuid>.dat`
+    pub fn add_player(&mut self, uuid: &str, tag: Tag) {
+        self.players.push((uuid.to_owned(), tag));
+    }
+
+    /// Adds `data/map_<id>.dat`
+    pub fn add_map(&mut self, id: u32, tag: Tag) {
+        self.maps.push((id, tag));
+    }
+
+    pub fn level_dat(&self) -> Tag {
+        let data = compound(vec![("LevelName", Tag::TagString(self.level_name.clone())),
+                                 ("DataVersion", Tag::TagInt(DATA_VERSION)),
+                                 ("Version",
+                                  compound(vec![("Name", Tag::TagString("1.20.1".to_owned()))])),
+                                 ("SpawnX", Tag::TagInt(self.spawn.0 as i32)),
+                                 ("SpawnY", Tag::TagInt(self.spawn.1 as i32)),
+                                 ("SpawnZ", Tag::TagInt(self.spawn.2 as i32)),
+                                 ("WorldGenSettings",
+                                  compound(vec![("seed", Tag::TagLong(self.seed))])),
+                                 ("LastPlayed", Tag::TagLong(0)),
+                                 ("GameType", Tag::TagInt(0)),
+                                 ("Time", Tag::TagLong(0)),
+                                 ("DayTime", Tag::TagLong(0))]);
+        compound(vec![("Data", data)])
+    }
+
+    /// Writes the world into the directory `dir` of `fs`, which must already exist
+    pub fn write<W: rio::FSWrite>(&self, fs: &W, dir: &rio::Path) -> io::Result<()> {
+        try!(write_gzip(fs, &dir.join("level.dat"), &self.level_dat()));
+
+        let chunks = self.chunks
+            .iter()
+            .map(|&((dim, cx, cz), ref chunk)| {
+                (dir.join(dim.region_dir()), cx, cz, chunk.timestamp, chunk.to_tag(cx, cz))
+            });
+        let entities = self.entities
+            .iter()
+            .map(|&((dim, cx, cz), ref entities)| {
+                let tag = compound(vec![("DataVersion", Tag::TagInt(DATA_VERSION)),
+                                        ("Position",
+                                         Tag::TagIntArray(vec![cx as i32 as u32, cz as i32 as u32])),
+                                        ("Entities", Tag::TagList(entities.clone()))]);
+                (dir.join(dim.entities_dir()), cx, cz, 0, tag)
+            });
+
+        // group chunks by the region file they belong in
+        let mut regions: BTreeMap<(rio::PathBuf, i64, i64), Vec<(u8, u8, u32, Tag)>> =
+            BTreeMap::new();
+        for (region_dir, cx, cz, timestamp, tag) in chunks.chain(entities) {
+            let (c, r) = Coord::<coords::Chunk, coords::World>::new(cx, 0, cz)
+                .split::<coords::Region>();
+            regions.entry((region_dir, r.x, r.z))
+                .or_insert_with(Vec::new)
+                .push((c.x as u8, c.z as u8, timestamp, tag));
+        }
+        for ((region_dir, rx, rz), chunks) in regions {
+            try!(fs.create_dir_all(&region_dir));
+            let mut region = try!(RegionWriter::create(Cursor::new(Vec::new())));
+            for (x, z, timestamp, tag) in chunks {
+                try!(region.write_chunk(x, z, &tag));
+                try!(region.set_timestamp(x, z, timestamp));
+            }
+            try!(region.save(fs, region_dir.join(format!("r.{}.{}.mca", rx, rz))));
+        }
+
+        if !self.players.is_empty() {
+            try!(fs.create_dir_all(dir.join("playerdata")));
+        }
+        for &(ref uuid, ref tag) in &self.players {
+            try!(write_gzip(fs, &dir.join("playerdata").join(format!("{}.dat", uuid)), tag));
+        }
+        if !self.maps.is_empty() {
+            try!(fs.create_dir_all(dir.join("data")));
+        }
+        for &(id, ref tag) in &self.maps {
+            try!(write_gzip(fs, &dir.join("data").join(format!("map_{}.dat", id)), tag));
+        }
+        if !self.user_cache.is_empty() {
+            let entries: Vec<String> = self.user_cache
+                .iter()
+                .map(|&(ref uuid, ref name)| format!(r#"{{"name":"{}","uuid":"{}"}}"#, name, uuid))
+                .collect();
+            let mut f = try!(fs.create(dir.join("usercache.json")));
+            try!(write!(f, "[{}]", entries.join(",")));
+        }
+        Ok(())
+    }
+}
+
+/// A world built in memory
+pub struct TempWorld {
+    /// Holds the world, in a directory of its own
+    pub fs: MemFS,
+    name: String,
+}
+
+impl TempWorld {
+    pub fn new(builder: &WorldBuilder) -> io::Result<TempWorld> {
+        let tmp = TempWorld {
+            fs: MemFS::new(),
+            name: "world".to_owned(),
+        };
+        try!(tmp.fs.create_dir_all(tmp.path()));
+        try!(builder.write(&tmp.fs, tmp.path()));
+        Ok(tmp)
+    }
+
+    /// The world's directory, as a path for `fs`
+    pub fn path(&self) -> &rio::Path {
+        rio::Path::new(&self.name)
+    }
+
+    pub fn world(&self) -> Result<World<MemFS>, OverviewerError> {
+        World::new(&self.fs, self.path())
+    }
+}
+
+fn block_state_tag(block: &BlockState) -> Tag {
+    let mut entries = vec![("Name", Tag::TagString(block.name.clone()))];
+    if !block.properties.is_empty() {
+        let properties = block.properties
+            .iter()
+            .map(|(k, v)| (k.clone(), Tag::TagString(v.clone())))
+            .collect();
+        entries.push(("Properties", Tag::TagCompound(properties)));
+    }
+    compound(entries)
+}
+
+// index into a section's 4096-entry arrays, which are stored YZX
+fn section_index(x: i64, y: i64, z: i64) -> usize {
+    (((y & 15) << 8) | (z << 4) | x) as usize
+}
+
+// Packs the levels of `light` in section `sy` into a light array, if there are any
+fn nibbles(light: &BTreeMap<(i64, i64, i64), u8>, sy: i64) -> Option<Vec<u8>> {
+    let mut array = vec![0u8; 2048];
+    let mut any = false;
+    for (&(x, y, z), &level) in light {
+        if y >> 4 != sy {
+            continue;
+        }
+        let index = section_index(x, y, z);
+        array[index >> 1] |= (level & 0x0f) << ((index & 1) * 4);
+        any = true;
+    }
+    if any { Some(array) } else { None }
+}
+
+// Packs `values` into longs, as many whole entries per long as fit, least significant bits first
+fn pack(values: &[usize], bits: usize) -> Vec<i64> {
+    let per_long = 64 / bits;
+    values.chunks(per_long)
+        .map(|chunk| {
+            chunk.iter()
+                .enumerate()
+                .fold(0u64, |long, (i, &v)| long | (v as u64) << (i * bits)) as i64
+        })
+        .collect()
+}
+
+fn write_gzip<W: rio::FSWrite>(fs: &W, path: &rio::Path, tag: &Tag) -> io::Result<()> {
+    let f = try!(fs.create(path));
+    let mut encoder = flate2::write::GzEncoder::new(f, flate2::Compression::Default);
+    try!(write_tag(&mut encoder, "", tag));
+    try!(encoder.finish());
+    Ok(())
+}
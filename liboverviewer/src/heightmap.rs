@@ -0,0 +1,266 @@
+//! Exporting elevation data
+//!
+//! A [`Heightmap`] is a grid with one elevation per block column, built from the heightmaps that
+//! Minecraft stores in every chunk.  It can be written out as a 16-bit greyscale PNG, as raw
+//! little-endian integers, or as an ESRI ASCII grid, which between them cover most terrain analysis
+//! and GIS tools.
+//!
+//! [`Heightmap`]: struct.Heightmap.html
+
+use image;
+use image::png::PNGEncoder;
+use rio;
+use std::cmp::{max, min};
+use std::io::{self, Read, Seek, Write};
+
+use coords::{self, Coord};
+use world::{HeightmapType, Regionset};
+
+/// The value written for columns with no data in raw output
+pub const RAW_NO_DATA: i16 = ::std::i16::MIN;
+
+/// The value written for columns with no data in ASCII grid output
+pub const ASCII_NO_DATA: i64 = -9999;
+
+/// An inclusive rectangle of block columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct CropBox {
+    pub min_x: i64,
+    pub min_z: i64,
+    pub max_x: i64,
+    pub max_z: i64,
+}
+
+impl CropBox {
+    /// Builds a crop box from any two opposite corners
+    pub fn new(x1: i64, z1: i64, x2: i64, z2: i64) -> CropBox {
+        CropBox {
+            min_x: min(x1, x2),
+            min_z: min(z1, z2),
+            max_x: max(x1, x2),
+            max_z: max(z1, z2),
+        }
+    }
+
+    pub fn contains(&self, x: i64, z: i64) -> bool {
+        x >= self.min_x && x <= self.max_x && z >= self.min_z && z <= self.max_z
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max_x - self.min_x + 1) as usize
+    }
+
+    pub fn depth(&self) -> usize {
+        (self.max_z - self.min_z + 1) as usize
+    }
+}
+
+/// Elevations for a rectangle of block columns
+///
+/// Heights are the Y coordinate just above the surface, as Minecraft stores them.  Columns in
+/// chunks that haven't been generated have no height.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    pub bounds: CropBox,
+
+    // row-major, north to south
+    heights: Vec<Option<i16>>,
+}
+
+impl Heightmap {
+    /// An empty heightmap covering `bounds`
+    pub fn new(bounds: CropBox) -> Heightmap {
+        Heightmap {
+            bounds: bounds,
+            heights: vec![None; bounds.width() * bounds.depth()],
+        }
+    }
+
+    /// Stitches the heightmaps of every chunk in a regionset together
+    ///
+    /// If `crop` is not given, the heightmap covers every generated chunk.  Returns `None` if there
+    /// are no chunks in the area.
+    pub fn from_regionset<'fs, FS>(rset: &Regionset<'fs, FS>,
+                                   kind: HeightmapType,
+                                   crop: Option<CropBox>)
+                                   -> Option<Heightmap>
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read + Seek
+    {
+        let chunks: Vec<_> = rset.get_chunks()
+            .map(|info| info.pos)
+            .filter(|pos| match crop {
+                Some(ref crop) => {
+                    pos.x * 16 <= crop.max_x && pos.x * 16 + 15 >= crop.min_x &&
+                    pos.z * 16 <= crop.max_z && pos.z * 16 + 15 >= crop.min_z
+                }
+                None => true,
+            })
+            .collect();
+        if chunks.is_empty() {
+            return None;
+        }
+
+        let bounds = crop.unwrap_or_else(|| {
+            CropBox::new(chunks.iter().map(|c| c.x * 16).min().unwrap(),
+                         chunks.iter().map(|c| c.z * 16).min().unwrap(),
+                         chunks.iter().map(|c| c.x * 16 + 15).max().unwrap(),
+                         chunks.iter().map(|c| c.z * 16 + 15).max().unwrap())
+        });
+
+        let mut map = Heightmap::new(bounds);
+        for pos in chunks {
+            let chunk = match rset.get_chunk(pos) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            for z in 0..16 {
+                for x in 0..16 {
+                    let in_chunk = Coord::<coords::Block, coords::Chunk>::new(x, 0, z);
+                    let block = in_chunk.join(pos);
+                    if let Some(h) = chunk.get_height(kind, in_chunk) {
+                        map.set(block.x, block.z, Some(h));
+                    }
+                }
+            }
+        }
+        Some(map)
+    }
+
+    /// The height of the column at `(x, z)`, in world coordinates
+    pub fn get(&self, x: i64, z: i64) -> Option<i64> {
+        self.index(x, z).and_then(|i| self.heights[i]).map(|h| h as i64)
+    }
+
+    /// Sets the height of the column at `(x, z)`.  Columns outside of the bounds are ignored.
+    pub fn set(&mut self, x: i64, z: i64, height: Option<i64>) {
+        if let Some(i) = self.index(x, z) {
+            self.heights[i] = height.map(|h| h as i16);
+        }
+    }
+
+    /// The lowest and highest heights in the map
+    pub fn range(&self) -> Option<(i64, i64)> {
+        let known = self.heights.iter().filter_map(|&h| h);
+        known.fold(None, |acc, h| {
+            let h = h as i64;
+            match acc {
+                Some((lo, hi)) => Some((min(lo, h), max(hi, h))),
+                None => Some((h, h)),
+            }
+        })
+    }
+
+    fn index(&self, x: i64, z: i64) -> Option<usize> {
+        if !self.bounds.contains(x, z) {
+            return None;
+        }
+        let row = (z - self.bounds.min_z) as usize;
+        let col = (x - self.bounds.min_x) as usize;
+        Some(row * self.bounds.width() + col)
+    }
+
+    /// Writes a 16-bit greyscale PNG, one pixel per column with north at the top
+    ///
+    /// Each pixel is `height - base`, clamped to the range of a `u16`.  Columns with no data are 0.
+    pub fn write_png16<W: Write>(&self, w: &mut W, base: i64) -> io::Result<()> {
+        // 16-bit samples are big-endian
+        let mut data = Vec::with_capacity(self.heights.len() * 2);
+        for h in &self.heights {
+            let v = h.map(|h| max(0, min(0xffff, h as i64 - base))).unwrap_or(0) as u16;
+            data.push((v >> 8) as u8);
+            data.push(v as u8);
+        }
+        let (width, depth) = (self.bounds.width() as u32, self.bounds.depth() as u32);
+        PNGEncoder::new(w).encode(&data, width, depth, image::Gray(16))
+    }
+
+    /// Writes one little-endian `i16` per column, row by row from north to south.  Columns with no
+    /// data are [`RAW_NO_DATA`].
+    ///
+    /// [`RAW_NO_DATA`]: constant.RAW_NO_DATA.html
+    pub fn write_raw<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(self.heights.len() * 2);
+        for h in &self.heights {
+            let v = h.unwrap_or(RAW_NO_DATA);
+            buf.push(v as u8);
+            buf.push((v >> 8) as u8);
+        }
+        w.write_all(&buf)
+    }
+
+    /// Writes an ESRI ASCII grid
+    ///
+    /// GIS tools expect Y to increase northwards, so the grid's Y axis is Minecraft's Z axis
+    /// negated.  Rows are still written from north to south, as the format requires.
+    pub fn write_ascii_grid<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let b = &self.bounds;
+        try!(writeln!(w, "ncols {}", b.width()));
+        try!(writeln!(w, "nrows {}", b.depth()));
+        try!(writeln!(w, "xllcorner {}", b.min_x));
+        try!(writeln!(w, "yllcorner {}", -(b.max_z + 1)));
+        try!(writeln!(w, "cellsize 1"));
+        try!(writeln!(w, "NODATA_value {}", ASCII_NO_DATA));
+        for row in self.heights.chunks(b.width()) {
+            let cells: Vec<String> = row.iter()
+                .map(|h| h.map(|h| h as i64).unwrap_or(ASCII_NO_DATA).to_string())
+                .collect();
+            try!(writeln!(w, "{}", cells.join(" ")));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Heightmap {
+        let mut map = Heightmap::new(CropBox::new(-1, -1, 1, 0));
+        map.set(-1, -1, Some(64));
+        map.set(0, -1, Some(70));
+        map.set(1, 0, Some(-10));
+        // outside, ignored
+        map.set(2, 0, Some(100));
+        map
+    }
+
+    #[test]
+    fn test_heightmap() {
+        let map = sample();
+        assert_eq!(map.bounds.width(), 3);
+        assert_eq!(map.bounds.depth(), 2);
+        assert_eq!(map.get(0, -1), Some(70));
+        assert_eq!(map.get(1, -1), None);
+        assert_eq!(map.get(2, 0), None);
+        assert_eq!(map.range(), Some((-10, 70)));
+    }
+
+    #[test]
+    fn test_write_raw() {
+        let mut out = Vec::new();
+        sample().write_raw(&mut out).unwrap();
+        assert_eq!(out, vec![64, 0, 70, 0, 0, 0x80, 0, 0x80, 0, 0x80, 0xf6, 0xff]);
+    }
+
+    #[test]
+    fn test_write_ascii_grid() {
+        let mut out = Vec::new();
+        sample().write_ascii_grid(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "ncols 3\nnrows 2\nxllcorner -1\nyllcorner -1\ncellsize 1\nNODATA_value -9999\n\
+                    64 70 -9999\n-9999 -9999 -10\n");
+    }
+
+    #[test]
+    fn test_write_png16() {
+        let mut out = Vec::new();
+        sample().write_png16(&mut out, -64).unwrap();
+        assert_eq!(&out[..8], b"\x89PNG\r\n\x1a\n");
+        // IHDR: 3x2, 16-bit greyscale
+        assert_eq!(&out[12..16], b"IHDR");
+        assert_eq!(&out[16..29], &[0, 0, 0, 3, 0, 0, 0, 2, 16, 0, 0, 0, 0]);
+        assert_eq!(&out[out.len() - 12..], &[0, 0, 0, 0, 73, 69, 78, 68, 0xae, 0x42, 0x60, 0x82]);
+    }
+}
@@ -2,9 +2,41 @@ extern crate lru_time_cache;
 extern crate nbtrs;
 extern crate flate2;
 extern crate rio;
+extern crate image;
+extern crate toml;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_json;
+
+#[cfg(feature = "serde-serialize")]
+extern crate serde;
+#[cfg(feature = "serde-serialize")]
+#[macro_use]
+extern crate serde_derive;
 
 pub mod world;
+pub mod heightmap;
+pub mod biome;
+pub mod blocks;
+pub mod render;
+pub mod tint;
 pub mod asset_manager;
+pub mod config;
+pub mod web;
+pub mod poi;
+pub mod maps;
+pub mod entity;
+pub mod block_entity;
+pub mod nbt;
+pub mod region;
+pub mod prune;
+pub mod stats;
+pub mod diff;
+pub mod activity;
+pub mod memfs;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fixtures;
 pub mod error;
 
 pub mod coords;
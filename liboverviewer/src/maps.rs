@@ -0,0 +1,375 @@
+//! In-game map items, from a world's `data/map_<id>.dat` files
+//!
+//! A map is 128 by 128 pixels, each covering `2^scale` by `2^scale` blocks, centered on
+//! `(x_center, z_center)`.  Pixels are indexes into a fixed palette of base colors, each of which
+//! comes in four shades.
+
+use image::{ImageBuffer, Rgba};
+use nbtrs::{Tag, Taglike};
+use rio;
+use serde_json::Value;
+use std::io::Write;
+
+use asset_manager::Texture;
+use coords::{self, Coord};
+use error::OverviewerError;
+use poi::text_component;
+use render::{ImageFormat, NorthDirection};
+use world::Dimension;
+
+/// The width and height of every map, in pixels
+pub const MAP_SIZE: u32 = 128;
+
+/// A single map item
+#[derive(Debug, Clone)]
+pub struct MapItem {
+    /// The number in the file name, which is also what the item refers to
+    pub id: u32,
+
+    /// From 0 (one block per pixel) to 4 (sixteen blocks per pixel)
+    pub scale: u8,
+    pub x_center: i64,
+    pub z_center: i64,
+
+    /// `None` for modded dimensions
+    pub dimension: Option<Dimension>,
+
+    /// One color index per pixel, row by row, north at the top
+    pub colors: Vec<u8>,
+    pub banners: Vec<MapBanner>,
+    pub frames: Vec<MapFrame>,
+}
+
+/// A banner that has been marked on a map
+#[derive(Debug, Clone)]
+pub struct MapBanner {
+    pub pos: Coord<coords::Block, coords::World>,
+
+    /// The dye color, like `white` or `light_blue`
+    pub color: String,
+    pub name: Option<String>,
+}
+
+/// An item frame holding a copy of the map
+#[derive(Debug, Clone)]
+pub struct MapFrame {
+    pub pos: Coord<coords::Block, coords::World>,
+    pub rotation: i32,
+}
+
+impl MapItem {
+    /// Given the root tag of a `map_<id>.dat` file, extract its fields
+    pub fn from_tag(id: u32, tag: &Tag) -> Result<MapItem, OverviewerError> {
+        let data = try!(tag.key("data"));
+        let colors = try!(data.key("colors").as_bytes()).clone();
+        if colors.len() != (MAP_SIZE * MAP_SIZE) as usize {
+            return Err(format!("Map {} has {} pixels", id, colors.len()).into());
+        }
+
+        // positions in banners and frames are compounds of X, Y and Z, or an array of three ints
+        // since 1.20.5, when every key here became lower case
+        fn pos(tag: &Tag) -> Option<Coord<coords::Block, coords::World>> {
+            if let Ok(ints) = tag.key("pos").as_ints() {
+                let ints: Vec<i64> = ints.iter().map(|&i| i as i32 as i64).collect();
+                return match ints.len() {
+                    3 => Some(Coord::new(ints[0], ints[1], ints[2])),
+                    _ => None,
+                };
+            }
+            match (tag.key("Pos").key("X").as_i32(),
+                   tag.key("Pos").key("Y").as_i32(),
+                   tag.key("Pos").key("Z").as_i32()) {
+                (Ok(x), Ok(y), Ok(z)) => Some(Coord::new(x as i64, y as i64, z as i64)),
+                _ => None,
+            }
+        }
+        let list = |key| data.key(key).as_list().map(|l| &l[..]).unwrap_or(&[]);
+        let banners = list("banners")
+            .iter()
+            .filter_map(|b| {
+                pos(b).map(|pos| {
+                    MapBanner {
+                        pos: pos,
+                        color: b.key("Color")
+                            .or_else(|_| b.key("color"))
+                            .as_string()
+                            .map(|c| c.clone())
+                            .unwrap_or_else(|_| "white".to_owned()),
+                        name: b.key("Name")
+                            .or_else(|_| b.key("name"))
+                            .as_string()
+                            .ok()
+                            .map(|n| text_component(n)),
+                    }
+                })
+            })
+            .collect();
+        let frames = list("frames")
+            .iter()
+            .filter_map(|f| {
+                pos(f).map(|pos| {
+                    MapFrame {
+                        pos: pos,
+                        rotation: f.key("Rotation")
+                            .or_else(|_| f.key("rotation"))
+                            .as_i32()
+                            .unwrap_or(0),
+                    }
+                })
+            })
+            .collect();
+
+        Ok(MapItem {
+            id: id,
+            scale: try!(data.key("scale").as_i8()) as u8,
+            x_center: try!(data.key("xCenter").as_i32()) as i64,
+            z_center: try!(data.key("zCenter").as_i32()) as i64,
+            dimension: data.key("dimension").ok().and_then(Dimension::from_tag),
+            colors: colors,
+            banners: banners,
+            frames: frames,
+        })
+    }
+
+    /// The number of blocks along each side of the map
+    pub fn blocks(&self) -> i64 {
+        (MAP_SIZE as i64) << self.scale
+    }
+
+    /// The north-west corner of the area covered by the map, and the south-east corner just
+    /// outside it
+    pub fn bounds(&self) -> ((i64, i64), (i64, i64)) {
+        let half = self.blocks() / 2;
+        ((self.x_center - half, self.z_center - half),
+         (self.x_center + half, self.z_center + half))
+    }
+
+    /// Draws the map as it looks in game.  Unexplored pixels are transparent.
+    pub fn to_image(&self) -> Texture {
+        ImageBuffer::from_fn(MAP_SIZE, MAP_SIZE, |x, y| {
+            map_color(self.colors[(y * MAP_SIZE + x) as usize])
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let ((x1, z1), (x2, z2)) = self.bounds();
+        let banners: Vec<Value> = self.banners
+            .iter()
+            .map(|b| {
+                json!({
+                    "x": b.pos.x,
+                    "y": b.pos.y,
+                    "z": b.pos.z,
+                    "color": b.color,
+                    "name": b.name,
+                })
+            })
+            .collect();
+        json!({
+            "id": self.id,
+            "scale": self.scale,
+            "bounds": [x1, z1, x2, z2],
+            "banners": banners,
+        })
+    }
+}
+
+/// Writes `maps` as images for the web viewer to lay over a render, plus a `maps.json` describing
+/// where each one goes
+///
+/// Images are rotated to match the render's `north`.  Maps are listed with the coarsest first, so
+/// that more detailed maps are drawn on top of them.
+pub fn write_overlay<W, P>(fs: &W,
+                           out_dir: P,
+                           maps: &[MapItem],
+                           north: NorthDirection)
+                           -> Result<(), OverviewerError>
+    where W: rio::FSWrite,
+          P: AsRef<rio::Path>
+{
+    let out_dir = out_dir.as_ref();
+    let mut maps: Vec<&MapItem> = maps.iter().collect();
+    maps.sort_by_key(|m| (-(m.scale as i32), m.id));
+    for map in &maps {
+        let mut f = try!(fs.create(out_dir.join(format!("map_{}.png", map.id))));
+        try!(ImageFormat::Png.save(north.rotate(&map.to_image()), &mut f));
+    }
+    let json = json!({ "maps": maps.iter().map(|m| m.to_json()).collect::<Vec<_>>() });
+    try!(write!(try!(fs.create(out_dir.join("maps.json"))), "{}\n", json));
+    Ok(())
+}
+
+/// The RGBA color of a map color index
+///
+/// The index is a base color times four, plus a shade.  Unknown base colors are transparent,
+/// like base color 0.
+pub fn map_color(index: u8) -> Rgba<u8> {
+    // the shades, from the shaded side of a slope to the lit side, and then the one only used by
+    // maps drawn with commands
+    const SHADES: [u32; 4] = [180, 220, 255, 135];
+
+    let base = (index / 4) as usize;
+    if base == 0 || base >= BASE_COLORS.len() {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let shade = SHADES[(index % 4) as usize];
+    let c = BASE_COLORS[base];
+    Rgba([(c[0] as u32 * shade / 255) as u8,
+          (c[1] as u32 * shade / 255) as u8,
+          (c[2] as u32 * shade / 255) as u8,
+          255])
+}
+
+// as of Minecraft 1.17; base color 0 is transparent
+const BASE_COLORS: &'static [[u8; 3]] = &[[0, 0, 0],
+                                          [127, 178, 56],
+                                          [247, 233, 163],
+                                          [199, 199, 199],
+                                          [255, 0, 0],
+                                          [160, 160, 255],
+                                          [167, 167, 167],
+                                          [0, 124, 0],
+                                          [255, 255, 255],
+                                          [164, 168, 184],
+                                          [151, 109, 77],
+                                          [112, 112, 112],
+                                          [64, 64, 255],
+                                          [143, 119, 72],
+                                          [255, 252, 245],
+                                          [216, 127, 51],
+                                          [178, 76, 216],
+                                          [102, 153, 216],
+                                          [229, 229, 51],
+                                          [127, 204, 25],
+                                          [242, 127, 165],
+                                          [76, 76, 76],
+                                          [153, 153, 153],
+                                          [76, 127, 153],
+                                          [127, 63, 178],
+                                          [51, 76, 178],
+                                          [102, 76, 51],
+                                          [102, 127, 51],
+                                          [153, 51, 51],
+                                          [25, 25, 25],
+                                          [250, 238, 77],
+                                          [92, 219, 213],
+                                          [74, 128, 255],
+                                          [0, 217, 58],
+                                          [129, 86, 49],
+                                          [112, 2, 0],
+                                          [209, 177, 161],
+                                          [159, 82, 36],
+                                          [149, 87, 108],
+                                          [112, 108, 138],
+                                          [186, 133, 36],
+                                          [103, 117, 53],
+                                          [160, 77, 78],
+                                          [57, 41, 35],
+                                          [135, 107, 98],
+                                          [87, 92, 92],
+                                          [122, 73, 88],
+                                          [76, 62, 92],
+                                          [76, 50, 35],
+                                          [76, 82, 42],
+                                          [142, 60, 46],
+                                          [37, 22, 16],
+                                          [189, 48, 49],
+                                          [148, 63, 97],
+                                          [92, 25, 29],
+                                          [22, 126, 134],
+                                          [58, 142, 140],
+                                          [86, 44, 62],
+                                          [20, 180, 133],
+                                          [100, 100, 100],
+                                          [216, 175, 147],
+                                          [127, 167, 150]];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Rgba;
+    use nbt::compound;
+    use nbtrs::Tag;
+
+    fn map(scale: u8) -> MapItem {
+        MapItem {
+            id: 3,
+            scale: scale,
+            x_center: 64,
+            z_center: -64,
+            dimension: Some(Dimension::Overworld),
+            colors: vec![0; (MAP_SIZE * MAP_SIZE) as usize],
+            banners: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_map_color() {
+        assert_eq!(map_color(0), Rgba([0, 0, 0, 0]));
+        assert_eq!(map_color(3), Rgba([0, 0, 0, 0]));
+        // grass, in each shade
+        assert_eq!(map_color(4), Rgba([89, 125, 39, 255]));
+        assert_eq!(map_color(6), Rgba([127, 178, 56, 255]));
+        assert_eq!(map_color(7), Rgba([67, 94, 29, 255]));
+        assert_eq!(map_color(255), Rgba([0, 0, 0, 0]));
+    }
+
+    // map data holding one banner and one frame
+    fn map_tag(banner: Vec<(&str, Tag)>, frame: Vec<(&str, Tag)>) -> Tag {
+        let data = compound(vec![("scale", Tag::TagByte(1)),
+                                 ("xCenter", Tag::TagInt(64)),
+                                 ("zCenter", Tag::TagInt(-64)),
+                                 ("colors", Tag::TagByteArray(vec![0; 128 * 128])),
+                                 ("banners", Tag::TagList(vec![compound(banner)])),
+                                 ("frames", Tag::TagList(vec![compound(frame)]))]);
+        compound(vec![("data", data)])
+    }
+
+    #[test]
+    fn test_from_tag() {
+        let xyz = |x: i32, y: i32, z: i32| {
+            compound(vec![("X", Tag::TagInt(x)), ("Y", Tag::TagInt(y)), ("Z", Tag::TagInt(z))])
+        };
+        let name = || Tag::TagString(r#"{"text":"Base"}"#.to_owned());
+        let old = map_tag(vec![("Pos", xyz(-100, 70, 30)),
+                               ("Color", Tag::TagString("red".to_owned())),
+                               ("Name", name())],
+                          vec![("Pos", xyz(5, 64, -5)), ("Rotation", Tag::TagInt(2))]);
+        // the same map, as saved since 1.20.5
+        let new = map_tag(vec![("pos", Tag::TagIntArray(vec![-100i32 as u32, 70, 30])),
+                               ("color", Tag::TagString("red".to_owned())),
+                               ("name", name())],
+                          vec![("pos", Tag::TagIntArray(vec![5, 64, -5i32 as u32])),
+                               ("rotation", Tag::TagInt(2)),
+                               ("entity_id", Tag::TagInt(17))]);
+
+        for tag in &[old, new] {
+            let map = MapItem::from_tag(3, tag).unwrap();
+            assert_eq!(map.banners.len(), 1);
+            let banner = &map.banners[0];
+            assert_eq!((banner.pos.x, banner.pos.y, banner.pos.z), (-100, 70, 30));
+            assert_eq!(banner.color, "red");
+            assert_eq!(banner.name, Some("Base".to_owned()));
+            assert_eq!(map.frames.len(), 1);
+            let frame = &map.frames[0];
+            assert_eq!((frame.pos.x, frame.pos.y, frame.pos.z, frame.rotation), (5, 64, -5, 2));
+        }
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(map(0).bounds(), ((0, -128), (128, 0)));
+        assert_eq!(map(2).bounds(), ((-192, -320), (320, 192)));
+    }
+
+    #[test]
+    fn test_to_image() {
+        let mut m = map(0);
+        m.colors[MAP_SIZE as usize + 2] = 14 * 4 + 2;
+        let img = m.to_image();
+        assert_eq!(img.dimensions(), (MAP_SIZE, MAP_SIZE));
+        assert_eq!(*img.get_pixel(2, 1), Rgba([255, 252, 245, 255]));
+        assert_eq!(img.get_pixel(0, 0)[3], 0);
+    }
+}
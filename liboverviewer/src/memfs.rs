@@ -0,0 +1,171 @@
+//! A filesystem kept in memory
+//!
+//! [`MemFS`] implements the same `rio` traits as `rio::Native`, so anything that reads a world or
+//! writes tiles can be pointed at it instead of the disk.  The test fixtures build their worlds
+//! in one.
+//!
+//! [`MemFS`]: struct.MemFS.html
+
+use rio::{self, FSRead};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Cursor, Write};
+use std::rc::Rc;
+
+/// Files and directories held in memory
+///
+/// Like a real filesystem, files can only be created in directories that exist.  The root, the
+/// empty path, always does.
+pub struct MemFS {
+    files: RefCell<BTreeMap<rio::PathBuf, Rc<RefCell<Vec<u8>>>>>,
+    dirs: RefCell<BTreeSet<rio::PathBuf>>,
+}
+
+/// A file open for writing in a [`MemFS`]
+///
+/// [`MemFS`]: struct.MemFS.html
+pub struct MemFile {
+    data: Rc<RefCell<Vec<u8>>>,
+}
+
+impl MemFS {
+    pub fn new() -> MemFS {
+        let mut dirs = BTreeSet::new();
+        dirs.insert(rio::PathBuf::from(""));
+        MemFS {
+            files: RefCell::new(BTreeMap::new()),
+            dirs: RefCell::new(dirs),
+        }
+    }
+
+    /// The contents of a file, if there is one at `p`
+    pub fn contents<P: AsRef<rio::Path>>(&self, p: P) -> Option<Vec<u8>> {
+        self.files.borrow().get(&p.as_ref().to_owned()).map(|data| data.borrow().clone())
+    }
+
+    fn parent_exists(&self, p: &rio::Path) -> bool {
+        match p.parent() {
+            Some(parent) => self.dirs.borrow().contains(&parent.to_owned()),
+            None => true,
+        }
+    }
+}
+
+impl Default for MemFS {
+    fn default() -> MemFS {
+        MemFS::new()
+    }
+}
+
+fn not_found(p: &rio::Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{:?} doesn't exist", p))
+}
+
+impl<'fs> rio::FSRead<'fs> for MemFS {
+    type ReadFile = Cursor<Vec<u8>>;
+
+    fn exists<P: AsRef<rio::Path>>(&self, p: P) -> bool {
+        let p = p.as_ref().to_owned();
+        self.files.borrow().contains_key(&p) || self.dirs.borrow().contains(&p)
+    }
+
+    fn is_dir<P: AsRef<rio::Path>>(&self, p: P) -> bool {
+        self.dirs.borrow().contains(&p.as_ref().to_owned())
+    }
+
+    fn open<P: AsRef<rio::Path>>(&self, p: P) -> io::Result<Cursor<Vec<u8>>> {
+        let p = p.as_ref();
+        self.contents(p).map(Cursor::new).ok_or_else(|| not_found(p))
+    }
+
+    fn read_dir<P: AsRef<rio::Path>>(&'fs self, p: P) -> io::Result<rio::ReadDir<'fs, MemFS>> {
+        let p = p.as_ref();
+        if !self.is_dir(p) {
+            return Err(not_found(p));
+        }
+        let dir = Some(p.to_owned());
+        let in_dir = |entry: &&rio::PathBuf| entry.parent().map(|e| e.to_owned()) == dir;
+        let mut entries: Vec<rio::PathBuf> =
+            self.files.borrow().keys().filter(&in_dir).cloned().collect();
+        entries.extend(self.dirs.borrow().iter().filter(&in_dir).cloned());
+        Ok(rio::ReadDir::new(self, entries))
+    }
+}
+
+impl rio::FSWrite for MemFS {
+    type WriteFile = MemFile;
+
+    /// Creates an empty file at `p`, replacing any file already there
+    fn create<P: AsRef<rio::Path>>(&self, p: P) -> io::Result<MemFile> {
+        let p = p.as_ref();
+        if !self.parent_exists(p) || self.is_dir(p) {
+            return Err(not_found(p));
+        }
+        let data = Rc::new(RefCell::new(Vec::new()));
+        self.files.borrow_mut().insert(p.to_owned(), data.clone());
+        Ok(MemFile { data: data })
+    }
+
+    fn create_dir_all<P: AsRef<rio::Path>>(&self, p: P) -> io::Result<()> {
+        let mut dir = Some(p.as_ref());
+        while let Some(d) = dir {
+            self.dirs.borrow_mut().insert(d.to_owned());
+            dir = d.parent();
+        }
+        Ok(())
+    }
+
+    fn remove_file<P: AsRef<rio::Path>>(&self, p: P) -> io::Result<()> {
+        let p = p.as_ref();
+        match self.files.borrow_mut().remove(&p.to_owned()) {
+            Some(_) => Ok(()),
+            None => Err(not_found(p)),
+        }
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rio::FSWrite;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_files() {
+        let fs = MemFS::new();
+        assert!(fs.create("world/level.dat").is_err());
+        fs.create_dir_all("world/region").unwrap();
+        assert!(fs.is_dir("world"));
+
+        let mut f = fs.create("world/level.dat").unwrap();
+        f.write_all(b"level").unwrap();
+        f.write_all(b".dat").unwrap();
+        let mut contents = String::new();
+        fs.open("world/level.dat").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "level.dat");
+
+        let mut names: Vec<String> = fs.read_dir("world")
+            .unwrap()
+            .map(|e| e.path().file_name().unwrap().to_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["level.dat", "region"]);
+        assert!(fs.read_dir("world/level.dat").is_err());
+
+        fs.remove_file("world/level.dat").unwrap();
+        assert!(!fs.exists("world/level.dat"));
+        assert!(fs.open("world/level.dat").is_err());
+        assert!(fs.remove_file("world/level.dat").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Writing NBT
+//!
+//! nbtrs only reads NBT, which is all that rendering needs.  Anything that saves chunks or builds
+//! worlds from scratch uses [`write_tag`] to serialize them again, in the same big-endian format
+//! Minecraft uses.
+//!
+//! [`write_tag`]: fn.write_tag.html
+
+use nbtrs::Tag;
+use std::io::{self, Write};
+
+/// Writes `tag` as a named root tag, the form that level.dat files and chunks take
+pub fn write_tag<W: Write>(w: &mut W, name: &str, tag: &Tag) -> io::Result<()> {
+    try!(w.write_all(&[type_id(tag)]));
+    try!(write_string(w, name));
+    write_payload(w, tag)
+}
+
+/// Serializes `tag` into a new buffer, with an empty root name
+pub fn to_bytes(tag: &Tag) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_tag(&mut buf, "", tag).expect("writing to a Vec can't fail");
+    buf
+}
+
+/// Builds a compound tag from a list of entries, which is much terser than filling a `HashMap`
+pub fn compound(entries: Vec<(&str, Tag)>) -> Tag {
+    Tag::TagCompound(entries.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+}
+
+fn type_id(tag: &Tag) -> u8 {
+    match *tag {
+        Tag::TagEnd => 0,
+        Tag::TagByte(_) => 1,
+        Tag::TagShort(_) => 2,
+        Tag::TagInt(_) => 3,
+        Tag::TagLong(_) => 4,
+        Tag::TagFloat(_) => 5,
+        Tag::TagDouble(_) => 6,
+        Tag::TagByteArray(_) => 7,
+        Tag::TagString(_) => 8,
+        Tag::TagList(_) => 9,
+        Tag::TagCompound(_) => 10,
+        Tag::TagIntArray(_) => 11,
+        Tag::TagLongArray(_) => 12,
+    }
+}
+
+fn write_payload<W: Write>(w: &mut W, tag: &Tag) -> io::Result<()> {
+    match *tag {
+        Tag::TagEnd => Ok(()),
+        Tag::TagByte(b) => w.write_all(&[b as u8]),
+        Tag::TagShort(s) => write_be(w, s as u64, 2),
+        Tag::TagInt(i) => write_be(w, i as u64, 4),
+        Tag::TagLong(l) => write_be(w, l as u64, 8),
+        Tag::TagFloat(f) => write_be(w, f.to_bits() as u64, 4),
+        Tag::TagDouble(d) => write_be(w, d.to_bits(), 8),
+        Tag::TagByteArray(ref bytes) => {
+            try!(write_be(w, bytes.len() as u64, 4));
+            let bytes: Vec<u8> = bytes.iter().map(|&b| b as u8).collect();
+            w.write_all(&bytes)
+        }
+        Tag::TagString(ref s) => write_string(w, s),
+        Tag::TagList(ref list) => {
+            // empty lists are written as lists of TagEnd, like Minecraft does
+            try!(w.write_all(&[list.first().map(type_id).unwrap_or(0)]));
+            try!(write_be(w, list.len() as u64, 4));
+            for item in list {
+                try!(write_payload(w, item));
+            }
+            Ok(())
+        }
+        Tag::TagCompound(ref map) => {
+            // sorted, so that the same tag always serializes to the same bytes
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            for key in keys {
+                try!(write_tag(w, key, &map[key]));
+            }
+            w.write_all(&[0])
+        }
+        Tag::TagIntArray(ref ints) => {
+            try!(write_be(w, ints.len() as u64, 4));
+            for &i in ints {
+                try!(write_be(w, i as u32 as u64, 4));
+            }
+            Ok(())
+        }
+        Tag::TagLongArray(ref longs) => {
+            try!(write_be(w, longs.len() as u64, 4));
+            for &l in longs {
+                try!(write_be(w, l as u64, 8));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_string<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    try!(write_be(w, s.len() as u64, 2));
+    w.write_all(s.as_bytes())
+}
+
+// the low `len` bytes of `value`, most significant first
+fn write_be<W: Write>(w: &mut W, value: u64, len: usize) -> io::Result<()> {
+    let bytes: Vec<u8> = (0..len).rev().map(|i| (value >> (i * 8)) as u8).collect();
+    w.write_all(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nbtrs::{Tag, Taglike};
+
+    #[test]
+    fn test_round_trip() {
+        let tag = compound(vec![("name", Tag::TagString("Steve".to_owned())),
+                                ("health", Tag::TagFloat(20.0)),
+                                ("pos", Tag::TagList(vec![Tag::TagDouble(-1.5), Tag::TagDouble(64.0)])),
+                                ("empty", Tag::TagList(Vec::new())),
+                                ("data", Tag::TagLongArray(vec![-1, 1 << 40])),
+                                ("uuid", Tag::TagIntArray(vec![1, 2, 3, 4])),
+                                ("flag", Tag::TagByte(-1)),
+                                ("nested", compound(vec![("y", Tag::TagShort(-300))]))]);
+        let bytes = to_bytes(&tag);
+        let (name, parsed) = Tag::parse(&mut &bytes[..]).unwrap();
+        assert_eq!(name, "");
+        assert_eq!(parsed.key("name").as_string().unwrap(), "Steve");
+        assert_eq!(parsed.key("pos").index(0).as_f64().unwrap(), -1.5);
+        assert_eq!(parsed.key("data").as_longs().unwrap(), &vec![-1, 1 << 40]);
+        assert_eq!(parsed.key("flag").as_i8().unwrap(), -1);
+        assert_eq!(parsed.key("nested").key("y").as_i16().unwrap(), -300);
+        assert_eq!(to_bytes(&parsed), bytes);
+    }
+}
@@ -0,0 +1,362 @@
+//! Points of interest, for markers on the web map
+//!
+//! Every block entity (signs, chests, banners, beacons...) and entity in a dimension is a
+//! potential point of interest.  [`MarkerFilter`]s, usually from the config file, pick out the
+//! ones worth showing, and [`markers_json`] turns the result into the file the web viewer reads.
+//!
+//! Players are points of interest too, with the ID `minecraft:player` where they were last seen
+//! and `overviewer:player_spawn` at their bed.
+//!
+//! [`MarkerFilter`]: struct.MarkerFilter.html
+//! [`markers_json`]: fn.markers_json.html
+
+use rio;
+use serde_json::{self, Value};
+use std::io::{Read, Seek};
+
+use block_entity::BlockEntity;
+use entity::Entity;
+use world::{Dimension, Player, Regionset};
+
+/// A single block entity or entity
+#[derive(Debug, Clone, PartialEq)]
+pub struct Poi {
+    /// The namespaced ID, like `minecraft:sign` or `minecraft:villager`
+    pub id: String,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+
+    /// Sign text, or the custom name given with a name tag or anvil
+    pub text: Option<String>,
+    pub is_entity: bool,
+}
+
+impl Poi {
+    pub fn from_block_entity(block_entity: &BlockEntity) -> Poi {
+        Poi {
+            id: block_entity.id.clone(),
+            x: block_entity.x as f64,
+            y: block_entity.y as f64,
+            z: block_entity.z as f64,
+            text: block_entity.text(),
+            is_entity: false,
+        }
+    }
+
+    pub fn from_entity(entity: &Entity) -> Poi {
+        Poi {
+            id: entity.id.clone(),
+            x: entity.x,
+            y: entity.y,
+            z: entity.z,
+            text: entity.custom_name.clone(),
+            is_entity: true,
+        }
+    }
+
+    /// The points for a player in `dim`: where they are, and where they'll respawn
+    pub fn from_player(player: &Player, dim: Dimension) -> Vec<Poi> {
+        let text = player.name.clone().or_else(|| player.uuid.clone());
+        let mut pois = Vec::new();
+        if player.dimension == Some(dim) {
+            pois.push(Poi {
+                id: "minecraft:player".to_owned(),
+                x: player.pos.x as f64,
+                y: player.pos.y as f64,
+                z: player.pos.z as f64,
+                text: text.clone(),
+                is_entity: true,
+            });
+        }
+        if let Some((spawn_dim, pos)) = player.spawn {
+            if spawn_dim == dim {
+                pois.push(Poi {
+                    id: "overviewer:player_spawn".to_owned(),
+                    x: pos.x as f64,
+                    y: pos.y as f64,
+                    z: pos.z as f64,
+                    text: text,
+                    is_entity: false,
+                });
+            }
+        }
+        pois
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "x": self.x,
+            "y": self.y,
+            "z": self.z,
+            "text": self.text,
+        })
+    }
+}
+
+/// Puts IDs from before 1.11 (`Sign`, `EnderChest`) into the modern form (`minecraft:sign`,
+/// `minecraft:ender_chest`)
+pub fn normalize_id(id: &str) -> String {
+    if id.contains(':') {
+        return id.to_owned();
+    }
+    let mut out = "minecraft:".to_owned();
+    for (i, c) in id.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Flattens a JSON text component into plain text.  Strings that aren't JSON (as written before
+/// Minecraft 1.8) are returned as-is.
+pub fn text_component(s: &str) -> String {
+    fn flatten(json: &Value, out: &mut String) {
+        match *json {
+            Value::String(ref s) => out.push_str(s),
+            Value::Array(ref items) => {
+                for item in items {
+                    flatten(item, out);
+                }
+            }
+            Value::Object(_) => {
+                if let Some(text) = json.get("text") {
+                    flatten(text, out);
+                }
+                if let Some(extra) = json.get("extra") {
+                    flatten(extra, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match serde_json::from_str(s) {
+        Ok(json) => {
+            let mut out = String::new();
+            flatten(&json, &mut out);
+            out
+        }
+        Err(_) => s.to_owned(),
+    }
+}
+
+/// Picks out the points of interest that make up one group of markers
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct MarkerFilter {
+    pub name: String,
+    pub title: String,
+
+    /// IDs to match, like `minecraft:sign`.  A `*` at the start or end matches anything, so
+    /// `*_banner` matches every banner.  IDs without a namespace are taken to be `minecraft:`.
+    pub ids: Vec<String>,
+
+    /// Only match points whose text starts with this.  The prefix is removed from the marker's
+    /// text, so that `[home]` on a sign can mark a home without showing up on the map.
+    pub text_prefix: Option<String>,
+
+    /// Only match points whose text contains this
+    pub text_contains: Option<String>,
+
+    /// The URL of an icon for the web viewer to use, relative to the output directory
+    pub icon: Option<String>,
+}
+
+impl MarkerFilter {
+    pub fn new(name: &str, ids: Vec<String>) -> MarkerFilter {
+        MarkerFilter {
+            name: name.to_owned(),
+            title: name.to_owned(),
+            ids: ids,
+            text_prefix: None,
+            text_contains: None,
+            icon: None,
+        }
+    }
+
+    /// Returns the marker to show for `poi`, or `None` if it doesn't match
+    pub fn apply(&self, poi: &Poi) -> Option<Poi> {
+        if !self.ids.iter().any(|pattern| id_matches(pattern, &poi.id)) {
+            return None;
+        }
+        let mut poi = poi.clone();
+        if let Some(ref prefix) = self.text_prefix {
+            match poi.text {
+                Some(ref text) if text.starts_with(&prefix[..]) => {}
+                _ => return None,
+            }
+            poi.text = poi.text.map(|t| t[prefix.len()..].trim().to_owned());
+        }
+        if let Some(ref needle) = self.text_contains {
+            if !poi.text.as_ref().map_or(false, |t| t.contains(&needle[..])) {
+                return None;
+            }
+        }
+        Some(poi)
+    }
+}
+
+fn id_matches(pattern: &str, id: &str) -> bool {
+    let pattern = if pattern.contains(':') || pattern.starts_with('*') {
+        pattern.to_owned()
+    } else {
+        format!("minecraft:{}", pattern)
+    };
+    match (pattern.starts_with('*'), pattern.ends_with('*')) {
+        (true, true) if pattern.len() > 1 => id.contains(&pattern[1..pattern.len() - 1]),
+        (true, true) => true,
+        (true, false) => id.ends_with(&pattern[1..]),
+        (false, true) => id.starts_with(&pattern[..pattern.len() - 1]),
+        (false, false) => id == pattern,
+    }
+}
+
+/// Walks every chunk of a dimension, returning the markers for each filter, in the same order as
+/// `filters`
+///
+/// `entities` is the separate entity regionset that Minecraft 1.17 and later write, if there is
+/// one.  `extra` holds points that aren't stored in chunks, such as players.
+pub fn collect_markers<'fs, FS>(rset: &Regionset<'fs, FS>,
+                                entities: Option<&Regionset<'fs, FS>>,
+                                extra: &[Poi],
+                                filters: &[MarkerFilter])
+                                -> Vec<Vec<Poi>>
+    where FS: rio::FSRead<'fs>,
+          FS::ReadFile: Read + Seek
+{
+    let mut groups = vec![Vec::new(); filters.len()];
+    {
+        let mut add = |poi: Option<Poi>| {
+            if let Some(poi) = poi {
+                for (filter, group) in filters.iter().zip(groups.iter_mut()) {
+                    if let Some(marker) = filter.apply(&poi) {
+                        group.push(marker);
+                    }
+                }
+            }
+        };
+
+        for poi in extra {
+            add(Some(poi.clone()));
+        }
+
+        for info in rset.get_chunks() {
+            if let Some(chunk) = rset.get_chunk(info.pos) {
+                for block_entity in chunk.block_entities() {
+                    add(Some(Poi::from_block_entity(&block_entity)));
+                }
+                for entity in chunk.entities() {
+                    add(Some(Poi::from_entity(&entity)));
+                }
+            }
+        }
+
+        if let Some(entities) = entities {
+            for info in entities.get_chunks() {
+                if let Some(chunk) = entities.get_chunk(info.pos) {
+                    for entity in chunk.entities() {
+                        add(Some(Poi::from_entity(&entity)));
+                    }
+                }
+            }
+        }
+    }
+    groups
+}
+
+/// The markers file for one render, as the web viewer expects it
+pub fn markers_json(filters: &[MarkerFilter], groups: &[Vec<Poi>]) -> Value {
+    let groups: Vec<Value> = filters.iter()
+        .zip(groups.iter())
+        .map(|(filter, markers)| {
+            json!({
+                "name": filter.name,
+                "title": filter.title,
+                "icon": filter.icon,
+                "markers": markers.iter().map(Poi::to_json).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    json!({ "groups": groups })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sign(text: &str) -> Poi {
+        Poi {
+            id: "minecraft:sign".to_owned(),
+            x: 1.0,
+            y: 64.0,
+            z: -3.0,
+            text: Some(text.to_owned()),
+            is_entity: false,
+        }
+    }
+
+    #[test]
+    fn test_normalize_id() {
+        assert_eq!(normalize_id("Sign"), "minecraft:sign");
+        assert_eq!(normalize_id("EnderChest"), "minecraft:ender_chest");
+        assert_eq!(normalize_id("minecraft:chest"), "minecraft:chest");
+    }
+
+    #[test]
+    fn test_text_component() {
+        assert_eq!(text_component("plain text"), "plain text");
+        assert_eq!(text_component(r#""quoted""#), "quoted");
+        assert_eq!(text_component(r#"{"text":"Hello ","extra":[{"text":"world"},"!"]}"#),
+                   "Hello world!");
+        assert_eq!(text_component(r#"{"text":""}"#), "");
+        // characters outside the BMP come escaped as surrogate pairs
+        assert_eq!(text_component(r#"{"text":"Spawn \ud83c\udfe0"}"#), "Spawn \u{1f3e0}");
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut homes = MarkerFilter::new("homes", vec!["sign".to_owned(), "*_sign".to_owned()]);
+        homes.text_prefix = Some("[home]".to_owned());
+        assert_eq!(homes.apply(&sign("[home]\nAlice")).and_then(|p| p.text),
+                   Some("Alice".to_owned()));
+        assert_eq!(homes.apply(&sign("Bob's shop")), None);
+
+        let mut oak = sign("[home] Carol");
+        oak.id = "minecraft:oak_wall_sign".to_owned();
+        assert!(homes.apply(&oak).is_some());
+
+        let mut shops = MarkerFilter::new("shops", vec!["*".to_owned()]);
+        shops.text_contains = Some("shop".to_owned());
+        assert!(shops.apply(&sign("Bob's shop")).is_some());
+        assert!(shops.apply(&sign("Nothing here")).is_none());
+
+        assert!(id_matches("*banner*", "minecraft:white_banner"));
+        assert!(id_matches("minecraft:*", "minecraft:chest"));
+        assert!(!id_matches("chest", "minecraft:ender_chest"));
+    }
+
+    #[test]
+    fn test_markers_json() {
+        let filters = vec![MarkerFilter::new("signs", vec!["sign".to_owned()])];
+        let json = markers_json(&filters, &[vec![sign("Hi")]]);
+        assert_eq!(json,
+                   json!({
+                       "groups": [{
+                           "name": "signs",
+                           "title": "signs",
+                           "icon": null,
+                           "markers": [
+                               {"id": "minecraft:sign", "x": 1.0, "y": 64.0, "z": -3.0, "text": "Hi"},
+                           ],
+                       }],
+                   }));
+    }
+}
@@ -0,0 +1,205 @@
+//! Deleting chunks that nobody has used
+//!
+//! Exploring generates far more chunks than players ever build in.  Minecraft keeps track of how
+//! long players have spent near each chunk in its `InhabitedTime`, which makes it easy to find the
+//! ones that were only ever passed through.  Deleted chunks are regenerated from the seed if
+//! anyone goes back, so pruning only loses what players changed.
+//!
+//! [`find_prunable`] decides what to delete without touching anything, and [`delete_chunks`]
+//! does the deleting.
+//!
+//! [`find_prunable`]: fn.find_prunable.html
+//! [`delete_chunks`]: fn.delete_chunks.html
+
+use rio;
+use std::io::{self, Read, Seek};
+
+use coords::{self, Coord};
+use heightmap::CropBox;
+use region::RegionWriter;
+use world::{Chunk, Dimension, Regionset};
+
+/// What to keep when pruning
+#[derive(Debug, Clone)]
+pub struct PruneOptions {
+    /// Chunks that players have spent fewer ticks than this near are deleted.  There are 20 ticks
+    /// in a second.
+    pub min_inhabited_time: i64,
+
+    /// Chunks overlapping any of these areas are always kept
+    pub protected: Vec<CropBox>,
+
+    /// Keep chunks with signs that have been written on, or containers and banners that have been
+    /// renamed, since world generation never makes those
+    pub keep_named: bool,
+}
+
+impl PruneOptions {
+    pub fn new(min_inhabited_time: i64) -> PruneOptions {
+        PruneOptions {
+            min_inhabited_time: min_inhabited_time,
+            protected: Vec::new(),
+            keep_named: true,
+        }
+    }
+
+    /// Whether the chunk at `pos` should be deleted
+    ///
+    /// Chunks from before 1.6, which don't record their inhabited time, are always kept.
+    pub fn should_prune(&self, pos: Coord<coords::Chunk, coords::World>, chunk: &Chunk) -> bool {
+        let protected = self.protected.iter().any(|b| {
+            pos.x * 16 <= b.max_x && pos.x * 16 + 15 >= b.min_x && pos.z * 16 <= b.max_z &&
+            pos.z * 16 + 15 >= b.min_z
+        });
+        if protected {
+            return false;
+        }
+        match chunk.inhabited_time() {
+            Some(t) if t < self.min_inhabited_time => {}
+            _ => return false,
+        }
+        !(self.keep_named && chunk.block_entities().iter().any(|b| b.text().is_some()))
+    }
+}
+
+/// The chunks in `rset` that `options` would delete
+pub fn find_prunable<'fs, FS>(rset: &Regionset<'fs, FS>,
+                              options: &PruneOptions)
+                              -> Vec<Coord<coords::Chunk, coords::World>>
+    where FS: rio::FSRead<'fs>,
+          FS::ReadFile: Read + Seek
+{
+    rset.get_chunks()
+        .filter(|info| match rset.get_chunk(info.pos) {
+            Some(chunk) => options.should_prune(info.pos, &chunk),
+            None => false,
+        })
+        .map(|info| info.pos)
+        .collect()
+}
+
+/// Deletes `chunks` from the region files in `dir`, then compacts each file that was changed
+///
+/// Region files left with no chunks are removed.  Chunks in region files that don't exist are
+/// ignored.  Returns the number of chunks deleted.
+pub fn delete_chunks<'fs, FS, P>(fs: &FS,
+                                 dir: P,
+                                 chunks: &[Coord<coords::Chunk, coords::World>])
+                                 -> io::Result<usize>
+    where FS: rio::FSRead<'fs> + rio::FSWrite,
+          FS::ReadFile: Read,
+          P: AsRef<rio::Path>
+{
+    let dir = dir.as_ref();
+    let mut by_region: Vec<((i64, i64), Vec<(u8, u8)>)> = Vec::new();
+    for pos in chunks {
+        let (c, r) = pos.split::<coords::Region>();
+        let chunk = (c.x as u8, c.z as u8);
+        match by_region.iter().position(|e| e.0 == (r.x, r.z)) {
+            Some(i) => by_region[i].1.push(chunk),
+            None => by_region.push(((r.x, r.z), vec![chunk])),
+        }
+    }
+
+    let mut deleted = 0;
+    for ((rx, rz), chunks) in by_region {
+        let path = dir.join(format!("r.{}.{}.mca", rx, rz));
+        if !fs.exists(&path) {
+            continue;
+        }
+        let mut region = try!(RegionWriter::load(fs, &path));
+        for (x, z) in chunks {
+            if region.chunk_exists(x, z) {
+                try!(region.delete_chunk(x, z));
+                deleted += 1;
+            }
+        }
+        if (0..32).all(|x| (0..32).all(|z| !region.chunk_exists(x, z))) {
+            try!(fs.remove_file(&path));
+        } else {
+            try!(region.compact_and_truncate());
+            try!(region.save(fs, &path));
+        }
+    }
+    Ok(deleted)
+}
+
+/// Deletes `chunks` from every kind of region file a dimension has: blocks, entities (1.17 and
+/// later) and points of interest (1.14 and later)
+///
+/// Returns the number of block chunks deleted.
+pub fn delete_dimension_chunks<'fs, FS, P>(fs: &FS,
+                                           world_dir: P,
+                                           dim: Dimension,
+                                           chunks: &[Coord<coords::Chunk, coords::World>])
+                                           -> io::Result<usize>
+    where FS: rio::FSRead<'fs> + rio::FSWrite,
+          FS::ReadFile: Read,
+          P: AsRef<rio::Path>
+{
+    let world_dir = world_dir.as_ref();
+    try!(delete_chunks(fs, world_dir.join(dim.entities_dir()), chunks));
+    try!(delete_chunks(fs, world_dir.join(dim.poi_dir()), chunks));
+    delete_chunks(fs, world_dir.join(dim.region_dir()), chunks)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coords::Coord;
+    use fixtures::{TempWorld, WorldBuilder};
+    use heightmap::CropBox;
+    use nbt::compound;
+    use nbtrs::Tag;
+    use rio::FSRead;
+    use world::{Dimension, Regionset};
+
+    fn sample() -> WorldBuilder {
+        let mut builder = WorldBuilder::new("Prune");
+        builder.chunk(Dimension::Overworld, 0, 0).inhabited_time = 72000;
+        builder.chunk(Dimension::Overworld, 1, 0).inhabited_time = 10;
+        builder.chunk(Dimension::Overworld, -40, 3).inhabited_time = 0;
+        builder.chunk(Dimension::Overworld, 5, 5).inhabited_time = 0;
+        builder.chunk(Dimension::Overworld, 6, 6).inhabited_time = 0;
+        let sign = compound(vec![("id", Tag::TagString("minecraft:sign".to_owned())),
+                                 ("x", Tag::TagInt(100)),
+                                 ("y", Tag::TagInt(64)),
+                                 ("z", Tag::TagInt(100)),
+                                 ("Text1", Tag::TagString(r#"{"text":"Home"}"#.to_owned()))]);
+        builder.add_block_entity(Dimension::Overworld, 100, 100, sign);
+        builder
+    }
+
+    #[test]
+    fn test_find_prunable() {
+        let tmp = TempWorld::new(&sample()).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        let mut options = PruneOptions::new(1200);
+        options.protected.push(CropBox::new(80, 80, 95, 95));
+
+        let mut found: Vec<(i64, i64)> =
+            find_prunable(&rset, &options).iter().map(|c| (c.x, c.z)).collect();
+        found.sort();
+        assert_eq!(found, vec![(-40, 3), (1, 0)]);
+
+        options.keep_named = false;
+        options.protected.clear();
+        assert_eq!(find_prunable(&rset, &options).len(), 4);
+    }
+
+    #[test]
+    fn test_delete_chunks() {
+        let tmp = TempWorld::new(&sample()).unwrap();
+        let chunks = vec![Coord::new(1, 0, 0), Coord::new(-40, 0, 3), Coord::new(99, 0, 99)];
+        let deleted =
+            delete_dimension_chunks(&tmp.fs, tmp.path(), Dimension::Overworld, &chunks).unwrap();
+        assert_eq!(deleted, 2);
+
+        // the region holding only (-40, 3) is gone entirely
+        assert!(!tmp.fs.exists(tmp.path().join("region/r.-2.0.mca")));
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        assert_eq!(rset.get_chunks().count(), 3);
+        assert!(rset.get_chunk(Coord::new(1, 0, 0)).is_none());
+        assert!(rset.get_chunk(Coord::new(6, 0, 6)).is_some());
+    }
+}
@@ -0,0 +1,425 @@
+//! Writing region files
+//!
+//! A region file holds up to 32 by 32 chunks.  It starts with two 4 KiB header tables, one giving
+//! each chunk's location (an offset and a length, both counted in 4 KiB sectors) and one giving
+//! the time each chunk was last saved.  Chunk data follows in whole sectors, in any order, with
+//! gaps where chunks have been moved or deleted.
+//!
+//! [`RegionWriter`] edits these files in place: chunks are written where they fit, space freed by
+//! deleted or moved chunks is reused, and [`RegionWriter::compact`] squeezes the gaps out.  Files
+//! on a `rio` filesystem are edited in memory, with [`RegionWriter::load`] and
+//! [`RegionWriter::save`].
+//!
+//! [`RegionWriter`]: struct.RegionWriter.html
+//! [`RegionWriter::compact`]: struct.RegionWriter.html#method.compact
+//! [`RegionWriter::load`]: struct.RegionWriter.html#method.load
+//! [`RegionWriter::save`]: struct.RegionWriter.html#method.save
+
+use flate2;
+use nbtrs::Tag;
+use rio;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nbt::write_tag;
+
+/// The size of a sector, the unit that region files are allocated in
+pub const SECTOR_SIZE: usize = 4096;
+
+// the location and timestamp tables
+const HEADER_SECTORS: usize = 2;
+
+// a location's length is a single byte.  Minecraft puts bigger chunks in separate files, which we
+// don't write.
+const MAX_CHUNK_SECTORS: usize = 255;
+
+// the compression type byte that comes before each chunk's data
+const COMPRESSION_ZLIB: u8 = 2;
+
+/// Creates and edits a region file
+///
+/// Chunk positions are relative to the region, from 0 to 31.  Every change is written through to
+/// the file immediately.
+pub struct RegionWriter<F> {
+    file: F,
+
+    // (offset, count) in sectors for each chunk, indexed by x + z * 32.  A count of 0 means the
+    // chunk doesn't exist
+    locations: Vec<(usize, usize)>,
+    timestamps: Vec<u32>,
+}
+
+impl<F> RegionWriter<F>
+    where F: Read + Write + Seek
+{
+    /// Starts a new, empty region file in `file`, which should be empty
+    pub fn create(mut file: F) -> io::Result<RegionWriter<F>> {
+        try!(file.seek(SeekFrom::Start(0)));
+        try!(file.write_all(&[0; HEADER_SECTORS * SECTOR_SIZE]));
+        Ok(RegionWriter {
+            file: file,
+            locations: vec![(0, 0); 1024],
+            timestamps: vec![0; 1024],
+        })
+    }
+
+    /// Opens an existing region file for editing
+    ///
+    /// An empty file is treated as an empty region, and is given a header.
+    pub fn open(mut file: F) -> io::Result<RegionWriter<F>> {
+        let len = try!(file.seek(SeekFrom::End(0)));
+        if len == 0 {
+            return RegionWriter::create(file);
+        }
+        try!(file.seek(SeekFrom::Start(0)));
+        let mut header = vec![0; HEADER_SECTORS * SECTOR_SIZE];
+        try!(file.read_exact(&mut header));
+
+        let locations = header[..SECTOR_SIZE]
+            .chunks(4)
+            .map(|l| (be24(&l[..3]) as usize, l[3] as usize))
+            .map(|(offset, count)| if offset < HEADER_SECTORS { (0, 0) } else { (offset, count) })
+            .collect();
+        let timestamps = header[SECTOR_SIZE..]
+            .chunks(4)
+            .map(|t| (t[0] as u32) << 24 | (t[1] as u32) << 16 | (t[2] as u32) << 8 | t[3] as u32)
+            .collect();
+        Ok(RegionWriter {
+            file: file,
+            locations: locations,
+            timestamps: timestamps,
+        })
+    }
+
+    pub fn chunk_exists(&self, x: u8, z: u8) -> bool {
+        self.locations[index(x, z)].1 != 0
+    }
+
+    /// When the chunk was last written, in seconds since the unix epoch
+    pub fn get_timestamp(&self, x: u8, z: u8) -> Option<u32> {
+        let i = index(x, z);
+        if self.locations[i].1 == 0 {
+            None
+        } else {
+            Some(self.timestamps[i])
+        }
+    }
+
+    /// Compresses and writes a chunk, replacing any chunk already there, and stamps it with the
+    /// current time
+    pub fn write_chunk(&mut self, x: u8, z: u8, tag: &Tag) -> io::Result<()> {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::Default);
+        try!(write_tag(&mut encoder, "", tag));
+        let data = try!(encoder.finish());
+        try!(self.write_chunk_data(x, z, COMPRESSION_ZLIB, &data));
+        self.set_timestamp(x, z, now())
+    }
+
+    /// Writes already compressed chunk data, leaving the chunk's timestamp alone
+    ///
+    /// `compression` is the type byte Minecraft uses: 1 for gzip, 2 for zlib and 3 for none.
+    pub fn write_chunk_data(&mut self,
+                            x: u8,
+                            z: u8,
+                            compression: u8,
+                            data: &[u8])
+                            -> io::Result<()> {
+        // a four byte length, which counts the compression type too
+        let mut buf = Vec::with_capacity(data.len() + 5);
+        buf.extend_from_slice(&be32(data.len() as u32 + 1));
+        buf.push(compression);
+        buf.extend_from_slice(data);
+        let count = (buf.len() + SECTOR_SIZE - 1) / SECTOR_SIZE;
+        if count > MAX_CHUNK_SECTORS {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("Chunk {}, {} needs {} sectors, more than a region \
+                                               file can hold",
+                                              x,
+                                              z,
+                                              count)));
+        }
+        buf.resize(count * SECTOR_SIZE, 0);
+
+        // rewrite in place if it still fits, otherwise move it to the first gap that's big enough
+        let i = index(x, z);
+        let (old_offset, old_count) = self.locations[i];
+        let offset = if old_count >= count {
+            old_offset
+        } else {
+            self.locations[i] = (0, 0);
+            self.find_space(count)
+        };
+
+        try!(self.file.seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64)));
+        try!(self.file.write_all(&buf));
+        self.set_location(i, offset, count)
+    }
+
+    /// Removes a chunk.  Its sectors are reused by later writes, or reclaimed by [`compact`].
+    ///
+    /// [`compact`]: #method.compact
+    pub fn delete_chunk(&mut self, x: u8, z: u8) -> io::Result<()> {
+        let i = index(x, z);
+        try!(self.set_location(i, 0, 0));
+        self.set_timestamp(x, z, 0)
+    }
+
+    pub fn set_timestamp(&mut self, x: u8, z: u8, timestamp: u32) -> io::Result<()> {
+        let i = index(x, z);
+        self.timestamps[i] = timestamp;
+        try!(self.file.seek(SeekFrom::Start((SECTOR_SIZE + i * 4) as u64)));
+        self.file.write_all(&be32(timestamp))
+    }
+
+    /// Moves every chunk down to close the gaps between them, and returns the length in bytes
+    /// that the file can then be truncated to
+    pub fn compact(&mut self) -> io::Result<u64> {
+        let mut chunks: Vec<usize> = (0..1024).filter(|&i| self.locations[i].1 != 0).collect();
+        chunks.sort_by_key(|&i| self.locations[i].0);
+
+        // chunks only ever move towards the start of the file, so nothing gets overwritten
+        // before it has been copied
+        let mut next = HEADER_SECTORS;
+        for i in chunks {
+            let (offset, count) = self.locations[i];
+            if offset != next {
+                let mut buf = Vec::with_capacity(count * SECTOR_SIZE);
+                try!(self.file.seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64)));
+                // Minecraft doesn't always pad the last chunk in a file out to a whole sector
+                try!((&mut self.file).take((count * SECTOR_SIZE) as u64).read_to_end(&mut buf));
+                buf.resize(count * SECTOR_SIZE, 0);
+                try!(self.file.seek(SeekFrom::Start((next * SECTOR_SIZE) as u64)));
+                try!(self.file.write_all(&buf));
+                try!(self.set_location(i, next, count));
+            }
+            next += count;
+        }
+        try!(self.file.flush());
+        Ok((next * SECTOR_SIZE) as u64)
+    }
+
+    /// The sectors in use, including the header
+    pub fn used_sectors(&self) -> usize {
+        HEADER_SECTORS + self.locations.iter().map(|l| l.1).sum::<usize>()
+    }
+
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+
+    // the first run of `count` free sectors, which may be at the end of the file
+    fn find_space(&self, count: usize) -> usize {
+        let mut used: Vec<(usize, usize)> =
+            self.locations.iter().cloned().filter(|l| l.1 != 0).collect();
+        used.sort();
+        let mut start = HEADER_SECTORS;
+        for (offset, len) in used {
+            if offset >= start + count {
+                break;
+            }
+            start = ::std::cmp::max(start, offset + len);
+        }
+        start
+    }
+
+    fn set_location(&mut self, i: usize, offset: usize, count: usize) -> io::Result<()> {
+        self.locations[i] = (offset, count);
+        try!(self.file.seek(SeekFrom::Start((i * 4) as u64)));
+        let location = be32((offset as u32) << 8 | count as u32);
+        self.file.write_all(&location)
+    }
+}
+
+impl RegionWriter<Cursor<Vec<u8>>> {
+    /// Reads the region file at `path` into memory for editing, or starts an empty region if
+    /// there is no such file.  Nothing is written back until [`save`].
+    ///
+    /// [`save`]: #method.save
+    pub fn load<'fs, FS, P>(fs: &FS, path: P) -> io::Result<RegionWriter<Cursor<Vec<u8>>>>
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read,
+              P: AsRef<rio::Path>
+    {
+        let path = path.as_ref();
+        let mut data = Vec::new();
+        if fs.exists(path) {
+            try!(try!(fs.open(path)).read_to_end(&mut data));
+        }
+        RegionWriter::open(Cursor::new(data))
+    }
+
+    /// Compacts the region and drops the space that frees up at the end
+    pub fn compact_and_truncate(&mut self) -> io::Result<()> {
+        let len = try!(self.compact());
+        self.file.get_mut().truncate(len as usize);
+        Ok(())
+    }
+
+    /// Writes the region out to `path`, replacing whatever is there
+    ///
+    /// rio can't rename files, so the new region can't be swapped in atomically.  Instead it is
+    /// written in full to `<path>.tmp` first, which leaves the old region alone if that fails (on
+    /// a full disk, say), and which is only deleted once `path` has been rewritten.  If that
+    /// rewrite is interrupted, the `.tmp` file still holds the whole region.
+    pub fn save<W, P>(&self, fs: &W, path: P) -> io::Result<()>
+        where W: rio::FSWrite,
+              P: AsRef<rio::Path>
+    {
+        let path = path.as_ref();
+        let tmp = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => dir.join(format!("{}.tmp", name)),
+            _ => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                          format!("{:?} isn't a file name", path)))
+            }
+        };
+        let data = self.file.get_ref();
+        if let Err(e) = fs.create(&tmp).and_then(|mut f| f.write_all(data)) {
+            let _ = fs.remove_file(&tmp);
+            return Err(e);
+        }
+        try!(try!(fs.create(path)).write_all(data));
+        fs.remove_file(&tmp)
+    }
+}
+
+/// The current time, as region file timestamps count it
+pub fn now() -> u32 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as u32).unwrap_or(0)
+}
+
+// positions wrap around, like they do in Minecraft
+fn index(x: u8, z: u8) -> usize {
+    (x as usize & 31) + (z as usize & 31) * 32
+}
+
+fn be24(b: &[u8]) -> u32 {
+    (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32
+}
+
+fn be32(v: u32) -> [u8; 4] {
+    [(v >> 24) as u8, (v >> 16) as u8, (v >> 8) as u8, v as u8]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use memfs::MemFS;
+    use nbt::compound;
+    use nbtrs::{RegionFile, Tag, Taglike};
+    use rio::{FSRead, FSWrite};
+    use std::io::{Cursor, Read};
+
+    // a chunk whose payload won't compress, so it takes up about `len` bytes
+    fn chunk(id: i32, len: usize) -> Tag {
+        let mut state = id as u32 + 1;
+        let noise = (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        compound(vec![("id", Tag::TagInt(id)), ("noise", Tag::TagByteArray(noise))])
+    }
+
+    fn read_id(data: &[u8], x: u8, z: u8) -> Option<i32> {
+        let mut region = RegionFile::new(Cursor::new(data.to_vec())).unwrap();
+        if !region.chunk_exists(x, z) {
+            return None;
+        }
+        region.load_chunk(x, z).unwrap().key("id").as_i32().ok()
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let mut writer = RegionWriter::create(Cursor::new(Vec::new())).unwrap();
+        writer.write_chunk(0, 0, &chunk(1, 10)).unwrap();
+        writer.write_chunk(31, 31, &chunk(2, 5000)).unwrap();
+        writer.set_timestamp(31, 31, 1454034069).unwrap();
+        assert_eq!(writer.used_sectors(), 2 + 1 + 2);
+
+        // reopening sees the same header
+        let writer = RegionWriter::open(writer.into_inner()).unwrap();
+        assert!(writer.chunk_exists(31, 31));
+        assert!(!writer.chunk_exists(1, 0));
+        assert_eq!(writer.get_timestamp(31, 31), Some(1454034069));
+
+        let data = writer.into_inner().into_inner();
+        assert_eq!(data.len(), 5 * SECTOR_SIZE);
+        assert_eq!(read_id(&data, 0, 0), Some(1));
+        assert_eq!(read_id(&data, 31, 31), Some(2));
+        let region = RegionFile::new(Cursor::new(data)).unwrap();
+        assert_eq!(region.get_chunk_timestamp(31, 31), Some(1454034069));
+    }
+
+    #[test]
+    fn test_reuse_and_compact() {
+        let mut writer = RegionWriter::create(Cursor::new(Vec::new())).unwrap();
+        writer.write_chunk(0, 0, &chunk(1, 5000)).unwrap();
+        writer.write_chunk(1, 0, &chunk(2, 10)).unwrap();
+        writer.write_chunk(2, 0, &chunk(3, 10)).unwrap();
+
+        // growing a chunk moves it to the end, and a small chunk fills the gap it left
+        writer.write_chunk(1, 0, &chunk(4, 9000)).unwrap();
+        writer.write_chunk(3, 0, &chunk(5, 10)).unwrap();
+        assert_eq!(writer.locations[index(3, 0)], (4, 1));
+
+        writer.delete_chunk(0, 0).unwrap();
+        assert!(!writer.chunk_exists(0, 0));
+        assert_eq!(writer.get_timestamp(0, 0), None);
+
+        let len = writer.compact().unwrap();
+        assert_eq!(len as usize, writer.used_sectors() * SECTOR_SIZE);
+        let mut data = writer.into_inner().into_inner();
+        data.truncate(len as usize);
+        assert_eq!(read_id(&data, 0, 0), None);
+        assert_eq!(read_id(&data, 1, 0), Some(4));
+        assert_eq!(read_id(&data, 2, 0), Some(3));
+        assert_eq!(read_id(&data, 3, 0), Some(5));
+    }
+
+    #[test]
+    fn test_compact_unpadded() {
+        let mut writer = RegionWriter::create(Cursor::new(Vec::new())).unwrap();
+        writer.write_chunk(0, 0, &chunk(1, 10)).unwrap();
+        writer.write_chunk(1, 0, &chunk(2, 10)).unwrap();
+        writer.delete_chunk(0, 0).unwrap();
+
+        // cut the last sector short, as if it had never been padded
+        let mut data = writer.into_inner().into_inner();
+        let len = data.len() - SECTOR_SIZE + 100;
+        data.truncate(len);
+        let mut writer = RegionWriter::open(Cursor::new(data)).unwrap();
+        writer.compact_and_truncate().unwrap();
+        let data = writer.into_inner().into_inner();
+        assert_eq!(data.len(), 3 * SECTOR_SIZE);
+        assert_eq!(read_id(&data, 1, 0), Some(2));
+    }
+
+    #[test]
+    fn test_save() {
+        let fs = MemFS::new();
+        fs.create_dir_all("region").unwrap();
+        let mut writer = RegionWriter::load(&fs, "region/r.0.0.mca").unwrap();
+        writer.write_chunk(0, 0, &chunk(1, 10)).unwrap();
+        writer.save(&fs, "region/r.0.0.mca").unwrap();
+
+        // saving over an existing region leaves nothing else behind
+        let mut writer = RegionWriter::load(&fs, "region/r.0.0.mca").unwrap();
+        writer.write_chunk(1, 0, &chunk(2, 10)).unwrap();
+        writer.save(&fs, "region/r.0.0.mca").unwrap();
+        assert!(!fs.exists("region/r.0.0.mca.tmp"));
+
+        let mut data = Vec::new();
+        fs.open("region/r.0.0.mca").unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(read_id(&data, 0, 0), Some(1));
+        assert_eq!(read_id(&data, 1, 0), Some(2));
+    }
+
+    #[test]
+    fn test_chunk_too_big() {
+        let mut writer = RegionWriter::create(Cursor::new(Vec::new())).unwrap();
+        assert!(writer.write_chunk(0, 0, &chunk(1, 256 * SECTOR_SIZE)).is_err());
+        assert!(!writer.chunk_exists(0, 0));
+    }
+}
@@ -0,0 +1,124 @@
+//! The cave render mode
+//!
+//! Hides everything on the surface and every block that can see the sky, which leaves the floors,
+//! walls and ceilings of underground spaces.  Optionally tints what's left by depth, so that
+//! overlapping cave systems can be told apart.
+
+use image::Rgba;
+
+use coords::{self, Coord};
+use super::{offset, BlockSource, Face, RenderPrimitive};
+
+const FACES: [Face; 6] = [Face::Top, Face::Bottom, Face::North, Face::South, Face::East, Face::West];
+
+/// Only shows blocks that border a cave
+pub struct Cave {
+    /// If set, tint blocks from red at the first Y level to blue at the second
+    pub depth_tinting: Option<(i64, i64)>,
+}
+
+impl Cave {
+    pub fn new() -> Cave {
+        Cave { depth_tinting: None }
+    }
+
+    pub fn with_depth_tinting(min_y: i64, max_y: i64) -> Cave {
+        Cave { depth_tinting: Some((min_y, max_y)) }
+    }
+}
+
+impl RenderPrimitive for Cave {
+    fn hidden(&self, world: &BlockSource, pos: Coord<coords::Block, coords::World>) -> bool {
+        // the surface itself, and anything above it, isn't underground
+        if pos.y >= world.get_height(pos.x, pos.z) - 1 {
+            return true;
+        }
+
+        // a block is part of a cave if it touches an open space that the sky can't reach
+        let mut borders_cave = false;
+        for face in FACES.iter() {
+            let neighbor = offset(pos, face.offset());
+            if world.get_block(neighbor).is_opaque() {
+                continue;
+            }
+            if world.get_sky_light(neighbor) > 0 {
+                return true;
+            }
+            borders_cave = true;
+        }
+        !borders_cave
+    }
+
+    fn draw(&self,
+            _world: &BlockSource,
+            pos: Coord<coords::Block, coords::World>,
+            _face: Face,
+            color: &mut Rgba<u8>) {
+        if let Some((min_y, max_y)) = self.depth_tinting {
+            let depth = (pos.y - min_y) as f32 / ::std::cmp::max(1, max_y - min_y) as f32;
+            let tint = depth_color(depth);
+            for i in 0..3 {
+                color[i] = ((color[i] as u32 + tint[i] as u32) / 2) as u8;
+            }
+        }
+    }
+}
+
+// Maps 0.0 - 1.0 onto a hue ramp from red, through green, to blue
+fn depth_color(depth: f32) -> [u8; 3] {
+    let hue = depth.max(0.0).min(1.0) * 4.0;
+    let rising = ((hue % 1.0) * 255.0) as u8;
+    let falling = 255 - rising;
+    match hue as u32 {
+        0 => [255, rising, 0],
+        1 => [falling, 255, 0],
+        2 => [0, 255, rising],
+        3 => [0, falling, 255],
+        _ => [0, 0, 255],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coords::Coord;
+    use render::RenderPrimitive;
+    use render::test::TestWorld;
+
+    #[test]
+    fn test_cave_hidden() {
+        let mut world = TestWorld::default();
+        for x in -1..2 {
+            for z in -1..2 {
+                for y in 0..11 {
+                    world.set((x, y, z), "stone", 0, 0);
+                }
+            }
+        }
+        world.set((0, 5, 0), "cave_air", 0, 0);
+        // an opening to the sky on the side of the column
+        world.set((1, 8, 0), "air", 0, 12);
+
+        let cave = Cave::new();
+        assert!(!cave.hidden(&world, Coord::new(0, 4, 0)));
+        assert!(!cave.hidden(&world, Coord::new(0, 6, 0)));
+        // solid rock doesn't border any cave
+        assert!(cave.hidden(&world, Coord::new(0, 2, 0)));
+        // the surface
+        assert!(cave.hidden(&world, Coord::new(0, 10, 0)));
+        // exposed to the sky through the opening
+        assert!(cave.hidden(&world, Coord::new(0, 8, 0)));
+    }
+
+    #[test]
+    fn test_depth_tinting() {
+        assert_eq!(depth_color(0.0), [255, 0, 0]);
+        assert_eq!(depth_color(0.5), [0, 255, 0]);
+        assert_eq!(depth_color(1.0), [0, 0, 255]);
+
+        let world = TestWorld::default();
+        let mut color = Rgba([255, 255, 255, 255]);
+        Cave::with_depth_tinting(0, 64).draw(&world, Coord::new(0, 0, 0), Face::Top, &mut color);
+        assert_eq!(color, Rgba([255, 127, 127, 255]));
+    }
+}
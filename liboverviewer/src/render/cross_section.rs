@@ -0,0 +1,100 @@
+//! The cross-section render mode
+//!
+//! Slices the world at a given Y level so that only what's at or below the slice is drawn.  Blocks
+//! whose tops were covered by something that got sliced away are highlighted, which makes the cut
+//! stand out from floors that were already open to the air.
+
+use image::Rgba;
+
+use coords::{self, Coord};
+use super::{offset, BlockSource, Face, RenderPrimitive};
+
+/// The Y level of the top of the sea, where a cross section is cut unless told otherwise
+pub const SEA_LEVEL: i64 = 62;
+
+/// What the cut is highlighted with by default: red
+pub const DEFAULT_HIGHLIGHT: u32 = 0xff0000;
+
+/// Only draws blocks between two Y levels
+pub struct CrossSection {
+    /// The highest Y level that gets drawn
+    pub max_y: i64,
+
+    /// The lowest Y level that gets drawn, if any
+    pub min_y: Option<i64>,
+
+    /// Color (as `0xRRGGBB`) to blend into the top of blocks that were cut, if any
+    pub highlight: Option<u32>,
+}
+
+impl CrossSection {
+    /// Slices at `max_y`, highlighting the cut in red
+    pub fn new(max_y: i64) -> CrossSection {
+        CrossSection {
+            max_y: max_y,
+            min_y: None,
+            highlight: Some(DEFAULT_HIGHLIGHT),
+        }
+    }
+}
+
+impl RenderPrimitive for CrossSection {
+    fn hidden(&self, _world: &BlockSource, pos: Coord<coords::Block, coords::World>) -> bool {
+        pos.y > self.max_y || self.min_y.map(|min_y| pos.y < min_y).unwrap_or(false)
+    }
+
+    fn draw(&self,
+            world: &BlockSource,
+            pos: Coord<coords::Block, coords::World>,
+            face: Face,
+            color: &mut Rgba<u8>) {
+        let highlight = match self.highlight {
+            Some(highlight) => highlight,
+            None => return,
+        };
+        if face != Face::Top || pos.y != self.max_y {
+            return;
+        }
+        if world.get_block(offset(pos, Face::Top.offset())).is_air() {
+            return;
+        }
+
+        let highlight = [(highlight >> 16) & 0xff, (highlight >> 8) & 0xff, highlight & 0xff];
+        for i in 0..3 {
+            color[i] = ((color[i] as u32 + highlight[i]) / 2) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coords::Coord;
+    use render::{Face, RenderPrimitive};
+    use render::test::TestWorld;
+
+    #[test]
+    fn test_cross_section() {
+        let mut world = TestWorld::default();
+        world.set((0, 10, 0), "stone", 0, 0);
+        world.set((0, 11, 0), "stone", 0, 0);
+        world.set((1, 10, 0), "stone", 0, 15);
+
+        let mut slice = CrossSection::new(10);
+        slice.min_y = Some(5);
+        assert!(slice.hidden(&world, Coord::new(0, 11, 0)));
+        assert!(!slice.hidden(&world, Coord::new(0, 10, 0)));
+        assert!(!slice.hidden(&world, Coord::new(0, 5, 0)));
+        assert!(slice.hidden(&world, Coord::new(0, 4, 0)));
+
+        // the cut goes through (0, 11, 0), so (0, 10, 0) gets highlighted
+        let mut color = Rgba([0, 0, 0, 255]);
+        slice.draw(&world, Coord::new(0, 10, 0), Face::Top, &mut color);
+        assert_eq!(color, Rgba([127, 0, 0, 255]));
+
+        // nothing was above (1, 10, 0) to begin with
+        let mut color = Rgba([0, 0, 0, 255]);
+        slice.draw(&world, Coord::new(1, 10, 0), Face::Top, &mut color);
+        assert_eq!(color, Rgba([0, 0, 0, 255]));
+    }
+}
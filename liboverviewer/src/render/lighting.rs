@@ -0,0 +1,155 @@
+//! Lighting primitives
+//!
+//! These darken each face according to the light level of the block in front of it, which is
+//! where the light hitting that face comes from.  With only the top-down renderer, that's the
+//! block above.
+
+use coords::{self, Coord};
+use super::{offset, BlockSource, Face, RenderPrimitive};
+
+/// Darkens faces based on the sky light and block light in front of them
+///
+/// The "night" variant ignores sky light completely, so only torches, lava and other light
+/// sources light up the map.
+pub struct Lighting {
+    /// How much to darken, from 0.0 (not at all) to 1.0 (fully)
+    pub strength: f32,
+
+    /// If true, ignore sky light
+    pub night: bool,
+}
+
+impl Lighting {
+    pub fn new() -> Lighting {
+        Lighting {
+            strength: 1.0,
+            night: false,
+        }
+    }
+
+    pub fn night() -> Lighting {
+        Lighting {
+            strength: 1.0,
+            night: true,
+        }
+    }
+
+    /// The light level at `pos` that this primitive uses: the brighter of sky light and block
+    /// light, or just block light at night
+    pub fn light_level(&self, world: &BlockSource, pos: Coord<coords::Block, coords::World>) -> u8 {
+        let block = world.get_block_light(pos);
+        if self.night {
+            block
+        } else {
+            ::std::cmp::max(block, world.get_sky_light(pos))
+        }
+    }
+
+    /// Converts a light level (0.0 - 15.0) to a brightness multiplier.  Each level below full
+    /// brightness is 80% as bright as the level above it.
+    pub fn brightness(&self, level: f32) -> f32 {
+        let darkness = 1.0 - 0.8f32.powf(15.0 - level);
+        1.0 - self.strength * darkness
+    }
+}
+
+impl RenderPrimitive for Lighting {
+    fn shade(&self,
+             world: &BlockSource,
+             pos: Coord<coords::Block, coords::World>,
+             face: Face)
+             -> [f32; 4] {
+        let level = self.light_level(world, offset(pos, face.offset()));
+        [self.brightness(level as f32); 4]
+    }
+}
+
+/// Like [`Lighting`], but interpolates the light at each corner of a face from the blocks that
+/// share that corner, which gives soft shadows instead of hard per-block steps
+///
+/// [`Lighting`]: struct.Lighting.html
+pub struct SmoothLighting {
+    pub lighting: Lighting,
+}
+
+impl SmoothLighting {
+    pub fn new(lighting: Lighting) -> SmoothLighting {
+        SmoothLighting { lighting: lighting }
+    }
+}
+
+impl RenderPrimitive for SmoothLighting {
+    fn shade(&self,
+             world: &BlockSource,
+             pos: Coord<coords::Block, coords::World>,
+             face: Face)
+             -> [f32; 4] {
+        let front = offset(pos, face.offset());
+        let (u, v) = face.axes();
+        let center = self.lighting.light_level(world, front) as f32;
+
+        let mut corners = [0.0; 4];
+        for (i, &(su, sv)) in [(-1, -1), (1, -1), (1, 1), (-1, 1)].iter().enumerate() {
+            // average the light of the non-opaque blocks in front of the face that touch this corner
+            let mut total = center;
+            let mut count = 1.0;
+            for &(a, b) in &[(su, 0), (0, sv), (su, sv)] {
+                let delta = (u.0 * a + v.0 * b, u.1 * a + v.1 * b, u.2 * a + v.2 * b);
+                let p = offset(front, delta);
+                if !world.get_block(p).is_opaque() {
+                    total += self.lighting.light_level(world, p) as f32;
+                    count += 1.0;
+                }
+            }
+            corners[i] = self.lighting.brightness(total / count);
+        }
+        corners
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coords::Coord;
+    use render::{Face, RenderPrimitive};
+    use render::test::TestWorld;
+
+    #[test]
+    fn test_lighting() {
+        let mut world = TestWorld::default();
+        world.set((0, 0, 0), "stone", 0, 0);
+        // a torch-lit, roofed-over spot in front of the north face
+        world.set((0, 0, -1), "air", 14, 0);
+
+        let lighting = Lighting::new();
+        assert_eq!(lighting.shade(&world, Coord::new(0, 0, 0), Face::Top), [1.0; 4]);
+        let north = lighting.shade(&world, Coord::new(0, 0, 0), Face::North);
+        assert!((north[0] - 0.8).abs() < 1e-6);
+
+        // at night, open sky is completely dark
+        let night = Lighting::night();
+        let top = night.shade(&world, Coord::new(0, 0, 0), Face::Top);
+        assert!((top[0] - 0.8f32.powi(15)).abs() < 1e-6);
+        assert_eq!(night.shade(&world, Coord::new(0, 0, 0), Face::North), north);
+
+        assert_eq!(Lighting { strength: 0.0, night: true }.brightness(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_smooth_lighting() {
+        let mut world = TestWorld::default();
+        world.set((0, 0, 0), "stone", 0, 0);
+        // a dark, transparent block at the -x,-z corner above the block
+        world.set((-1, 1, -1), "glass", 0, 0);
+        // an opaque block at the +x side doesn't count towards the average
+        world.set((1, 1, 0), "stone", 0, 0);
+
+        let smooth = SmoothLighting::new(Lighting::new());
+        let corners = smooth.shade(&world, Coord::new(0, 0, 0), Face::Top);
+        let lighting = Lighting::new();
+        assert_eq!(corners[0], lighting.brightness(45.0 / 4.0));
+        assert_eq!(corners[1], 1.0);
+        assert_eq!(corners[2], 1.0);
+        assert_eq!(corners[3], 1.0);
+    }
+}
@@ -0,0 +1,581 @@
+//! Render modes, built out of render primitives
+//!
+//! A render mode is a stack of [`RenderPrimitive`]s.  Each primitive gets a chance to hide a
+//! block entirely, to shade the corners of each face that gets drawn, and to recolor that face.
+//! This is the same model classic Overviewer uses, and it keeps the primitives independent of the
+//! renderer that draws the faces; renderers hand primitives a [`BlockSource`] to look at the
+//! world through.
+//!
+//! The only renderer so far is the top-down one in [`topdown`], which draws nothing but the top
+//! face of each column.  There is no isometric renderer yet, so the parts of primitives that deal
+//! with side faces are only exercised by their tests.
+//!
+//! [`RenderPrimitive`]: trait.RenderPrimitive.html
+//! [`topdown`]: topdown/index.html
+//! [`BlockSource`]: trait.BlockSource.html
+
+use image::{self, Rgba};
+use rio;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Read, Seek, Write};
+use std::rc::Rc;
+
+use super::asset_manager::Texture;
+use super::biome::Biome;
+use super::blocks::BlockState;
+use super::coords::{self, Coord};
+use super::error::OverviewerError;
+use super::heightmap::CropBox;
+use super::world::{Chunk, HeightmapType, Regionset};
+
+pub mod cave;
+pub mod cross_section;
+pub mod lighting;
+pub mod palette;
+pub mod slime;
+pub mod spawn;
+pub mod topdown;
+
+pub use self::cave::Cave;
+pub use self::cross_section::CrossSection;
+use self::cross_section::{DEFAULT_HIGHLIGHT, SEA_LEVEL};
+pub use self::lighting::{Lighting, SmoothLighting};
+pub use self::palette::BlockPalette;
+pub use self::slime::SlimeChunks;
+pub use self::spawn::MobSpawns;
+pub use self::topdown::TopDown;
+use self::topdown::TILE_BLOCKS;
+
+/// One of the six faces of a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    Top,
+    Bottom,
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Face {
+    /// The offset from a block to the neighbor that this face looks at
+    pub fn offset(&self) -> (i64, i64, i64) {
+        match *self {
+            Face::Top => (0, 1, 0),
+            Face::Bottom => (0, -1, 0),
+            Face::North => (0, 0, -1),
+            Face::South => (0, 0, 1),
+            Face::East => (1, 0, 0),
+            Face::West => (-1, 0, 0),
+        }
+    }
+
+    /// The two axes that lie in the plane of this face, as unit offsets.  Face corners are given
+    /// in the order (-u, -v), (+u, -v), (+u, +v), (-u, +v).
+    pub fn axes(&self) -> ((i64, i64, i64), (i64, i64, i64)) {
+        match *self {
+            Face::Top | Face::Bottom => ((1, 0, 0), (0, 0, 1)),
+            Face::North | Face::South => ((1, 0, 0), (0, 1, 0)),
+            Face::East | Face::West => ((0, 0, 1), (0, 1, 0)),
+        }
+    }
+}
+
+/// Returns the block coordinate offset by `(dx, dy, dz)`
+pub fn offset(pos: Coord<coords::Block, coords::World>,
+              (dx, dy, dz): (i64, i64, i64))
+              -> Coord<coords::Block, coords::World> {
+    Coord::new(pos.x + dx, pos.y + dy, pos.z + dz)
+}
+
+/// Read access to the world for render primitives
+pub trait BlockSource {
+    fn get_block(&self, pos: Coord<coords::Block, coords::World>) -> BlockState;
+    fn get_block_light(&self, pos: Coord<coords::Block, coords::World>) -> u8;
+    fn get_sky_light(&self, pos: Coord<coords::Block, coords::World>) -> u8;
+    fn get_biome(&self, pos: Coord<coords::Block, coords::World>) -> Option<&'static Biome>;
+
+    /// The Y coordinate just above the highest motion-blocking block in the column at `(x, z)`, or
+    /// the bottom of the world if the column is empty
+    fn get_height(&self, x: i64, z: i64) -> i64;
+}
+
+/// A piece of a render mode
+///
+/// All methods have do-nothing defaults, so a primitive only implements what it cares about.
+pub trait RenderPrimitive {
+    /// Returns true if the block at `pos` should not be drawn at all
+    fn hidden(&self, _world: &BlockSource, _pos: Coord<coords::Block, coords::World>) -> bool {
+        false
+    }
+
+    /// Brightness multipliers for the four corners of `face`, in the corner order given by
+    /// [`Face::axes`].  Renderers multiply these together across all primitives.
+    ///
+    /// [`Face::axes`]: enum.Face.html#method.axes
+    fn shade(&self,
+             _world: &BlockSource,
+             _pos: Coord<coords::Block, coords::World>,
+             _face: Face)
+             -> [f32; 4] {
+        [1.0; 4]
+    }
+
+    /// Adjusts the color of `face` after it has been shaded
+    fn draw(&self,
+            _world: &BlockSource,
+            _pos: Coord<coords::Block, coords::World>,
+            _face: Face,
+            _color: &mut Rgba<u8>) {
+    }
+}
+
+/// Multiplies a color by a brightness factor, leaving alpha alone
+pub fn darken(color: &mut Rgba<u8>, factor: f32) {
+    for i in 0..3 {
+        color[i] = (color[i] as f32 * factor).max(0.0).min(255.0) as u8;
+    }
+}
+
+/// Mixes `opacity` (from 0 to 1) of `rgb`, given as `0xRRGGBB`, into a color, leaving alpha alone
+pub fn blend(color: &mut Rgba<u8>, rgb: u32, opacity: f32) {
+    let tint = [(rgb >> 16) & 0xff, (rgb >> 8) & 0xff, rgb & 0xff];
+    for i in 0..3 {
+        color[i] = (color[i] as f32 * (1.0 - opacity) + tint[i] as f32 * opacity).round() as u8;
+    }
+}
+
+/// Parses a color written as `#rrggbb`
+pub fn parse_color(s: &str) -> Option<u32> {
+    if s.len() != 7 || !s.starts_with('#') || !s[1..].chars().all(|c| c.is_digit(16)) {
+        return None;
+    }
+    u32::from_str_radix(&s[1..], 16).ok()
+}
+
+/// The built-in render modes, each a preset stack of primitives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum RenderMode {
+    Normal,
+
+    /// Flat lighting, with each face as bright as the block in front of it; `"flat_lighting"`
+    Lighting,
+
+    /// Lighting blended across each face; `"lighting"`, or `"smooth_lighting"`
+    SmoothLighting,
+    Night,
+
+    /// Only what borders a cave, tinted from red to blue between the two Y levels of
+    /// `depth_tinting` if set
+    Cave { depth_tinting: Option<(i64, i64)> },
+
+    /// Everything above `max_y`, and below `min_y` if set, sliced away, with the top of the cut
+    /// tinted `highlight` (as `0xRRGGBB`) if set; `"cross_section"`, which cuts at sea level
+    CrossSection {
+        max_y: i64,
+        min_y: Option<i64>,
+        highlight: Option<u32>,
+    },
+}
+
+impl RenderMode {
+    pub fn from_name(name: &str) -> Option<RenderMode> {
+        match name {
+            "normal" => Some(RenderMode::Normal),
+            "flat_lighting" => Some(RenderMode::Lighting),
+            // "lighting" has always meant smooth lighting on the command line
+            "lighting" | "smooth_lighting" => Some(RenderMode::SmoothLighting),
+            "night" => Some(RenderMode::Night),
+            "cave" => Some(RenderMode::Cave { depth_tinting: None }),
+            "cross_section" => {
+                Some(RenderMode::CrossSection {
+                    max_y: SEA_LEVEL,
+                    min_y: None,
+                    highlight: Some(DEFAULT_HIGHLIGHT),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn primitives(&self) -> Vec<Box<RenderPrimitive>> {
+        match *self {
+            RenderMode::Normal => vec![],
+            RenderMode::Lighting => vec![Box::new(Lighting::new())],
+            RenderMode::SmoothLighting => vec![Box::new(SmoothLighting::new(Lighting::new()))],
+            RenderMode::Night => vec![Box::new(SmoothLighting::new(Lighting::night()))],
+            RenderMode::Cave { depth_tinting } => {
+                vec![Box::new(Cave { depth_tinting: depth_tinting })]
+            }
+            RenderMode::CrossSection { max_y, min_y, highlight } => {
+                vec![Box::new(CrossSection {
+                         max_y: max_y,
+                         min_y: min_y,
+                         highlight: highlight,
+                     })]
+            }
+        }
+    }
+}
+
+/// Which way north points on a finished map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum NorthDirection {
+    UpperLeft,
+    UpperRight,
+    LowerRight,
+    LowerLeft,
+}
+
+impl NorthDirection {
+    /// Parses the names classic Overviewer uses, such as `upper-left`
+    pub fn from_name(name: &str) -> Option<NorthDirection> {
+        match name {
+            "upper-left" => Some(NorthDirection::UpperLeft),
+            "upper-right" => Some(NorthDirection::UpperRight),
+            "lower-right" => Some(NorthDirection::LowerRight),
+            "lower-left" => Some(NorthDirection::LowerLeft),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            NorthDirection::UpperLeft => "upper-left",
+            NorthDirection::UpperRight => "upper-right",
+            NorthDirection::LowerRight => "lower-right",
+            NorthDirection::LowerLeft => "lower-left",
+        }
+    }
+
+    /// How many clockwise quarter turns the map is rotated from the default of `UpperLeft`
+    pub fn quarter_turns(&self) -> u8 {
+        match *self {
+            NorthDirection::UpperLeft => 0,
+            NorthDirection::UpperRight => 1,
+            NorthDirection::LowerRight => 2,
+            NorthDirection::LowerLeft => 3,
+        }
+    }
+
+    /// Rotates a whole tile to match this direction
+    pub fn rotate(&self, img: &Texture) -> Texture {
+        match self.quarter_turns() {
+            1 => image::imageops::rotate90(img),
+            2 => image::imageops::rotate180(img),
+            3 => image::imageops::rotate270(img),
+            _ => img.clone(),
+        }
+    }
+
+    /// Where the tile at `(x, z)` ends up once every tile has been rotated
+    pub fn rotate_tile(&self, x: i64, z: i64) -> (i64, i64) {
+        match self.quarter_turns() {
+            1 => (-z - 1, x),
+            2 => (-x - 1, -z - 1),
+            3 => (z, -x - 1),
+            _ => (x, z),
+        }
+    }
+}
+
+/// The file format of rendered tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum ImageFormat {
+    Png,
+
+    /// JPEG, with a quality from 1 to 100.  Transparent areas come out black.
+    Jpeg(u8),
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg(_) => "jpg",
+        }
+    }
+
+    /// Encodes `img` into `w`
+    pub fn save<W: Write>(&self, img: Texture, w: &mut W) -> Result<(), OverviewerError> {
+        match *self {
+            ImageFormat::Png => try!(image::ImageRgba8(img).save(w, image::PNG)),
+            ImageFormat::Jpeg(quality) => {
+                let rgb = image::ImageRgba8(img).to_rgb();
+                let (width, height) = rgb.dimensions();
+                let mut encoder = image::jpeg::JPEGEncoder::new_with_quality(w, quality);
+                try!(encoder.encode(&rgb, width, height, image::RGB(8)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The file name of the tile at `(x, y)` in zoom level `zoom`
+pub fn tile_name(zoom: u32, x: i64, y: i64, format: ImageFormat) -> String {
+    format!("{}.{}.{}.{}", zoom, x, y, format.extension())
+}
+
+/// How an overlay's tiles line up with those of the render they're laid over
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlayLayout {
+    /// The size of each block, in pixels, which should match the render
+    pub pixels_per_block: u32,
+
+    /// If set, only blocks inside this box are colored
+    pub crop: Option<CropBox>,
+    pub north: NorthDirection,
+
+    /// The zoom level that tiles are named for
+    pub zoom: u32,
+}
+
+impl OverlayLayout {
+    pub fn new() -> OverlayLayout {
+        OverlayLayout {
+            pixels_per_block: 1,
+            crop: None,
+            north: NorthDirection::UpperLeft,
+            zoom: 0,
+        }
+    }
+
+    /// Whether the block column `(x, z)` is colored at all
+    pub fn shows(&self, x: i64, z: i64) -> bool {
+        self.crop.as_ref().map_or(true, |crop| crop.contains(x, z))
+    }
+
+    /// Writes a PNG tile for each of `regions` that isn't cropped away into `out_dir`
+    ///
+    /// `draw` draws a region unrotated, and its tile is rotated and named the same way as
+    /// [`TopDown::render_regionset`] rotates and names its tiles.  Regions may be listed more than
+    /// once.  Returns the coordinates of the tiles written.
+    ///
+    /// [`TopDown::render_regionset`]: struct.TopDown.html#method.render_regionset
+    pub fn write_tiles<W, P, I, F>(&self,
+                                   out: &W,
+                                   out_dir: P,
+                                   regions: I,
+                                   draw: F)
+                                   -> Result<Vec<(i64, i64)>, OverviewerError>
+        where W: rio::FSWrite,
+              P: AsRef<rio::Path>,
+              I: IntoIterator<Item = (i64, i64)>,
+              F: Fn(Coord<coords::Region, coords::World>) -> Texture
+    {
+        let regions: BTreeSet<(i64, i64)> = regions.into_iter().collect();
+        let mut tiles = Vec::with_capacity(regions.len());
+        for (rx, rz) in regions {
+            if let Some(ref crop) = self.crop {
+                if rx * TILE_BLOCKS > crop.max_x || (rx + 1) * TILE_BLOCKS <= crop.min_x ||
+                   rz * TILE_BLOCKS > crop.max_z || (rz + 1) * TILE_BLOCKS <= crop.min_z {
+                    continue;
+                }
+            }
+            let img = self.north.rotate(&draw(Coord::new(rx, 0, rz)));
+            let (x, z) = self.north.rotate_tile(rx, rz);
+            let name = tile_name(self.zoom, x, z, ImageFormat::Png);
+            let mut f = try!(out.create(out_dir.as_ref().join(name)));
+            try!(ImageFormat::Png.save(img, &mut f));
+            tiles.push((x, z));
+        }
+        Ok(tiles)
+    }
+}
+
+/// A [`BlockSource`] backed by a `Regionset`
+///
+/// Decoded chunks are kept around for the lifetime of the view, so a renderer should use one view
+/// per tile (or per region) rather than one for the whole world.
+///
+/// [`BlockSource`]: trait.BlockSource.html
+pub struct RegionsetView<'a, 'fs: 'a, FS: rio::FSRead<'fs> + 'a> {
+    rset: &'a Regionset<'fs, FS>,
+    chunks: RefCell<HashMap<(i64, i64), Option<Rc<Chunk>>>>,
+}
+
+impl<'a, 'fs, FS> RegionsetView<'a, 'fs, FS>
+    where FS: rio::FSRead<'fs>,
+          FS::ReadFile: Read + Seek
+{
+    pub fn new(rset: &'a Regionset<'fs, FS>) -> RegionsetView<'a, 'fs, FS> {
+        RegionsetView {
+            rset: rset,
+            chunks: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Loads (or fetches from the cache) the chunk containing `pos`, along with the position
+    /// inside of that chunk
+    pub fn chunk_at(&self,
+                    pos: Coord<coords::Block, coords::World>)
+                    -> (Option<Rc<Chunk>>, Coord<coords::Block, coords::Chunk>) {
+        let (in_chunk, chunk) = pos.split::<coords::Chunk>();
+        let mut chunks = self.chunks.borrow_mut();
+        let loaded = chunks.entry((chunk.x, chunk.z))
+            .or_insert_with(|| self.rset.get_chunk(chunk).map(Rc::new))
+            .clone();
+        (loaded, in_chunk)
+    }
+
+    /// Calls `f` with every chunk loaded so far, skipping those that didn't exist
+    pub fn for_each_chunk<F>(&self, mut f: F)
+        where F: FnMut(Coord<coords::Chunk, coords::World>, &Chunk)
+    {
+        for (&(x, z), chunk) in self.chunks.borrow().iter() {
+            if let Some(ref chunk) = *chunk {
+                f(Coord::new(x, 0, z), chunk);
+            }
+        }
+    }
+}
+
+impl<'a, 'fs, FS> BlockSource for RegionsetView<'a, 'fs, FS>
+    where FS: rio::FSRead<'fs>,
+          FS::ReadFile: Read + Seek
+{
+    fn get_block(&self, pos: Coord<coords::Block, coords::World>) -> BlockState {
+        match self.chunk_at(pos) {
+            (Some(chunk), in_chunk) => chunk.get_block(in_chunk),
+            (None, _) => BlockState::air(),
+        }
+    }
+
+    fn get_block_light(&self, pos: Coord<coords::Block, coords::World>) -> u8 {
+        match self.chunk_at(pos) {
+            (Some(chunk), in_chunk) => chunk.get_block_light(in_chunk),
+            (None, _) => 0,
+        }
+    }
+
+    fn get_sky_light(&self, pos: Coord<coords::Block, coords::World>) -> u8 {
+        match self.chunk_at(pos) {
+            (Some(chunk), in_chunk) => chunk.get_sky_light(in_chunk),
+            (None, _) => 15,
+        }
+    }
+
+    fn get_biome(&self, pos: Coord<coords::Block, coords::World>) -> Option<&'static Biome> {
+        match self.chunk_at(pos) {
+            (Some(chunk), in_chunk) => chunk.get_biome(in_chunk),
+            (None, _) => None,
+        }
+    }
+
+    fn get_height(&self, x: i64, z: i64) -> i64 {
+        match self.chunk_at(Coord::new(x, 0, z)) {
+            (Some(chunk), in_chunk) => {
+                chunk.get_height(HeightmapType::MotionBlocking, in_chunk)
+                    .unwrap_or_else(|| chunk.compute_height(in_chunk))
+            }
+            // nothing is there, so the surface is as low as it goes
+            (None, _) => topdown::MIN_Y,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A tiny in-memory world for testing primitives.  Anything not set is air with full sky light.
+    #[derive(Default)]
+    pub struct TestWorld {
+        pub blocks: HashMap<(i64, i64, i64), (BlockState, u8, u8)>,
+    }
+
+    impl TestWorld {
+        /// Places a block with the given block light and sky light
+        pub fn set(&mut self, pos: (i64, i64, i64), name: &str, block_light: u8, sky_light: u8) {
+            self.blocks.insert(pos, (BlockState::new(name), block_light, sky_light));
+        }
+    }
+
+    impl BlockSource for TestWorld {
+        fn get_block(&self, pos: Coord<coords::Block, coords::World>) -> BlockState {
+            self.blocks
+                .get(&(pos.x, pos.y, pos.z))
+                .map(|b| b.0.clone())
+                .unwrap_or_else(BlockState::air)
+        }
+        fn get_block_light(&self, pos: Coord<coords::Block, coords::World>) -> u8 {
+            self.blocks.get(&(pos.x, pos.y, pos.z)).map(|b| b.1).unwrap_or(0)
+        }
+        fn get_sky_light(&self, pos: Coord<coords::Block, coords::World>) -> u8 {
+            self.blocks.get(&(pos.x, pos.y, pos.z)).map(|b| b.2).unwrap_or(15)
+        }
+        fn get_biome(&self, _pos: Coord<coords::Block, coords::World>) -> Option<&'static Biome> {
+            Biome::from_name("plains")
+        }
+        fn get_height(&self, x: i64, z: i64) -> i64 {
+            self.blocks
+                .iter()
+                .filter(|&(&(bx, _, bz), b)| bx == x && bz == z && !b.0.is_air())
+                .map(|(&(_, y, _), _)| y + 1)
+                .max()
+                .unwrap_or(0)
+        }
+    }
+
+    #[test]
+    fn test_rotate_tile() {
+        // a tile's corner pixel should land in the rotated tile
+        for dir in &[NorthDirection::UpperLeft,
+                     NorthDirection::UpperRight,
+                     NorthDirection::LowerRight,
+                     NorthDirection::LowerLeft] {
+            let (x, z) = dir.rotate_tile(2, -3);
+            let turns = dir.quarter_turns();
+            let (mut px, mut pz) = (2 * 512, -3 * 512);
+            for _ in 0..turns {
+                // one clockwise quarter turn, in pixels
+                let t = px;
+                px = -pz - 1;
+                pz = t;
+            }
+            let tile = |p: i64| (p as f64 / 512.0).floor() as i64;
+            assert_eq!((tile(px), tile(pz)), (x, z));
+        }
+    }
+
+    #[test]
+    fn test_render_mode_names() {
+        assert_eq!(RenderMode::from_name("lighting"), Some(RenderMode::SmoothLighting));
+        assert_eq!(RenderMode::from_name("smooth_lighting"), Some(RenderMode::SmoothLighting));
+        assert_eq!(RenderMode::from_name("flat_lighting"), Some(RenderMode::Lighting));
+        assert_eq!(RenderMode::from_name("isometric"), None);
+        assert_eq!(RenderMode::from_name("cross_section"),
+                   Some(RenderMode::CrossSection {
+                       max_y: 62,
+                       min_y: None,
+                       highlight: Some(0xff0000),
+                   }));
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#40ff0a"), Some(0x40ff0a));
+        assert_eq!(parse_color("40ff0a"), None);
+        assert_eq!(parse_color("#+40ff0"), None);
+        assert_eq!(parse_color("#red"), None);
+    }
+
+    #[test]
+    fn test_darken() {
+        let mut c = Rgba([200, 100, 50, 255]);
+        darken(&mut c, 0.5);
+        assert_eq!(c, Rgba([100, 50, 25, 255]));
+    }
+
+    #[test]
+    fn test_blend() {
+        let mut c = Rgba([200, 100, 0, 128]);
+        blend(&mut c, 0x0000ff, 0.25);
+        assert_eq!(c, Rgba([150, 75, 64, 128]));
+    }
+}
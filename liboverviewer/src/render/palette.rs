@@ -0,0 +1,191 @@
+//! Flat per-block colors, for renderers that draw a block as a single color
+
+use image::Rgba;
+use rio;
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+
+use asset_manager::{AssetManager, Texture};
+use blocks::BlockState;
+use error::OverviewerError;
+
+// used for any block that the palette doesn't know about
+const UNKNOWN: [u8; 4] = [128, 128, 128, 255];
+
+/// Maps blocks to a single representative color
+pub struct BlockPalette {
+    // keyed by block name without the namespace
+    colors: HashMap<String, Rgba<u8>>,
+
+    /// True if the colors came from greyscale textures, and so still need biome tinting
+    pub needs_tinting: bool,
+}
+
+impl BlockPalette {
+    /// A palette with approximate colors for common blocks, for when no textures are available
+    pub fn builtin() -> BlockPalette {
+        let mut palette = BlockPalette {
+            colors: HashMap::new(),
+            needs_tinting: false,
+        };
+        for &(name, color) in BUILTIN_COLORS {
+            palette.insert(name, Rgba(color));
+        }
+        palette
+    }
+
+    /// Builds a palette out of the average color of every block texture in the assets
+    ///
+    /// Blocks with a separate top texture (`grass_block_top`, `oak_log_top`) use that, since it's
+    /// the face a top-down map sees.
+    pub fn from_assets<'fs, FS>(assets: &AssetManager<'fs, FS>) -> Result<BlockPalette, OverviewerError>
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read + Seek
+    {
+        let mut palette = BlockPalette {
+            colors: HashMap::new(),
+            needs_tinting: true,
+        };
+        let mut tops = Vec::new();
+        for texture in try!(assets.list_textures("block")) {
+            let name = texture.trim_left_matches("block/").to_owned();
+            let color = average_color(&try!(assets.get_texture(&texture)));
+            if name.ends_with("_top") {
+                tops.push((name.trim_right_matches("_top").to_owned(), color));
+            } else {
+                // fluids are named after their still texture
+                palette.insert(name.trim_right_matches("_still"), color);
+            }
+        }
+        for (name, color) in tops {
+            palette.insert(&name, color);
+        }
+        Ok(palette)
+    }
+
+    pub fn insert(&mut self, name: &str, color: Rgba<u8>) {
+        self.colors.insert(name.to_owned(), color);
+    }
+
+    /// The color of a block.  Unknown blocks are grey.
+    pub fn get(&self, block: &BlockState) -> Rgba<u8> {
+        self.colors.get(block.short_name()).cloned().unwrap_or(Rgba(UNKNOWN))
+    }
+}
+
+/// The average color of a texture, weighting each pixel by its alpha.  The alpha of the result is
+/// the average alpha of the texture.
+pub fn average_color(texture: &Texture) -> Rgba<u8> {
+    let mut totals = [0u64; 4];
+    for p in texture.pixels() {
+        for i in 0..3 {
+            totals[i] += p[i] as u64 * p[3] as u64;
+        }
+        totals[3] += p[3] as u64;
+    }
+    if totals[3] == 0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let count = texture.width() as u64 * texture.height() as u64;
+    Rgba([(totals[0] / totals[3]) as u8,
+          (totals[1] / totals[3]) as u8,
+          (totals[2] / totals[3]) as u8,
+          (totals[3] / count) as u8])
+}
+
+static BUILTIN_COLORS: &'static [(&'static str, [u8; 4])] = &[
+    ("stone", [125, 125, 125, 255]),
+    ("granite", [149, 103, 85, 255]),
+    ("diorite", [188, 188, 188, 255]),
+    ("andesite", [136, 136, 136, 255]),
+    ("deepslate", [80, 80, 82, 255]),
+    ("tuff", [108, 109, 102, 255]),
+    ("calcite", [223, 224, 220, 255]),
+    ("bedrock", [85, 85, 85, 255]),
+    ("grass_block", [95, 159, 53, 255]),
+    ("dirt", [134, 96, 67, 255]),
+    ("coarse_dirt", [119, 85, 59, 255]),
+    ("podzol", [91, 63, 24, 255]),
+    ("mycelium", [111, 98, 101, 255]),
+    ("farmland", [81, 44, 15, 255]),
+    ("dirt_path", [148, 122, 65, 255]),
+    ("mud", [60, 57, 61, 255]),
+    ("moss_block", [89, 109, 45, 255]),
+    ("clay", [160, 166, 179, 255]),
+    ("sand", [219, 207, 163, 255]),
+    ("red_sand", [190, 102, 33, 255]),
+    ("sandstone", [216, 203, 155, 255]),
+    ("red_sandstone", [186, 99, 29, 255]),
+    ("gravel", [131, 127, 126, 255]),
+    ("cobblestone", [122, 122, 122, 255]),
+    ("mossy_cobblestone", [110, 118, 94, 255]),
+    ("stone_bricks", [122, 121, 122, 255]),
+    ("bricks", [150, 97, 83, 255]),
+    ("coal_ore", [115, 115, 115, 255]),
+    ("iron_ore", [136, 130, 127, 255]),
+    ("gold_ore", [143, 140, 125, 255]),
+    ("diamond_ore", [121, 141, 140, 255]),
+    ("oak_planks", [162, 130, 78, 255]),
+    ("spruce_planks", [114, 84, 48, 255]),
+    ("oak_log", [151, 121, 73, 255]),
+    ("spruce_log", [108, 80, 46, 255]),
+    ("birch_log", [193, 179, 135, 255]),
+    ("jungle_log", [149, 109, 70, 255]),
+    ("acacia_log", [150, 88, 55, 255]),
+    ("dark_oak_log", [65, 43, 20, 255]),
+    ("oak_leaves", [60, 110, 30, 255]),
+    ("spruce_leaves", [55, 90, 55, 255]),
+    ("birch_leaves", [80, 120, 50, 255]),
+    ("jungle_leaves", [50, 120, 20, 255]),
+    ("acacia_leaves", [70, 110, 25, 255]),
+    ("dark_oak_leaves", [40, 90, 15, 255]),
+    ("short_grass", [90, 140, 50, 150]),
+    ("grass", [90, 140, 50, 150]),
+    ("tall_grass", [90, 140, 50, 150]),
+    ("fern", [80, 125, 45, 150]),
+    ("sugar_cane", [148, 192, 101, 200]),
+    ("cactus", [85, 127, 43, 255]),
+    ("pumpkin", [198, 118, 24, 255]),
+    ("melon", [111, 145, 30, 255]),
+    ("lily_pad", [32, 128, 48, 200]),
+    ("water", [63, 118, 228, 170]),
+    ("lava", [207, 92, 20, 255]),
+    ("ice", [145, 183, 253, 200]),
+    ("packed_ice", [141, 180, 250, 255]),
+    ("snow", [249, 254, 254, 255]),
+    ("snow_block", [249, 254, 254, 255]),
+    ("glass", [200, 220, 230, 60]),
+    ("terracotta", [152, 94, 67, 255]),
+    ("white_wool", [234, 236, 237, 255]),
+    ("netherrack", [97, 38, 38, 255]),
+    ("soul_sand", [81, 62, 50, 255]),
+    ("basalt", [80, 81, 86, 255]),
+    ("blackstone", [42, 36, 41, 255]),
+    ("glowstone", [171, 131, 84, 255]),
+    ("obsidian", [15, 10, 24, 255]),
+    ("end_stone", [219, 222, 158, 255]),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blocks::BlockState;
+    use image::{ImageBuffer, Rgba};
+
+    #[test]
+    fn test_average_color() {
+        // half opaque red, half fully transparent
+        let tex = ImageBuffer::from_fn(2, 2, |x, _| {
+            if x == 0 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 255, 0, 0]) }
+        });
+        assert_eq!(average_color(&tex), Rgba([255, 0, 0, 127]));
+    }
+
+    #[test]
+    fn test_builtin_palette() {
+        let palette = BlockPalette::builtin();
+        assert_eq!(palette.get(&BlockState::new("minecraft:stone")), Rgba([125, 125, 125, 255]));
+        assert_eq!(palette.get(&BlockState::new("minecraft:not_a_block")), Rgba(UNKNOWN));
+    }
+}
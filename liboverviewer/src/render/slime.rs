@@ -0,0 +1,138 @@
+//! The slime chunk overlay
+//!
+//! Slimes spawn below Y 40 in one overworld chunk in ten, picked from the world seed with Java's
+//! random number generator.  This primitive tints every block in those chunks.  Only the top-down
+//! renderer draws primitives, and it only asks for top faces, so the tint shows as whole chunks.
+
+use image::Rgba;
+
+use coords::{self, Coord};
+use super::{blend, BlockSource, Face, RenderPrimitive};
+
+/// Tints the blocks of slime chunks
+pub struct SlimeChunks {
+    pub seed: i64,
+
+    /// Color (as `0xRRGGBB`) to blend into slime chunks
+    pub color: u32,
+
+    /// How much of `color` to blend in, from 0 to 1
+    pub opacity: f32,
+}
+
+impl SlimeChunks {
+    /// Marks the slime chunks of the world with the given seed in green
+    pub fn new(seed: i64) -> SlimeChunks {
+        SlimeChunks {
+            seed: seed,
+            color: 0x40ff40,
+            opacity: 0.4,
+        }
+    }
+}
+
+impl RenderPrimitive for SlimeChunks {
+    fn draw(&self,
+            _world: &BlockSource,
+            pos: Coord<coords::Block, coords::World>,
+            _face: Face,
+            color: &mut Rgba<u8>) {
+        let (_, chunk) = pos.split::<coords::Chunk>();
+        if !is_slime_chunk(self.seed, chunk) {
+            return;
+        }
+        blend(color, self.color, self.opacity);
+    }
+}
+
+/// Whether slimes can spawn anywhere in a chunk, regardless of light, in a world with `seed`
+///
+/// This seeds a `java.util.Random` from the chunk position exactly as Minecraft does, including
+/// the 32 bit overflow of the chunk coordinate products, and takes a chunk where its first
+/// `nextInt(10)` is 0.
+pub fn is_slime_chunk(seed: i64, pos: Coord<coords::Chunk, coords::World>) -> bool {
+    let (x, z) = (pos.x as i32, pos.z as i32);
+    let chunk_seed = seed.wrapping_add(x.wrapping_mul(x).wrapping_mul(0x4c1906) as i64)
+        .wrapping_add(x.wrapping_mul(0x5ac0db) as i64)
+        .wrapping_add((z.wrapping_mul(z) as i64).wrapping_mul(0x4307a7))
+        .wrapping_add(z.wrapping_mul(0x5f24f) as i64);
+    JavaRandom::new(chunk_seed ^ 0x3ad8025f).next_int(10) == 0
+}
+
+const MULTIPLIER: i64 = 0x5deece66d;
+const MASK: i64 = (1 << 48) - 1;
+
+// just enough of java.util.Random to pick slime chunks
+struct JavaRandom {
+    seed: i64,
+}
+
+impl JavaRandom {
+    fn new(seed: i64) -> JavaRandom {
+        JavaRandom { seed: (seed ^ MULTIPLIER) & MASK }
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = self.seed.wrapping_mul(MULTIPLIER).wrapping_add(0xb) & MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    // the general case of Random.nextInt(bound), for bounds that aren't powers of two
+    fn next_int(&mut self, bound: i32) -> i32 {
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            // Java retries when this overflows, to keep the result uniform
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use coords::Coord;
+    use image::Rgba;
+    use render::{Face, RenderPrimitive};
+    use render::test::TestWorld;
+
+    #[test]
+    fn test_java_random() {
+        // new Random(42).nextInt()
+        assert_eq!(JavaRandom::new(42).next(32), -1170105035);
+    }
+
+    #[test]
+    fn test_is_slime_chunk() {
+        // worked out with the same code in Java
+        let mut found = Vec::new();
+        for x in -4..4 {
+            for z in -4..4 {
+                if is_slime_chunk(0, Coord::new(x, 0, z)) {
+                    found.push((x, z));
+                }
+            }
+        }
+        assert_eq!(found, vec![(-2, 0), (1, -3), (2, -3), (2, 2)]);
+        assert!(is_slime_chunk(-4172144997902289642, Coord::new(1, 0, 1)));
+        assert!(!is_slime_chunk(-4172144997902289642, Coord::new(0, 0, 0)));
+        // far enough out that the products overflow
+        assert!(is_slime_chunk(0, Coord::new(-1875000, 0, 1875000)));
+        assert!(!is_slime_chunk(0, Coord::new(100000, 0, -30000)));
+    }
+
+    #[test]
+    fn test_draw() {
+        let world = TestWorld::default();
+        let slime = SlimeChunks::new(0);
+        // chunk (-2, 0) is a slime chunk, (-1, 0) isn't
+        let mut color = Rgba([0, 0, 0, 255]);
+        slime.draw(&world, Coord::new(-20, 30, 5), Face::East, &mut color);
+        assert_eq!(color, Rgba([26, 102, 26, 255]));
+        let mut color = Rgba([0, 0, 0, 255]);
+        slime.draw(&world, Coord::new(-10, 30, 5), Face::Top, &mut color);
+        assert_eq!(color, Rgba([0, 0, 0, 255]));
+    }
+}
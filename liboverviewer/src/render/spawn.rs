@@ -0,0 +1,177 @@
+//! The mob spawning overlay
+//!
+//! Highlights the tops of blocks that hostile mobs could spawn on at night: the block has a solid
+//! top, the two blocks above it leave room for a zombie or a skeleton, and no torch or other light
+//! source lights the space.  Sky light is ignored, since at night it doesn't keep mobs away.
+//!
+//! Minecraft 1.18 made mobs only spawn in complete darkness; before that, a block light of 7 or
+//! less was dark enough.
+
+use image::Rgba;
+
+use blocks::BlockState;
+use coords::{self, Coord};
+use super::{blend, offset, BlockSource, Face, RenderPrimitive};
+
+/// The first data version (1.18) where hostile mobs need a block light of 0 to spawn
+pub const DARK_SPAWNING_DATA_VERSION: i32 = 2860;
+
+/// Tints the tops of blocks that hostile mobs can spawn on
+pub struct MobSpawns {
+    /// The brightest block light that mobs still spawn in
+    pub max_block_light: u8,
+
+    /// Color (as `0xRRGGBB`) to blend into the top of spawnable blocks
+    pub color: u32,
+
+    /// How much of `color` to blend in, from 0 to 1
+    pub opacity: f32,
+}
+
+impl MobSpawns {
+    /// The spawning rules of the version that saved a world, from its `level.dat`.  Worlds too old
+    /// to record a data version get the old rules.
+    pub fn for_data_version(data_version: Option<i32>) -> MobSpawns {
+        let dark = data_version.map(|v| v >= DARK_SPAWNING_DATA_VERSION).unwrap_or(false);
+        MobSpawns {
+            max_block_light: if dark { 0 } else { 7 },
+            color: 0xff0000,
+            opacity: 0.5,
+        }
+    }
+
+    /// Whether a hostile mob could spawn standing on the block at `floor`
+    pub fn can_spawn_on(&self,
+                        world: &BlockSource,
+                        floor: Coord<coords::Block, coords::World>)
+                        -> bool {
+        if !has_solid_top(&world.get_block(floor)) {
+            return false;
+        }
+        let feet = offset(floor, (0, 1, 0));
+        let head = offset(floor, (0, 2, 0));
+        is_open(&world.get_block(feet)) && is_open(&world.get_block(head)) &&
+        world.get_block_light(feet) <= self.max_block_light
+    }
+}
+
+impl RenderPrimitive for MobSpawns {
+    fn draw(&self,
+            world: &BlockSource,
+            pos: Coord<coords::Block, coords::World>,
+            face: Face,
+            color: &mut Rgba<u8>) {
+        if face != Face::Top {
+            return;
+        }
+        // grass and flowers are drawn over the block that mobs would stand on
+        let block = world.get_block(pos);
+        let floor = if !block.is_air() && is_open(&block) {
+            offset(pos, (0, -1, 0))
+        } else {
+            pos
+        };
+        if !self.can_spawn_on(world, floor) {
+            return;
+        }
+        blend(color, self.color, self.opacity);
+    }
+}
+
+/// Whether a mob can stand on top of a block
+///
+/// Full opaque cubes count, as do top and double slabs and upside-down stairs.  Bedrock is
+/// excluded, since nothing spawns on it outside of world generation.
+pub fn has_solid_top(block: &BlockState) -> bool {
+    let name = block.short_name();
+    if name.ends_with("_slab") {
+        return block.property("type").map(|t| t != "bottom").unwrap_or(false);
+    }
+    if name.ends_with("_stairs") {
+        return block.property("half") == Some("top");
+    }
+    block.is_opaque() && name != "bedrock"
+}
+
+/// Whether a block leaves room for a mob to spawn in it
+///
+/// Blocks with no collision box count, except those that Minecraft keeps mobs from spawning in,
+/// such as rails and liquids.
+pub fn is_open(block: &BlockState) -> bool {
+    if block.is_air() {
+        return true;
+    }
+    let name = block.short_name();
+    const OPEN_SUFFIXES: &'static [&'static str] =
+        &["_sign", "_button", "_pressure_plate", "_tulip", "_torch", "_sapling", "_banner"];
+    if OPEN_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+        return true;
+    }
+    match name {
+        "grass" | "short_grass" | "fern" | "dead_bush" | "dandelion" | "poppy" | "blue_orchid" |
+        "allium" | "azure_bluet" | "oxeye_daisy" | "cornflower" | "lily_of_the_valley" |
+        "torch" | "redstone_wire" | "lever" | "tripwire" | "tripwire_hook" | "ladder" | "vine" |
+        "light" => true,
+        "snow" => block.property("layers").map(|l| l == "1").unwrap_or(true),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blocks::BlockState;
+    use coords::Coord;
+    use image::Rgba;
+    use render::{Face, RenderPrimitive};
+    use render::test::TestWorld;
+
+    #[test]
+    fn test_has_solid_top() {
+        let mut slab = BlockState::new("oak_slab");
+        slab.properties.insert("type".to_owned(), "bottom".to_owned());
+        assert!(!has_solid_top(&slab));
+        slab.properties.insert("type".to_owned(), "top".to_owned());
+        assert!(has_solid_top(&slab));
+        assert!(has_solid_top(&BlockState::new("stone")));
+        assert!(!has_solid_top(&BlockState::new("glass")));
+        assert!(!has_solid_top(&BlockState::new("bedrock")));
+        assert!(is_open(&BlockState::new("short_grass")));
+        assert!(!is_open(&BlockState::new("water")));
+        assert!(!is_open(&BlockState::new("rail")));
+    }
+
+    #[test]
+    fn test_can_spawn_on() {
+        let mut world = TestWorld::default();
+        world.set((0, 10, 0), "stone", 0, 0);
+        world.set((1, 10, 0), "stone", 0, 0);
+        world.set((1, 11, 0), "air", 5, 0);
+        world.set((2, 10, 0), "stone", 0, 0);
+        world.set((2, 12, 0), "oak_leaves", 0, 0);
+        world.set((3, 10, 0), "stone", 0, 0);
+        world.set((3, 11, 0), "short_grass", 0, 0);
+
+        let old = MobSpawns::for_data_version(Some(1343));
+        let new = MobSpawns::for_data_version(Some(3465));
+        assert_eq!(old.max_block_light, 7);
+        assert_eq!(new.max_block_light, 0);
+        assert_eq!(MobSpawns::for_data_version(None).max_block_light, 7);
+
+        assert!(new.can_spawn_on(&world, Coord::new(0, 10, 0)));
+        // lit, but only too brightly for 1.18
+        assert!(old.can_spawn_on(&world, Coord::new(1, 10, 0)));
+        assert!(!new.can_spawn_on(&world, Coord::new(1, 10, 0)));
+        // no room
+        assert!(!new.can_spawn_on(&world, Coord::new(2, 10, 0)));
+        assert!(new.can_spawn_on(&world, Coord::new(3, 10, 0)));
+
+        // the grass is drawn, but it's the stone below that mobs spawn on
+        let mut color = Rgba([0, 0, 0, 255]);
+        new.draw(&world, Coord::new(3, 11, 0), Face::Top, &mut color);
+        assert_eq!(color, Rgba([128, 0, 0, 255]));
+        let mut color = Rgba([0, 0, 0, 255]);
+        new.draw(&world, Coord::new(3, 10, 0), Face::North, &mut color);
+        assert_eq!(color, Rgba([0, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,336 @@
+//! A top-down orthographic renderer
+//!
+//! Each block column becomes a square of `pixels_per_block` pixels, colored by the highest block
+//! that isn't hidden by a render primitive.  Translucent blocks such as water and glass are blended
+//! with whatever is below them.  This is much faster than an isometric render, and is what you want
+//! for a quick overview of a world.
+
+use image::{ImageBuffer, Rgba};
+use rio;
+use std::io::{Read, Seek};
+
+use asset_manager::Texture;
+use blocks::BlockState;
+use coords::{self, Coord};
+use error::OverviewerError;
+use heightmap::CropBox;
+use tint::{apply_tint, BiomeTinter};
+use world::{Chunk, Regionset};
+use super::palette::BlockPalette;
+use super::{darken, tile_name, BlockSource, Face, ImageFormat, NorthDirection, RegionsetView,
+            RenderPrimitive};
+
+/// The lowest Y level that a column is searched down to
+pub const MIN_Y: i64 = -64;
+
+/// The number of blocks along each side of a tile; one tile covers one region
+pub const TILE_BLOCKS: i64 = 512;
+
+/// What's visible in one block column
+struct Column {
+    /// The highest block that was drawn
+    pos: Coord<coords::Block, coords::World>,
+    color: Rgba<u8>,
+    corners: [f32; 4],
+}
+
+/// Renders the world as seen from directly above
+pub struct TopDown {
+    /// The size of each block, in pixels
+    pub pixels_per_block: u32,
+    pub palette: BlockPalette,
+
+    /// Used to tint palette colors that came from greyscale textures
+    pub tinter: Option<BiomeTinter>,
+    pub primitives: Vec<Box<RenderPrimitive>>,
+
+    /// Brighten blocks that are higher than their northern neighbor and darken those that are
+    /// lower, the way in-game maps do
+    pub height_shading: bool,
+
+    /// If set, only blocks inside this box are drawn
+    pub crop: Option<CropBox>,
+    pub north: NorthDirection,
+    pub format: ImageFormat,
+
+    /// The zoom level that tiles are named for.  A web viewer puts full resolution tiles at its
+    /// deepest zoom level.
+    pub zoom: u32,
+}
+
+impl TopDown {
+    pub fn new(palette: BlockPalette) -> TopDown {
+        TopDown {
+            pixels_per_block: 1,
+            palette: palette,
+            tinter: None,
+            primitives: Vec::new(),
+            height_shading: true,
+            crop: None,
+            north: NorthDirection::UpperLeft,
+            format: ImageFormat::Png,
+            zoom: 0,
+        }
+    }
+
+    /// Renders the `width` by `depth` block area whose north-west corner is at `(x, z)`
+    pub fn render_area(&self, world: &BlockSource, x: i64, z: i64, width: i64, depth: i64) -> Texture {
+        let n = self.pixels_per_block;
+        let mut img = ImageBuffer::new(width as u32 * n, depth as u32 * n);
+
+        // height shading compares against the row to the north, including the one just outside
+        let mut north: Vec<Option<i64>> =
+            (0..width).map(|dx| self.render_column(world, x + dx, z - 1).map(|c| c.pos.y)).collect();
+        for dz in 0..depth {
+            let mut row = Vec::with_capacity(width as usize);
+            for dx in 0..width {
+                let column = self.render_column(world, x + dx, z + dz);
+                row.push(column.as_ref().map(|c| c.pos.y));
+                if let Some(column) = column {
+                    let shade = match north[dx as usize] {
+                        Some(north_y) if self.height_shading => height_shade(column.pos.y, north_y),
+                        _ => 1.0,
+                    };
+                    self.paint(&mut img, world, &column, dx as u32 * n, dz as u32 * n, shade);
+                }
+            }
+            north = row;
+        }
+        img
+    }
+
+    /// Renders a single region
+    pub fn render_region(&self, world: &BlockSource, region: Coord<coords::Region, coords::World>) -> Texture {
+        self.render_area(world,
+                         region.x * TILE_BLOCKS,
+                         region.z * TILE_BLOCKS,
+                         TILE_BLOCKS,
+                         TILE_BLOCKS)
+    }
+
+    /// Renders every region in a regionset, writing one tile per region into `out_dir`, named as
+    /// [`tile_name`] says.  Tiles are rotated, and renamed to match, according to `north`.
+    /// Returns the coordinates of the tiles written.
+    ///
+    /// [`tile_name`]: fn.tile_name.html
+    pub fn render_regionset<'fs, FS, W, P>(&self,
+                                           rset: &Regionset<'fs, FS>,
+                                           out: &W,
+                                           out_dir: P)
+                                           -> Result<Vec<(i64, i64)>, OverviewerError>
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read + Seek,
+              W: rio::FSWrite,
+              P: AsRef<rio::Path>
+    {
+        self.render_regionset_with(rset, out, out_dir, |_, _| ())
+    }
+
+    /// Like [`render_regionset`], but also hands every chunk it loads to `visit`, so that callers
+    /// can gather more from the chunks without decoding them all again.  Chunks along the edges of
+    /// regions may be visited more than once.
+    ///
+    /// [`render_regionset`]: #method.render_regionset
+    pub fn render_regionset_with<'fs, FS, W, P, F>(&self,
+                                                   rset: &Regionset<'fs, FS>,
+                                                   out: &W,
+                                                   out_dir: P,
+                                                   mut visit: F)
+                                                   -> Result<Vec<(i64, i64)>, OverviewerError>
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read + Seek,
+              W: rio::FSWrite,
+              P: AsRef<rio::Path>,
+              F: FnMut(Coord<coords::Chunk, coords::World>, &Chunk)
+    {
+        let regions: Vec<_> = rset.get_regions()
+            .into_iter()
+            .filter(|r| match self.crop {
+                Some(ref crop) => {
+                    r.x * TILE_BLOCKS <= crop.max_x && (r.x + 1) * TILE_BLOCKS > crop.min_x &&
+                    r.z * TILE_BLOCKS <= crop.max_z && (r.z + 1) * TILE_BLOCKS > crop.min_z
+                }
+                None => true,
+            })
+            .collect();
+        let mut tiles = Vec::with_capacity(regions.len());
+        for region in &regions {
+            // a fresh view per region keeps the chunk cache from growing without bound
+            let view = RegionsetView::new(rset);
+            let img = self.north.rotate(&self.render_region(&view, *region));
+            view.for_each_chunk(&mut visit);
+            let (x, z) = self.north.rotate_tile(region.x, region.z);
+            let name = tile_name(self.zoom, x, z, self.format);
+            let mut f = try!(out.create(out_dir.as_ref().join(name)));
+            try!(self.format.save(img, &mut f));
+            tiles.push((x, z));
+        }
+        Ok(tiles)
+    }
+
+    fn block_color(&self, world: &BlockSource, pos: Coord<coords::Block, coords::World>, block: &BlockState) -> Rgba<u8> {
+        let mut color = self.palette.get(block);
+        if self.palette.needs_tinting {
+            if let (Some(tinter), Some(biome)) = (self.tinter.as_ref(), world.get_biome(pos)) {
+                if let Some(tint) = tinter.get_tint(&block.name, biome) {
+                    apply_tint(&mut color, tint);
+                }
+            }
+        }
+        color
+    }
+
+    fn render_column(&self, world: &BlockSource, x: i64, z: i64) -> Option<Column> {
+        if let Some(ref crop) = self.crop {
+            if !crop.contains(x, z) {
+                return None;
+            }
+        }
+
+        let mut surface = None;
+        let mut color = [0.0f32; 3];
+        let mut alpha = 0.0f32;
+
+        let mut y = world.get_height(x, z) - 1;
+        while y >= MIN_Y && alpha < 0.99 {
+            let pos = Coord::new(x, y, z);
+            y -= 1;
+
+            let block = world.get_block(pos);
+            if block.is_air() || self.primitives.iter().any(|p| p.hidden(world, pos)) {
+                continue;
+            }
+            if surface.is_none() {
+                surface = Some(pos);
+            }
+
+            // blend front to back
+            let c = self.block_color(world, pos, &block);
+            let a = c[3] as f32 / 255.0;
+            for i in 0..3 {
+                color[i] += (1.0 - alpha) * a * c[i] as f32;
+            }
+            alpha += (1.0 - alpha) * a;
+        }
+
+        surface.map(|pos| {
+            let mut corners = [1.0; 4];
+            for primitive in &self.primitives {
+                let shade = primitive.shade(world, pos, Face::Top);
+                for i in 0..4 {
+                    corners[i] *= shade[i];
+                }
+            }
+            let alpha = alpha.max(1.0 / 255.0);
+            Column {
+                pos: pos,
+                color: Rgba([(color[0] / alpha) as u8,
+                             (color[1] / alpha) as u8,
+                             (color[2] / alpha) as u8,
+                             (alpha * 255.0).round() as u8]),
+                corners: corners,
+            }
+        })
+    }
+
+    fn paint(&self, img: &mut Texture, world: &BlockSource, column: &Column, x: u32, y: u32, shade: f32) {
+        let n = self.pixels_per_block;
+        let c = column.corners;
+        for py in 0..n {
+            for px in 0..n {
+                // interpolate the corner shading across the block; corners go (-x, -z), (+x, -z),
+                // (+x, +z), (-x, +z)
+                let u = (px as f32 + 0.5) / n as f32;
+                let v = (py as f32 + 0.5) / n as f32;
+                let north = c[0] * (1.0 - u) + c[1] * u;
+                let south = c[3] * (1.0 - u) + c[2] * u;
+
+                let mut pixel = column.color;
+                darken(&mut pixel, (north * (1.0 - v) + south * v) * shade);
+                for primitive in &self.primitives {
+                    primitive.draw(world, column.pos, Face::Top, &mut pixel);
+                }
+                img.put_pixel(x + px, y + py, pixel);
+            }
+        }
+    }
+}
+
+// the same three shades that in-game maps use
+fn height_shade(y: i64, north_y: i64) -> f32 {
+    if y > north_y {
+        255.0 / 220.0
+    } else if y < north_y {
+        180.0 / 220.0
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Rgba;
+    use render::palette::BlockPalette;
+    use render::test::TestWorld;
+    use render::{CrossSection, Lighting};
+
+    fn palette() -> BlockPalette {
+        let mut palette = BlockPalette::builtin();
+        palette.insert("stone", Rgba([100, 100, 100, 255]));
+        palette.insert("sand", Rgba([200, 200, 0, 255]));
+        palette.insert("water", Rgba([0, 0, 200, 128]));
+        palette
+    }
+
+    #[test]
+    fn test_render_area() {
+        let mut world = TestWorld::default();
+        world.set((0, 10, 0), "stone", 0, 0);
+        world.set((1, 5, 0), "sand", 0, 0);
+        world.set((1, 6, 0), "water", 0, 15);
+
+        let mut renderer = TopDown::new(palette());
+        renderer.height_shading = false;
+        let img = renderer.render_area(&world, 0, 0, 3, 1);
+        assert_eq!(img.dimensions(), (3, 1));
+        assert_eq!(*img.get_pixel(0, 0), Rgba([100, 100, 100, 255]));
+        // half-transparent water over sand
+        assert_eq!(*img.get_pixel(1, 0), Rgba([99, 99, 100, 255]));
+        // nothing there at all
+        assert_eq!(img.get_pixel(2, 0)[3], 0);
+
+        renderer.pixels_per_block = 4;
+        assert_eq!(renderer.render_area(&world, 0, 0, 3, 1).dimensions(), (12, 4));
+    }
+
+    #[test]
+    fn test_render_primitives() {
+        let mut world = TestWorld::default();
+        world.set((0, 10, 0), "stone", 0, 0);
+        world.set((0, 11, 0), "sand", 0, 0);
+        world.set((0, 12, 0), "air", 0, 0);
+
+        let mut renderer = TopDown::new(palette());
+        renderer.height_shading = false;
+        renderer.primitives.push(Box::new(CrossSection { max_y: 10, min_y: None, highlight: None }));
+        renderer.primitives.push(Box::new(Lighting::night()));
+        let img = renderer.render_area(&world, 0, 0, 1, 1);
+        // sand is cut away, and the stone is lit by the dark block above the sand
+        let expected = (100.0 * Lighting::night().brightness(0.0)) as u8;
+        assert_eq!(*img.get_pixel(0, 0), Rgba([expected, expected, expected, 255]));
+    }
+
+    #[test]
+    fn test_height_shading() {
+        let mut world = TestWorld::default();
+        world.set((0, 10, 0), "stone", 0, 0);
+        world.set((0, 11, 1), "stone", 0, 0);
+        world.set((0, 9, 2), "stone", 0, 0);
+
+        let img = TopDown::new(palette()).render_area(&world, 0, 0, 1, 3);
+        assert_eq!(*img.get_pixel(0, 0), Rgba([100, 100, 100, 255]));
+        assert_eq!(*img.get_pixel(0, 1), Rgba([115, 115, 115, 255]));
+        assert_eq!(*img.get_pixel(0, 2), Rgba([81, 81, 81, 255]));
+    }
+}
@@ -0,0 +1,185 @@
+//! Statistics about what a world is made of
+//!
+//! [`WorldStats`] counts every block in a dimension, how ores are spread over Y levels, how much
+//! of the surface each biome covers, and how long players have spent in its chunks.  Ore
+//! histograms are handy for spotting X-ray mining: in a mined-out area, ores vanish from the
+//! levels they're found at much faster than the stone around them.
+//!
+//! [`WorldStats`]: struct.WorldStats.html
+
+use rio;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, Write};
+
+use coords::Coord;
+use world::{Chunk, HeightmapType, Regionset};
+
+/// Totals for a set of chunks
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldStats {
+    pub chunks: u64,
+
+    /// Sum of every chunk's `InhabitedTime`, in ticks
+    pub inhabited_time: i64,
+
+    /// Number of blocks of each type, by name.  Air isn't counted.
+    pub blocks: BTreeMap<String, u64>,
+
+    /// For each kind of ore, the number of blocks at each Y level
+    pub ores: BTreeMap<String, BTreeMap<i64, u64>>,
+
+    /// Number of block columns whose surface is in each biome, by name without the `minecraft:`
+    /// prefix
+    pub biome_area: BTreeMap<String, u64>,
+}
+
+impl WorldStats {
+    pub fn new() -> WorldStats {
+        WorldStats {
+            chunks: 0,
+            inhabited_time: 0,
+            blocks: BTreeMap::new(),
+            ores: BTreeMap::new(),
+            biome_area: BTreeMap::new(),
+        }
+    }
+
+    /// Reads every chunk in `rset`
+    pub fn scan<'fs, FS>(rset: &Regionset<'fs, FS>) -> WorldStats
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read + Seek
+    {
+        let mut stats = WorldStats::new();
+        for info in rset.get_chunks() {
+            if let Some(chunk) = rset.get_chunk(info.pos) {
+                stats.add_chunk(&chunk);
+            }
+        }
+        stats
+    }
+
+    pub fn add_chunk(&mut self, chunk: &Chunk) {
+        self.chunks += 1;
+        self.inhabited_time += chunk.inhabited_time().unwrap_or(0);
+
+        let blocks = &mut self.blocks;
+        let ores = &mut self.ores;
+        chunk.for_each_block(|pos, block| {
+            if block.is_air() {
+                return;
+            }
+            if is_ore(&block.name) {
+                *ores.entry(block.name.clone())
+                    .or_insert_with(BTreeMap::new)
+                    .entry(pos.y)
+                    .or_insert(0) += 1;
+            }
+            // looking the name up first saves allocating a key for every block
+            if let Some(count) = blocks.get_mut(&block.name) {
+                *count += 1;
+                return;
+            }
+            blocks.insert(block.name.clone(), 1);
+        });
+
+        // the biome of the top block in each column, skipping empty columns
+        let min_y = chunk.min_y();
+        for z in 0..16 {
+            for x in 0..16 {
+                let y = match chunk.get_height(HeightmapType::WorldSurface, Coord::new(x, 0, z)) {
+                    Some(h) if h > min_y => h - 1,
+                    _ => continue,
+                };
+                if let Some(biome) = chunk.get_biome(Coord::new(x, y, z)) {
+                    *self.biome_area.entry(biome.name().to_owned()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        // each ore as a list of [y, count] pairs
+        let ores: BTreeMap<&String, Vec<(i64, u64)>> = self.ores
+            .iter()
+            .map(|(ore, levels)| (ore, levels.iter().map(|(&y, &count)| (y, count)).collect()))
+            .collect();
+        json!({
+            "chunks": self.chunks,
+            "inhabited_time": self.inhabited_time,
+            "blocks": self.blocks,
+            "ores": ores,
+            "biome_area": self.biome_area,
+        })
+    }
+
+    /// Writes every statistic as a row of `kind,name,y,count`, where `y` is only filled in for
+    /// ores
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        try!(writeln!(w, "kind,name,y,count"));
+        try!(writeln!(w, "chunks,,,{}", self.chunks));
+        try!(writeln!(w, "inhabited_time,,,{}", self.inhabited_time));
+        for (name, count) in &self.blocks {
+            try!(writeln!(w, "block,{},,{}", name, count));
+        }
+        for (name, levels) in &self.ores {
+            for (y, count) in levels {
+                try!(writeln!(w, "ore,{},{},{}", name, y, count));
+            }
+        }
+        for (name, count) in &self.biome_area {
+            try!(writeln!(w, "biome,{},,{}", name, count));
+        }
+        Ok(())
+    }
+}
+
+/// Whether a block is an ore, including the deepslate and nether variants and ancient debris
+pub fn is_ore(name: &str) -> bool {
+    name.ends_with("_ore") || name == "minecraft:ancient_debris"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blocks::BlockState;
+    use fixtures::{TempWorld, WorldBuilder};
+    use world::{Dimension, Regionset};
+
+    #[test]
+    fn test_scan() {
+        let mut builder = WorldBuilder::new("Stats");
+        for x in 0..4 {
+            builder.set_block(Dimension::Overworld, x, 10, 0, BlockState::new("stone"));
+        }
+        let ore = BlockState::new("deepslate_diamond_ore");
+        builder.set_block(Dimension::Overworld, 0, -50, 0, ore.clone());
+        builder.set_block(Dimension::Overworld, 1, -50, 0, ore.clone());
+        builder.set_block(Dimension::Overworld, 2, -52, 0, ore);
+        builder.set_block(Dimension::Overworld, 20, 70, 0, BlockState::new("grass_block"));
+        builder.chunk(Dimension::Overworld, 0, 0).inhabited_time = 100;
+        builder.chunk(Dimension::Overworld, 1, 0).inhabited_time = 50;
+        builder.chunk(Dimension::Overworld, 1, 0).biome = "minecraft:forest".to_owned();
+        let tmp = TempWorld::new(&builder).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+
+        let stats = WorldStats::scan(&rset);
+        assert_eq!(stats.chunks, 2);
+        assert_eq!(stats.inhabited_time, 150);
+        assert_eq!(stats.blocks["minecraft:stone"], 4);
+        assert_eq!(stats.blocks.get("minecraft:air"), None);
+        let diamonds = &stats.ores["minecraft:deepslate_diamond_ore"];
+        assert_eq!(diamonds.iter().map(|(&y, &n)| (y, n)).collect::<Vec<_>>(),
+                   vec![(-52, 1), (-50, 2)]);
+        assert_eq!(stats.biome_area["plains"], 4);
+        assert_eq!(stats.biome_area["forest"], 1);
+
+        assert_eq!(stats.to_json()["ores"]["minecraft:deepslate_diamond_ore"],
+                   json!([[-52, 1], [-50, 2]]));
+        let mut csv = Vec::new();
+        stats.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("kind,name,y,count\nchunks,,,2\n"));
+        assert!(csv.contains("ore,minecraft:deepslate_diamond_ore,-50,2\n"));
+    }
+}
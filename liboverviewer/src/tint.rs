@@ -0,0 +1,181 @@
+//! Biome-based tinting for grass, foliage and water
+//!
+//! Many block textures are greyscale and get their color from the biome they're in.  Grass and
+//! foliage are multiplied by a color sampled from `colormap/grass.png` or `colormap/foliage.png`
+//! using the biome's temperature and downfall.  A few blocks (spruce and birch leaves) use a fixed
+//! color instead, and water uses a per-biome color.
+
+use image::Rgba;
+use rio;
+use std::io::{Read, Seek};
+
+use super::asset_manager::{AssetManager, Texture};
+use super::biome::Biome;
+use super::error::OverviewerError;
+
+const SWAMP_GRASS: u32 = 0x6a7039;
+const BADLANDS_GRASS: u32 = 0x90814d;
+const BADLANDS_FOLIAGE: u32 = 0x9e814d;
+const SPRUCE_LEAVES: u32 = 0x619961;
+const BIRCH_LEAVES: u32 = 0x80a755;
+const DEFAULT_WATER: u32 = 0x3f76e4;
+
+/// A colormap texture, sampled by biome climate
+pub struct Colormap {
+    image: Texture,
+}
+
+impl Colormap {
+    pub fn new(image: Texture) -> Colormap {
+        Colormap { image: image }
+    }
+
+    /// Samples this colormap the same way Minecraft does: temperature selects the column, and
+    /// downfall (scaled by temperature) selects the row
+    pub fn sample(&self, biome: &Biome) -> u32 {
+        let temperature = clamp(biome.temperature);
+        let downfall = clamp(biome.downfall) * temperature;
+        let (w, h) = self.image.dimensions();
+        let x = ((1.0 - temperature) * (w - 1) as f32) as u32;
+        let y = ((1.0 - downfall) * (h - 1) as f32) as u32;
+        let p = self.image.get_pixel(x, y);
+        (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32
+    }
+}
+
+/// Decides which color, if any, a tinted block face should be multiplied by
+pub struct BiomeTinter {
+    grass: Colormap,
+    foliage: Colormap,
+}
+
+impl BiomeTinter {
+    pub fn new(grass: Colormap, foliage: Colormap) -> BiomeTinter {
+        BiomeTinter {
+            grass: grass,
+            foliage: foliage,
+        }
+    }
+
+    /// Loads the grass and foliage colormaps from the assets
+    pub fn from_assets<'fs, FS>(assets: &AssetManager<'fs, FS>) -> Result<BiomeTinter, OverviewerError>
+        where FS: rio::FSRead<'fs>,
+              FS::ReadFile: Read + Seek
+    {
+        let grass = try!(assets.get_texture("colormap/grass"));
+        let foliage = try!(assets.get_texture("colormap/foliage"));
+        Ok(BiomeTinter::new(Colormap::new(grass), Colormap::new(foliage)))
+    }
+
+    /// The tint color (as `0xRRGGBB`) for the named block in the given biome, or `None` if the
+    /// block isn't tinted
+    ///
+    /// This goes by the block alone, not the `tintindex` of its model's faces: the faces of these
+    /// blocks that show from above are always the tinted ones.
+    pub fn get_tint(&self, block: &str, biome: &Biome) -> Option<u32> {
+        match block.trim_left_matches("minecraft:") {
+            "grass_block" | "grass" | "short_grass" | "tall_grass" | "fern" | "large_fern" |
+            "potted_fern" | "sugar_cane" => Some(self.grass_color(biome)),
+            "oak_leaves" | "jungle_leaves" | "acacia_leaves" | "dark_oak_leaves" |
+            "mangrove_leaves" | "vine" | "leaves" | "leaves2" => Some(self.foliage_color(biome)),
+            "spruce_leaves" => Some(SPRUCE_LEAVES),
+            "birch_leaves" => Some(BIRCH_LEAVES),
+            "water" | "flowing_water" | "bubble_column" | "water_cauldron" => {
+                Some(water_color(biome))
+            }
+            _ => None,
+        }
+    }
+
+    fn grass_color(&self, biome: &Biome) -> u32 {
+        match biome.name() {
+            "swamp" | "swamp_hills" | "mangrove_swamp" => SWAMP_GRASS,
+            "badlands" | "wooded_badlands" | "badlands_plateau" | "eroded_badlands" |
+            "modified_wooded_badlands_plateau" | "modified_badlands_plateau" => BADLANDS_GRASS,
+            "dark_forest" | "dark_forest_hills" => {
+                // dark forests blend the colormap with a fixed dark green
+                ((self.grass.sample(biome) & 0xfefefe) + 0x28340a) >> 1
+            }
+            _ => self.grass.sample(biome),
+        }
+    }
+
+    fn foliage_color(&self, biome: &Biome) -> u32 {
+        match biome.name() {
+            "swamp" | "swamp_hills" | "mangrove_swamp" => SWAMP_GRASS,
+            "badlands" | "wooded_badlands" | "badlands_plateau" | "eroded_badlands" |
+            "modified_wooded_badlands_plateau" | "modified_badlands_plateau" => BADLANDS_FOLIAGE,
+            _ => self.foliage.sample(biome),
+        }
+    }
+}
+
+/// The water color for a biome, as `0xRRGGBB`
+pub fn water_color(biome: &Biome) -> u32 {
+    match biome.name() {
+        "swamp" | "swamp_hills" => 0x617b64,
+        "mangrove_swamp" => 0x3a7a6a,
+        "warm_ocean" | "deep_warm_ocean" => 0x43d5ee,
+        "lukewarm_ocean" | "deep_lukewarm_ocean" => 0x45adf2,
+        "cold_ocean" | "deep_cold_ocean" | "snowy_taiga" | "snowy_beach" => 0x3d57d6,
+        "frozen_ocean" | "deep_frozen_ocean" | "frozen_river" => 0x3938c9,
+        _ => DEFAULT_WATER,
+    }
+}
+
+/// Multiplies a pixel by a tint color, leaving alpha alone
+pub fn apply_tint(pixel: &mut Rgba<u8>, tint: u32) {
+    let tint = [(tint >> 16) & 0xff, (tint >> 8) & 0xff, tint & 0xff];
+    for i in 0..3 {
+        pixel[i] = (pixel[i] as u32 * tint[i] / 255) as u8;
+    }
+}
+
+fn clamp(v: f32) -> f32 {
+    v.max(0.0).min(1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use biome::Biome;
+    use image::{ImageBuffer, Rgba};
+
+    // a colormap where red encodes the column and green encodes the row
+    fn gradient() -> Colormap {
+        Colormap::new(ImageBuffer::from_fn(256, 256, |x, y| Rgba([x as u8, y as u8, 0, 255])))
+    }
+
+    #[test]
+    fn test_colormap_sample() {
+        let plains = Biome::from_name("plains").unwrap();
+        // temperature 0.8, downfall 0.4 * 0.8
+        assert_eq!(gradient().sample(plains), 50 << 16 | 173 << 8);
+
+        // out of range temperatures are clamped
+        let desert = Biome::from_name("desert").unwrap();
+        assert_eq!(gradient().sample(desert), 255 << 8);
+    }
+
+    #[test]
+    fn test_get_tint() {
+        let tinter = BiomeTinter::new(gradient(), gradient());
+        let plains = Biome::from_name("plains").unwrap();
+        let swamp = Biome::from_name("swamp").unwrap();
+
+        assert_eq!(tinter.get_tint("minecraft:stone", plains), None);
+        assert_eq!(tinter.get_tint("minecraft:grass_block", plains),
+                   Some(gradient().sample(plains)));
+        assert_eq!(tinter.get_tint("minecraft:oak_leaves", swamp), Some(SWAMP_GRASS));
+        assert_eq!(tinter.get_tint("minecraft:birch_leaves", swamp), Some(BIRCH_LEAVES));
+        assert_eq!(tinter.get_tint("minecraft:water", swamp), Some(0x617b64));
+        assert_eq!(tinter.get_tint("minecraft:water", plains), Some(DEFAULT_WATER));
+    }
+
+    #[test]
+    fn test_apply_tint() {
+        let mut p = Rgba([255, 128, 0, 200]);
+        apply_tint(&mut p, 0x80ff40);
+        assert_eq!(p, Rgba([128, 128, 0, 200]));
+    }
+}
@@ -0,0 +1,308 @@
+//! The static web viewer
+//!
+//! The output directory of a render holds one subdirectory of tiles per [`Tileset`], plus an
+//! `index.html` that shows them with a bundled copy of Leaflet.  The viewer learns about the
+//! tilesets from `overviewer.json`, so everything can be served by any static file server, even
+//! offline.
+//!
+//! Tiles are named by [`tile_name`].  The deepest zoom level holds one tile per region, and each
+//! level above it halves the resolution, so that at zoom 0 the whole world fits in a tile or two.
+//!
+//! [`Tileset`]: struct.Tileset.html
+//! [`tile_name`]: ../render/fn.tile_name.html
+
+use image::{self, ImageBuffer, Rgba};
+use rio;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::io::{BufReader, Read, Seek, Write};
+
+use asset_manager::Texture;
+use coords::{self, Coord};
+use error::OverviewerError;
+use render::{tile_name, ImageFormat, NorthDirection};
+use world::Dimension;
+
+const INDEX_HTML: &'static str = include_str!("../web/index.html");
+const OVERVIEWER_JS: &'static str = include_str!("../web/overviewer.js");
+
+// Leaflet itself, vendored by web/fetch-leaflet.sh so the viewer works without reaching a CDN.
+// Its BSD license asks for the notice to go along with every copy.
+const LEAFLET: &'static [(&'static str, &'static [u8])] = &[
+    ("LICENSE.leaflet", include_bytes!("../web/LICENSE.leaflet")),
+    ("leaflet.js", include_bytes!("../web/leaflet.js")),
+    ("leaflet.css", include_bytes!("../web/leaflet.css")),
+    ("images/layers.png", include_bytes!("../web/images/layers.png")),
+    ("images/layers-2x.png", include_bytes!("../web/images/layers-2x.png")),
+    ("images/marker-icon.png", include_bytes!("../web/images/marker-icon.png")),
+    ("images/marker-icon-2x.png", include_bytes!("../web/images/marker-icon-2x.png")),
+    ("images/marker-shadow.png", include_bytes!("../web/images/marker-shadow.png")),
+];
+
+/// Everything the viewer needs to know to show one render
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Tileset {
+    /// The directory holding the tiles, relative to the output directory
+    pub name: String,
+    pub title: String,
+    pub world_name: String,
+    pub dimension: Dimension,
+    pub format: ImageFormat,
+
+    /// The width and height of every tile, in pixels
+    pub tile_size: u32,
+    pub pixels_per_block: u32,
+
+    /// The deepest zoom level, which has one tile per region
+    pub max_zoom: u32,
+    pub north: NorthDirection,
+    pub spawn: Option<Coord<coords::Block, coords::World>>,
+
+    /// Whether there's a `markers.json` next to the tiles
+    pub markers: bool,
+
+    /// Whether there's a `maps.json`, and in-game map images, next to the tiles
+    pub maps: bool,
+
+    /// Transparent tilesets laid out exactly like this one, in subdirectories next to its tiles
+    pub overlays: Vec<Overlay>,
+}
+
+impl Tileset {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "title": self.title,
+            "world": self.world_name,
+            "dimension": self.dimension.name(),
+            "path": format!("{}/", self.name),
+            "extension": self.format.extension(),
+            "tile_size": self.tile_size,
+            "pixels_per_block": self.pixels_per_block,
+            "min_zoom": 0,
+            "max_zoom": self.max_zoom,
+            "north_direction": self.north.name(),
+            "spawn": self.spawn.map(|s| vec![s.x, s.y, s.z]),
+            "markers": self.markers,
+            "maps": self.maps,
+            "overlays": self.overlays.iter().map(Overlay::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// A tileset that the viewer draws over another, and can turn on and off
+///
+/// Overlay tiles are always PNGs, so that they can be partly transparent.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Overlay {
+    /// The directory holding the tiles, relative to the directory of the tileset it's laid over
+    pub name: String,
+    pub title: String,
+}
+
+impl Overlay {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "path": format!("{}/", self.name),
+            "title": self.title,
+        })
+    }
+}
+
+/// The number of zoom levels needed so that zoom 0 covers the given tiles with at most two tiles
+/// in each direction
+pub fn zoom_levels_for(tiles: &[(i64, i64)]) -> u32 {
+    let extent = |coords: Vec<i64>| {
+        let min = coords.iter().cloned().min().unwrap_or(0);
+        let max = coords.iter().cloned().max().unwrap_or(0);
+        max - min + 1
+    };
+    let size = ::std::cmp::max(extent(tiles.iter().map(|t| t.0).collect()),
+                               extent(tiles.iter().map(|t| t.1).collect()));
+    let mut zoom = 0;
+    while (1 << zoom) < size {
+        zoom += 1;
+    }
+    zoom
+}
+
+/// Builds every zoom level above `max_zoom` out of the tiles already written there
+///
+/// Each tile is made by shrinking the four tiles beneath it.  Returns the number of tiles written.
+pub fn build_pyramid<'fs, FS, P>(fs: &'fs FS,
+                                 dir: P,
+                                 tiles: &[(i64, i64)],
+                                 max_zoom: u32,
+                                 format: ImageFormat,
+                                 tile_size: u32)
+                                 -> Result<usize, OverviewerError>
+    where FS: rio::FSRead<'fs> + rio::FSWrite,
+          FS::ReadFile: Read + Seek,
+          P: AsRef<rio::Path>
+{
+    let dir = dir.as_ref();
+    let mut written = 0;
+    let mut level: BTreeSet<(i64, i64)> = tiles.iter().cloned().collect();
+    for zoom in (0..max_zoom).rev() {
+        // shifting floors, so negative tiles find the right parent
+        let parents: BTreeSet<_> = level.iter().map(|&(x, y)| (x >> 1, y >> 1)).collect();
+        for &(x, y) in &parents {
+            let mut img = ImageBuffer::new(tile_size, tile_size);
+            for dy in 0..2 {
+                for dx in 0..2 {
+                    let child = (x * 2 + dx, y * 2 + dy);
+                    if !level.contains(&child) {
+                        continue;
+                    }
+                    let f = try!(fs.open(dir.join(tile_name(zoom + 1, child.0, child.1, format))));
+                    let child = try!(image::load(BufReader::new(f), image_format(format)));
+                    shrink_into(&child.to_rgba(),
+                                &mut img,
+                                dx as u32 * tile_size / 2,
+                                dy as u32 * tile_size / 2);
+                }
+            }
+            let mut f = try!(fs.create(dir.join(tile_name(zoom, x, y, format))));
+            try!(format.save(img, &mut f));
+            written += 1;
+        }
+        level = parents;
+    }
+    Ok(written)
+}
+
+fn image_format(format: ImageFormat) -> image::ImageFormat {
+    match format {
+        ImageFormat::Png => image::PNG,
+        ImageFormat::Jpeg(_) => image::JPEG,
+    }
+}
+
+// draws `src` at half size into `dest` with its top left corner at (x, y), averaging each 2x2
+// block of pixels
+fn shrink_into(src: &Texture, dest: &mut Texture, x: u32, y: u32) {
+    let (width, height) = src.dimensions();
+    for py in 0..height / 2 {
+        for px in 0..width / 2 {
+            let mut sum = [0u32; 4];
+            for &(sx, sy) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let p = src.get_pixel(px * 2 + sx, py * 2 + sy);
+                for i in 0..4 {
+                    sum[i] += p[i] as u32;
+                }
+            }
+            dest.put_pixel(x + px,
+                           y + py,
+                           Rgba([(sum[0] / 4) as u8,
+                                 (sum[1] / 4) as u8,
+                                 (sum[2] / 4) as u8,
+                                 (sum[3] / 4) as u8]));
+        }
+    }
+}
+
+/// Writes the viewer itself, and the descriptor listing `tilesets`, into `out_dir`
+///
+/// Leaflet is written alongside it, with its license and with its icons in an `images`
+/// directory.
+pub fn write_viewer<W, P>(fs: &W, out_dir: P, tilesets: &[Tileset]) -> Result<(), OverviewerError>
+    where W: rio::FSWrite,
+          P: AsRef<rio::Path>
+{
+    let out_dir = out_dir.as_ref();
+    let descriptor = json!({
+        "generator": "overviewer-ng",
+        "tilesets": tilesets.iter().map(Tileset::to_json).collect::<Vec<_>>(),
+    });
+
+    try!(fs.create_dir_all(out_dir.join("images")));
+    try!(try!(fs.create(out_dir.join("index.html"))).write_all(INDEX_HTML.as_bytes()));
+    try!(try!(fs.create(out_dir.join("overviewer.js"))).write_all(OVERVIEWER_JS.as_bytes()));
+    for &(name, data) in LEAFLET {
+        try!(try!(fs.create(out_dir.join(name))).write_all(data));
+    }
+    try!(write!(try!(fs.create(out_dir.join("overviewer.json"))), "{}\n", descriptor));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{ImageBuffer, Rgba};
+    use memfs::MemFS;
+    use render::{ImageFormat, NorthDirection};
+    use rio::FSRead;
+    use world::Dimension;
+
+    #[test]
+    fn test_zoom_levels() {
+        assert_eq!(zoom_levels_for(&[]), 0);
+        assert_eq!(zoom_levels_for(&[(0, 0)]), 0);
+        assert_eq!(zoom_levels_for(&[(-1, 0), (0, 0)]), 1);
+        assert_eq!(zoom_levels_for(&[(-3, 0), (4, 1)]), 3);
+    }
+
+    #[test]
+    fn test_shrink_into() {
+        let src = ImageBuffer::from_fn(4, 4, |x, _| {
+            if x % 2 == 0 { Rgba([200, 0, 0, 255]) } else { Rgba([0, 0, 100, 255]) }
+        });
+        let mut dest = ImageBuffer::new(4, 4);
+        shrink_into(&src, &mut dest, 2, 2);
+        assert_eq!(*dest.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(*dest.get_pixel(3, 3), Rgba([100, 0, 50, 255]));
+    }
+
+    #[test]
+    fn test_tileset_json() {
+        let tileset = Tileset {
+            name: "day".to_owned(),
+            title: "Daytime".to_owned(),
+            world_name: "Survival".to_owned(),
+            dimension: Dimension::Overworld,
+            format: ImageFormat::Jpeg(90),
+            tile_size: 512,
+            pixels_per_block: 1,
+            max_zoom: 3,
+            north: NorthDirection::UpperRight,
+            spawn: Some(Coord::new(10, 64, -20)),
+            markers: true,
+            maps: false,
+            overlays: vec![Overlay {
+                               name: "inhabited_time".to_owned(),
+                               title: "Time spent".to_owned(),
+                           }],
+        };
+        assert_eq!(tileset.to_json(),
+                   json!({
+                       "name": "day",
+                       "title": "Daytime",
+                       "world": "Survival",
+                       "dimension": "overworld",
+                       "path": "day/",
+                       "extension": "jpg",
+                       "tile_size": 512,
+                       "pixels_per_block": 1,
+                       "min_zoom": 0,
+                       "max_zoom": 3,
+                       "north_direction": "upper-right",
+                       "spawn": [10, 64, -20],
+                       "markers": true,
+                       "maps": false,
+                       "overlays": [{"path": "inhabited_time/", "title": "Time spent"}],
+                   }));
+    }
+
+    #[test]
+    fn test_write_viewer() {
+        let fs = MemFS::new();
+        write_viewer(&fs, "map", &[]).unwrap();
+        for name in &["index.html", "overviewer.json", "leaflet.js", "LICENSE.leaflet"] {
+            assert!(fs.exists(format!("map/{}", name)), "{} is missing", name);
+        }
+        assert!(fs.is_dir("map/images"));
+        assert!(fs.exists("map/images/marker-icon.png"));
+    }
+}
@@ -1,15 +1,23 @@
 use nbtrs::{Tag, RegionFile, Taglike};
 use lru_time_cache::LruCache;
 use rio;
+use serde_json::{self, Value};
 use super::error::OverviewerError;
 use std::path::{PathBuf, Path};
 use std::convert::From;
 use std::fs::File;
 use std::io::{Read, Seek};
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
+use super::biome::Biome;
+use super::blocks::BlockState;
 use super::coords;
 use super::coords::Coord;
+use super::block_entity::BlockEntity;
+use super::entity::Entity;
+use super::maps::MapItem;
 
 /// Encapsulates the concept of a Minecraft "world". A Minecraft world is a
 /// level.dat file, a players directory with info about each player, a data
@@ -25,17 +33,12 @@ pub struct World<'fs, FS: rio::FSRead<'fs>> {
 impl<'fs, FS> World<'fs, FS> where FS: rio::FSRead<'fs>, FS::ReadFile: Read + Seek {
     /// Given a path to a world (a directory containing a level.dat file), construct a World
     pub fn new<P: AsRef<rio::Path>>(fs: &'fs FS, p: P) -> Result<World<FS>, OverviewerError> {
-        use flate2::read::GzDecoder;
-
         let world_dir = p.as_ref();
         if ! fs.exists(world_dir) {
             return Err(From::from(format!("Path {:?} does not exist", world_dir)));
         }
 
-        let level_dat = world_dir.join("level.dat");
-        let level_dat_file = try!(fs.open(level_dat));
-        let mut decoder = try!(GzDecoder::new(level_dat_file));
-        let (_, level_dat_nbt) = try!(Tag::parse(&mut decoder));
+        let level_dat_nbt = try!(read_nbt_file(fs, world_dir.join("level.dat")));
 
         let mut regionsets = Vec::new();
         for entry in try!(fs.read_dir(world_dir)) {
@@ -59,6 +62,123 @@ impl<'fs, FS> World<'fs, FS> where FS: rio::FSRead<'fs>, FS::ReadFile: Read + Se
         })
     }
 
+    /// Parses the interesting parts of this world's level.dat into a [`LevelDat`]
+    ///
+    /// [`LevelDat`]: struct.LevelDat.html
+    pub fn get_level_dat(&self) -> Result<LevelDat, OverviewerError> {
+        LevelDat::from_tag(&self.level_dat)
+    }
+
+    /// Opens the regionset for one of the vanilla dimensions
+    pub fn get_dimension(&self, dim: Dimension) -> Result<Regionset<'fs, FS>, OverviewerError> {
+        Regionset::new(self.fs, self.world_dir.join(dim.region_dir()))
+    }
+
+    /// Opens the separate entity region files that Minecraft 1.17 and later write, if there are
+    /// any for this dimension
+    pub fn get_entities(&self, dim: Dimension) -> Option<Regionset<'fs, FS>> {
+        let dir = self.world_dir.join(dim.entities_dir());
+        if self.fs.exists(&dir) {
+            Regionset::new(self.fs, dir).ok()
+        } else {
+            None
+        }
+    }
+
+    /// The vanilla dimensions that this world has region files for
+    pub fn get_dimensions(&self) -> Vec<Dimension> {
+        Dimension::all()
+            .iter()
+            .cloned()
+            .filter(|dim| self.fs.exists(self.world_dir.join(dim.region_dir())))
+            .collect()
+    }
+
+    /// Every player that has played in this world, from `playerdata/` (1.7.6 and later, named by
+    /// UUID) and `players/` (older worlds, named by player name)
+    ///
+    /// Names and UUIDs are filled in from `usercache.json`, which a server keeps next to its world
+    /// directory.  Files that can't be read are logged and skipped.
+    pub fn players(&self) -> Result<Vec<Player>, OverviewerError> {
+        let cache = self.user_cache();
+        let mut players = Vec::new();
+        for (dir, by_uuid) in vec![("playerdata", true), ("players", false)] {
+            let dir = self.world_dir.join(dir);
+            if !self.fs.exists(&dir) {
+                continue;
+            }
+            for entry in try!(self.fs.read_dir(&dir)) {
+                let stem = match (entry.path().file_stem(), entry.path().extension()) {
+                    (Some(stem), Some("dat")) => stem.to_owned(),
+                    _ => continue,
+                };
+                let tag = match read_nbt_file(self.fs, entry.path()) {
+                    Ok(tag) => tag,
+                    Err(e) => {
+                        warn!("Skipping player file {:?}: {}", entry.path(), e);
+                        continue;
+                    }
+                };
+                let (uuid, name) = if by_uuid {
+                    let name = cache.iter().find(|u| u.0 == stem).map(|u| u.1.clone());
+                    (Some(stem), name)
+                } else {
+                    let uuid = cache.iter().find(|u| u.1 == stem).map(|u| u.0.clone());
+                    (uuid, Some(stem))
+                };
+                match Player::from_tag(uuid, name, &tag) {
+                    Ok(player) => players.push(player),
+                    Err(e) => warn!("Skipping player file {:?}: {}", entry.path(), e),
+                }
+            }
+        }
+        Ok(players)
+    }
+
+    /// Every in-game map item in `data/`, in no particular order
+    ///
+    /// Files that can't be read are logged and skipped.
+    pub fn maps(&self) -> Result<Vec<MapItem>, OverviewerError> {
+        let dir = self.world_dir.join("data");
+        let mut maps = Vec::new();
+        if !self.fs.exists(&dir) {
+            return Ok(maps);
+        }
+        for entry in try!(self.fs.read_dir(&dir)) {
+            let id = match (entry.path().file_stem(), entry.path().extension()) {
+                (Some(stem), Some("dat")) if stem.starts_with("map_") => {
+                    match stem["map_".len()..].parse() {
+                        Ok(id) => id,
+                        Err(_) => continue,
+                    }
+                }
+                _ => continue,
+            };
+            match read_nbt_file(self.fs, entry.path()).and_then(|tag| MapItem::from_tag(id, &tag)) {
+                Ok(map) => maps.push(map),
+                Err(e) => warn!("Skipping map file {:?}: {}", entry.path(), e),
+            }
+        }
+        Ok(maps)
+    }
+
+    // (uuid, name) pairs from the first usercache.json found, if any
+    fn user_cache(&self) -> Vec<(String, String)> {
+        let mut candidates = vec![self.world_dir.join("usercache.json")];
+        if let Some(parent) = self.world_dir.parent() {
+            candidates.push(parent.join("usercache.json"));
+        }
+        for path in candidates {
+            let mut text = String::new();
+            if let Ok(mut f) = self.fs.open(&path) {
+                if f.read_to_string(&mut text).is_ok() {
+                    return parse_user_cache(&text);
+                }
+            }
+        }
+        Vec::new()
+    }
+
     //pub fn get_regionsets(&self) -> ::std::slice::Iter<Regionset<T>> {
     //    self.regionsets.iter()
     //}
@@ -68,6 +188,157 @@ impl<'fs, FS> World<'fs, FS> where FS: rio::FSRead<'fs>, FS::ReadFile: Read + Se
     //}
 }
 
+/// A typed view of the commonly used fields of a world's level.dat
+///
+/// The raw NBT is still available as [`World::level_dat`] for anything not covered here.
+///
+/// [`World::level_dat`]: struct.World.html#structfield.level_dat
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct LevelDat {
+    pub level_name: String,
+
+    /// Only present in worlds saved by Minecraft 1.9 or later
+    pub data_version: Option<i32>,
+
+    /// The game version that last saved this world, e.g. "1.12.2".  Only present in worlds saved
+    /// by Minecraft 1.9 or later
+    pub version_name: Option<String>,
+
+    pub spawn: Coord<coords::Block, coords::World>,
+    pub seed: i64,
+
+    /// Milliseconds since the unix epoch
+    pub last_played: i64,
+    pub game_type: i32,
+    pub hardcore: bool,
+
+    /// Total ticks since the world was created
+    pub time: i64,
+
+    /// Time of day in ticks; 0 is sunrise and 24000 is a full day
+    pub day_time: i64,
+}
+
+impl LevelDat {
+    /// Given the root tag of a level.dat file, extract its fields
+    pub fn from_tag(tag: &Tag) -> Result<LevelDat, OverviewerError> {
+        let data = try!(tag.key("Data"));
+
+        // Minecraft 1.16 moved the seed into the world generation settings
+        let seed = match data.key("RandomSeed").as_i64() {
+            Ok(seed) => seed,
+            Err(_) => try!(data.key("WorldGenSettings").key("seed").as_i64()),
+        };
+
+        Ok(LevelDat {
+            level_name: try!(data.key("LevelName").as_string()).clone(),
+            data_version: data.key("DataVersion").as_i32().ok(),
+            version_name: data.key("Version").key("Name").as_string().ok().cloned(),
+            spawn: Coord::new(try!(data.key("SpawnX").as_i32()) as i64,
+                              try!(data.key("SpawnY").as_i32()) as i64,
+                              try!(data.key("SpawnZ").as_i32()) as i64),
+            seed: seed,
+            last_played: try!(data.key("LastPlayed").as_i64()),
+            game_type: try!(data.key("GameType").as_i32()),
+            hardcore: data.key("hardcore").as_i8().unwrap_or(0) != 0,
+            time: try!(data.key("Time").as_i64()),
+            day_time: data.key("DayTime").as_i64().unwrap_or(0),
+        })
+    }
+}
+
+/// A player, as last saved in the world's player data
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Player {
+    /// Only missing for players from very old worlds who aren't in `usercache.json`
+    pub uuid: Option<String>,
+
+    /// The last known name, if `usercache.json` has one
+    pub name: Option<String>,
+
+    /// `None` for modded dimensions
+    pub dimension: Option<Dimension>,
+    pub pos: Coord<coords::Block, coords::World>,
+
+    /// The bed (or respawn anchor) the player will respawn at, and the dimension it's in
+    pub spawn: Option<(Dimension, Coord<coords::Block, coords::World>)>,
+}
+
+impl Player {
+    /// Given the root tag of a player's .dat file, extract its fields
+    pub fn from_tag(uuid: Option<String>, name: Option<String>, tag: &Tag) -> Result<Player, OverviewerError> {
+        let pos = |i| tag.key("Pos").index(i).as_f64();
+        let pos = Coord::new(try!(pos(0)).floor() as i64,
+                             try!(pos(1)).floor() as i64,
+                             try!(pos(2)).floor() as i64);
+
+        // 1.21.5 gathered the spawn into one compound; before that it was SpawnX, SpawnY and
+        // SpawnZ, with SpawnDimension from 1.16 on
+        let spawn = match tag.key("respawn").key("pos").as_ints() {
+            Ok(p) if p.len() == 3 => {
+                Some((tag.key("respawn").key("dimension"),
+                      Coord::new(p[0] as i32 as i64, p[1] as i32 as i64, p[2] as i32 as i64)))
+            }
+            _ => {
+                match (tag.key("SpawnX").as_i32(), tag.key("SpawnY").as_i32(), tag.key("SpawnZ").as_i32()) {
+                    (Ok(x), Ok(y), Ok(z)) => {
+                        Some((tag.key("SpawnDimension"), Coord::new(x as i64, y as i64, z as i64)))
+                    }
+                    _ => None,
+                }
+            }
+        };
+        let spawn = spawn.map(|(dim, pos)| {
+            (dim.ok().and_then(Dimension::from_tag).unwrap_or(Dimension::Overworld), pos)
+        });
+
+        Ok(Player {
+            uuid: uuid,
+            name: name,
+            dimension: tag.key("Dimension").ok().and_then(Dimension::from_tag),
+            pos: pos,
+            spawn: spawn,
+        })
+    }
+}
+
+/// Reads the `(uuid, name)` pairs out of a `usercache.json`
+pub fn parse_user_cache(text: &str) -> Vec<(String, String)> {
+    let json: Value = match serde_json::from_str(text) {
+        Ok(json) => json,
+        Err(_) => return Vec::new(),
+    };
+    json.as_array()
+        .map(|users| {
+            users.iter()
+                .filter_map(|u| {
+                    let uuid = u.get("uuid").and_then(Value::as_str);
+                    let name = u.get("name").and_then(Value::as_str);
+                    match (uuid, name) {
+                        (Some(uuid), Some(name)) => Some((uuid.to_owned(), name.to_owned())),
+                        _ => None,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_else(Vec::new)
+}
+
+// level.dat, player data and maps are all gzipped NBT
+fn read_nbt_file<'fs, FS, P>(fs: &'fs FS, p: P) -> Result<Tag, OverviewerError>
+    where FS: rio::FSRead<'fs>,
+          FS::ReadFile: Read,
+          P: AsRef<rio::Path>
+{
+    use flate2::read::GzDecoder;
+
+    let mut decoder = try!(GzDecoder::new(try!(fs.open(p))));
+    let (_, tag) = try!(Tag::parse(&mut decoder));
+    Ok(tag)
+}
+
 //pub struct RegionsetIter<T>;
 //
 //impl<T> Iterator for RegionsetIter<T> {
@@ -92,6 +363,12 @@ pub struct Regionset<'fs, FS: rio::FSRead<'fs>> {
     regions: Vec<(i64, i64)>,
 
     region_cache: RefCell<LruCache<(i64, i64), RegionFile<FS::ReadFile>>>,
+
+    // Regions whose files couldn't be read, which are treated as empty
+    bad_regions: RefCell<HashSet<(i64, i64)>>,
+
+    // Decoded chunks for single block lookups, which tend to come in runs over the same chunk
+    chunk_cache: RefCell<LruCache<(i64, i64), Option<Rc<Chunk>>>>,
     fs: &'fs FS
 }
 
@@ -123,62 +400,186 @@ impl<'fs, FS> Regionset<'fs, FS> where FS: rio::FSRead<'fs>, FS::ReadFile: Read
             region_dir: region_dir.to_owned(),
             regions: regions,
             region_cache: RefCell::new(LruCache::with_capacity(16)),
+            bad_regions: RefCell::new(HashSet::new()),
+            chunk_cache: RefCell::new(LruCache::with_capacity(16)),
             fs: fs
         })
 
     }
 
+    /// The coordinates of every region in this regionset
+    pub fn get_regions(&self) -> Vec<Coord<coords::Region, coords::World>> {
+        self.regions.iter().map(|&(x, z)| Coord::new(x, 0, z)).collect()
+    }
+
     pub fn get_type(&self) -> String {
         unimplemented!()
     }
 
     pub fn chunk_exists(&self, xz: Coord<coords::Chunk, coords::World>) -> bool {
         let (c, r) = xz.split::<coords::Region>();
-        if !self.regions.contains(&(r.x, r.z)) {
-            return false;
-        }
-
-        let mut region_cache = self.region_cache.borrow_mut();
-        let region_file: &mut RegionFile<_> = region_cache.entry((r.x, r.z)).or_insert_with(|| {
-            let fp = self.region_dir.join(format!("r.{}.{}.mca", r.x, r.z));
-            let f = self.fs.open(fp).unwrap();
-            RegionFile::new(f).unwrap()
-        });
-
-        region_file.chunk_exists(c.x as u8, c.z as u8)
+        self.with_region_file((r.x, r.z), |region| region.chunk_exists(c.x as u8, c.z as u8))
+            .unwrap_or(false)
     }
 
     pub fn get_chunk(&self, xz: Coord<coords::Chunk, coords::World>) -> Option<Chunk> {
         // what regionfile is this chunk in?
         let (c, r) = xz.split::<coords::Region>();
-        if !self.regions.contains(&(r.x, r.z)) {
-            return None;
+        self.with_region_file((r.x, r.z), |region_file| {
+                if !region_file.chunk_exists(c.x as u8, c.z as u8) {
+                    return None;
+                }
+                region_file.load_chunk(c.x as u8, c.z as u8).ok().map(Chunk::new)
+            })
+            .and_then(|chunk| chunk)
+    }
+
+    /// Returns an iterator over all chunk metadata in this world. Iterates
+    /// over a [`ChunkInfo`] (position and mtime) for each chunk.  Other chunk data
+    /// is not returned here.
+    ///
+    /// Region files that can't be read, such as the empty ones Minecraft sometimes leaves behind,
+    /// are skipped with a warning.
+    ///
+    /// [`ChunkInfo`]: struct.ChunkInfo.html
+    pub fn get_chunks(&self) -> ChunkIter {
+        let mut chunks = Vec::new();
+        for &(rx, rz) in &self.regions {
+            let region = Coord::<coords::Region, coords::World>::new(rx, 0, rz);
+            self.with_region_file((rx, rz), |region_file| {
+                for cx in 0..32 {
+                    for cz in 0..32 {
+                        if !region_file.chunk_exists(cx, cz) {
+                            continue;
+                        }
+                        let in_region =
+                            Coord::<coords::Chunk, coords::Region>::new(cx as i64, 0, cz as i64);
+                        chunks.push(ChunkInfo {
+                            pos: in_region.join(region),
+                            mtime: region_file.get_chunk_timestamp(cx, cz).unwrap_or(0),
+                        });
+                    }
+                }
+            });
         }
 
-        let mut region_cache = self.region_cache.borrow_mut();
-        let region_file: &mut RegionFile<_> = region_cache.entry((r.x, r.z)).or_insert_with(|| {
-            let fp = self.region_dir.join(format!("r.{}.{}.mca", r.x, r.z));
-            println!("loading {:?} from disk", fp);
-            let f = self.fs.open(fp).unwrap();
-            RegionFile::new(f).unwrap()
-        });
+        ChunkIter { chunks: chunks.into_iter() }
+    }
+
+    /// Reads every chunk in every region file, and returns a list of problems found
+    ///
+    /// Unlike the rest of the methods here, this reports region files that can't be read instead of
+    /// skipping them.
+    pub fn check(&self) -> Vec<RegionProblem> {
+        let mut problems = Vec::new();
+        for &(rx, rz) in &self.regions {
+            let region = Coord::<coords::Region, coords::World>::new(rx, 0, rz);
+            let problem = |chunk, msg| {
+                RegionProblem {
+                    region: region,
+                    chunk: chunk,
+                    msg: msg,
+                }
+            };
+
+            let fp = self.region_dir.join(format!("r.{}.{}.mca", rx, rz));
+            let mut region_file = match self.fs.open(fp).map_err(OverviewerError::from)
+                .and_then(|f| RegionFile::new(f).map_err(OverviewerError::from)) {
+                Ok(region_file) => region_file,
+                Err(e) => {
+                    problems.push(problem(None, e.to_string()));
+                    continue;
+                }
+            };
+
+            for cx in 0..32 {
+                for cz in 0..32 {
+                    if !region_file.chunk_exists(cx, cz) {
+                        continue;
+                    }
+                    let in_region =
+                        Coord::<coords::Chunk, coords::Region>::new(cx as i64, 0, cz as i64);
+                    let pos = in_region.join(region);
+                    match region_file.load_chunk(cx, cz) {
+                        Ok(tag) => {
+                            // chunks record their own position, which had better match
+                            let chunk = Chunk::new(tag);
+                            let level = chunk.level();
+                            let x = level.key("xPos").as_i32();
+                            let z = level.key("zPos").as_i32();
+                            match (x, z) {
+                                (Ok(x), Ok(z)) if x as i64 == pos.x && z as i64 == pos.z => {}
+                                (Ok(x), Ok(z)) => {
+                                    problems.push(problem(Some(pos),
+                                                          format!("Chunk claims to be at {}, {}",
+                                                                  x,
+                                                                  z)))
+                                }
+                                _ => problems.push(problem(Some(pos), "Missing xPos or zPos".into())),
+                            }
+                        }
+                        Err(e) => problems.push(problem(Some(pos), format!("{:?}", e))),
+                    }
+                }
+            }
+        }
+        problems
+    }
 
-        if !region_file.chunk_exists(c.x as u8, c.z as u8) {
+    // Runs `f` against the (possibly cached) region file at region coords `r`.  Returns None if
+    // there's no such region, or its file can't be read; the latter is warned about only once.
+    fn with_region_file<T, F>(&self, r: (i64, i64), f: F) -> Option<T>
+        where F: FnOnce(&mut RegionFile<FS::ReadFile>) -> T
+    {
+        if !self.regions.contains(&r) || self.bad_regions.borrow().contains(&r) {
             return None;
         }
 
-        if let Ok(chunk) = region_file.load_chunk(c.x as u8, c.z as u8) {
-            return Some(Chunk(chunk));
+        let mut region_cache = self.region_cache.borrow_mut();
+        if !region_cache.contains_key(&r) {
+            let fp = self.region_dir.join(format!("r.{}.{}.mca", r.0, r.1));
+            debug!("loading {:?} from disk", fp);
+            let region_file = self.fs.open(&fp).map_err(OverviewerError::from)
+                .and_then(|f| RegionFile::new(f).map_err(OverviewerError::from));
+            match region_file {
+                Ok(region_file) => {
+                    region_cache.insert(r, region_file);
+                }
+                Err(e) => {
+                    warn!("Skipping region file {:?}: {}", fp, e);
+                    self.bad_regions.borrow_mut().insert(r);
+                    return None;
+                }
+            }
         }
 
-        None
+        region_cache.get_mut(&r).map(f)
     }
 
-    /// Returns an iterator over all chunk metadata in this world. Iterates
-    /// over tuples of integers (x,z,mtime) for each chunk.  Other chunk data
-    /// is not returned here.
-    pub fn get_chunks(&self) -> ChunkIter {
-        unimplemented!()
+    // Like get_chunk, but keeps the last few chunks decoded
+    fn get_cached_chunk(&self, xz: Coord<coords::Chunk, coords::World>) -> Option<Rc<Chunk>> {
+        let key = (xz.x, xz.z);
+        if let Some(chunk) = self.chunk_cache.borrow_mut().get(&key) {
+            return chunk.clone();
+        }
+        let chunk = self.get_chunk(xz).map(Rc::new);
+        self.chunk_cache.borrow_mut().insert(key, chunk.clone());
+        chunk
+    }
+
+    /// Block light level at the given world position, or 0 if the chunk doesn't exist
+    ///
+    /// The last few chunks looked at are kept decoded, so looking at many blocks close together is
+    /// cheap.
+    pub fn get_block_light(&self, pos: Coord<coords::Block, coords::World>) -> u8 {
+        let (in_chunk, chunk) = pos.split::<coords::Chunk>();
+        self.get_cached_chunk(chunk).map(|c| c.get_block_light(in_chunk)).unwrap_or(0)
+    }
+
+    /// Sky light level at the given world position, or 15 if the chunk doesn't exist
+    pub fn get_sky_light(&self, pos: Coord<coords::Block, coords::World>) -> u8 {
+        let (in_chunk, chunk) = pos.split::<coords::Chunk>();
+        self.get_cached_chunk(chunk).map(|c| c.get_sky_light(in_chunk)).unwrap_or(15)
     }
 
     // TODO consider using something other than a u32 for time (like bring in one of the types from
@@ -201,36 +602,588 @@ impl<'fs, FS> Regionset<'fs, FS> where FS: rio::FSRead<'fs>, FS::ReadFile: Read
 }
 
 #[derive(Debug)]
-pub struct Chunk(Tag);
-pub struct ChunkIter;
+pub struct Chunk {
+    tag: Tag,
+
+    // block entities by position, indexed the first time one is looked up
+    block_entity_index: RefCell<Option<HashMap<(i64, i64, i64), BlockEntity>>>,
+}
+
+/// The heightmaps that Minecraft 1.13 and later keep for each chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeightmapType {
+    /// Highest block that blocks motion or contains a fluid
+    MotionBlocking,
+
+    /// Like `MotionBlocking`, but ignoring leaves
+    MotionBlockingNoLeaves,
+
+    /// Highest block that blocks motion, ignoring fluids
+    OceanFloor,
+
+    /// Highest non-air block
+    WorldSurface,
+}
+
+impl HeightmapType {
+    /// The NBT key for this heightmap
+    pub fn key(&self) -> &'static str {
+        match *self {
+            HeightmapType::MotionBlocking => "MOTION_BLOCKING",
+            HeightmapType::MotionBlockingNoLeaves => "MOTION_BLOCKING_NO_LEAVES",
+            HeightmapType::OceanFloor => "OCEAN_FLOOR",
+            HeightmapType::WorldSurface => "WORLD_SURFACE",
+        }
+    }
+
+    /// Parses a heightmap name, either the NBT key or its lowercase form
+    pub fn from_name(name: &str) -> Option<HeightmapType> {
+        match &*name.to_uppercase() {
+            "MOTION_BLOCKING" => Some(HeightmapType::MotionBlocking),
+            "MOTION_BLOCKING_NO_LEAVES" => Some(HeightmapType::MotionBlockingNoLeaves),
+            "OCEAN_FLOOR" => Some(HeightmapType::OceanFloor),
+            "WORLD_SURFACE" => Some(HeightmapType::WorldSurface),
+            _ => None,
+        }
+    }
+}
+
+/// The three vanilla dimensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Dimension {
+    Overworld,
+    Nether,
+    End,
+}
+
+impl Dimension {
+    /// The directory holding this dimension's region files, relative to the world directory
+    pub fn region_dir(&self) -> &'static str {
+        match *self {
+            Dimension::Overworld => "region",
+            Dimension::Nether => "DIM-1/region",
+            Dimension::End => "DIM1/region",
+        }
+    }
+
+    /// The directory holding this dimension's entity region files (1.17 and later)
+    pub fn entities_dir(&self) -> &'static str {
+        match *self {
+            Dimension::Overworld => "entities",
+            Dimension::Nether => "DIM-1/entities",
+            Dimension::End => "DIM1/entities",
+        }
+    }
+
+    /// The directory holding this dimension's point of interest region files (1.14 and later),
+    /// which track beds, job sites and nether portals
+    pub fn poi_dir(&self) -> &'static str {
+        match *self {
+            Dimension::Overworld => "poi",
+            Dimension::Nether => "DIM-1/poi",
+            Dimension::End => "DIM1/poi",
+        }
+    }
+
+    pub fn all() -> &'static [Dimension] {
+        static ALL: [Dimension; 3] = [Dimension::Overworld, Dimension::Nether, Dimension::End];
+        &ALL
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Dimension::Overworld => "overworld",
+            Dimension::Nether => "nether",
+            Dimension::End => "end",
+        }
+    }
+
+    /// Reads a dimension as saved in player data and maps: a number before 1.16, and a name since
+    pub fn from_tag(tag: &Tag) -> Option<Dimension> {
+        match *tag {
+            Tag::TagByte(id) => Dimension::from_id(id as i32),
+            Tag::TagInt(id) => Dimension::from_id(id),
+            Tag::TagString(ref name) => Dimension::from_name(name),
+            _ => None,
+        }
+    }
+
+    fn from_id(id: i32) -> Option<Dimension> {
+        match id {
+            0 => Some(Dimension::Overworld),
+            -1 => Some(Dimension::Nether),
+            1 => Some(Dimension::End),
+            _ => None,
+        }
+    }
+
+    /// Parses a dimension name such as `nether`, `minecraft:the_nether` or `DIM-1`
+    pub fn from_name(name: &str) -> Option<Dimension> {
+        match name.trim_left_matches("minecraft:") {
+            "overworld" => Some(Dimension::Overworld),
+            "nether" | "the_nether" | "DIM-1" => Some(Dimension::Nether),
+            "end" | "the_end" | "DIM1" => Some(Dimension::End),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata about a single chunk, as yielded by [`Regionset::get_chunks`]
+///
+/// [`Regionset::get_chunks`]: struct.Regionset.html#method.get_chunks
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct ChunkInfo {
+    pub pos: Coord<coords::Chunk, coords::World>,
+
+    /// Last modification time from the region file header, in seconds since the unix epoch
+    pub mtime: u32,
+}
+
+/// Something wrong with a region file, as found by [`Regionset::check`]
+///
+/// [`Regionset::check`]: struct.Regionset.html#method.check
+#[derive(Debug, Clone)]
+pub struct RegionProblem {
+    pub region: Coord<coords::Region, coords::World>,
+
+    /// The chunk with the problem, or `None` if the whole region file couldn't be read
+    pub chunk: Option<Coord<coords::Chunk, coords::World>>,
+    pub msg: String,
+}
+
+pub struct ChunkIter {
+    chunks: ::std::vec::IntoIter<ChunkInfo>,
+}
 
 impl Iterator for ChunkIter {
-    type Item = Chunk;
-    fn next(&mut self) -> Option<Chunk> {
-        unimplemented!()
+    type Item = ChunkInfo;
+    fn next(&mut self) -> Option<ChunkInfo> {
+        self.chunks.next()
     }
 }
 
 impl Chunk {
+    fn new(tag: Tag) -> Chunk {
+        Chunk {
+            tag: tag,
+            block_entity_index: RefCell::new(None),
+        }
+    }
+
     /// Heightmap for this chunk, pre-computed by Minecraft
     ///
     /// to index into this vec:
     ///
     /// let height = v.get(x + z*16)
     pub fn get_heightmap(&self) -> Vec<u32> {
-        let &Chunk(ref tag) = self;
+        let tag = &self.tag;
         // 256 tagints.  16x16
         //let h = map.get(x + (z*16)).unwrap() - 64;
         let data = tag.key("Level").key("HeightMap").as_ints().unwrap();
         return data.clone();
         //println!("height at x=3 z=12 {:?}", map.get(3 + 12*16));
     }
+
+    /// The Y coordinate just above the surface of a column, according to the given heightmap
+    ///
+    /// Chunks saved before 1.13 only have one heightmap (the lowest Y with full sky light), which
+    /// is used no matter which type is asked for.
+    pub fn get_height(&self,
+                      kind: HeightmapType,
+                      pos: Coord<coords::Block, coords::Chunk>)
+                      -> Option<i64> {
+        let level = self.level();
+        let column = (pos.z * 16 + pos.x) as usize;
+        if let Ok(data) = level.key("HeightMap").as_ints() {
+            return data.get(column).map(|&h| h as i64);
+        }
+
+        let data = match level.key("Heightmaps").key(kind.key()).as_longs() {
+            Ok(data) => data,
+            Err(_) => return None,
+        };
+        // 9 bits per entry, which spanned long boundaries before 1.16
+        let value = if data.len() == 36 {
+            get_packed_spanning(data, 9, column)
+        } else {
+            get_packed(data, 9, column)
+        };
+        Some(value as i64 + self.min_y())
+    }
+
+    /// The lowest Y coordinate that this chunk can hold blocks at
+    pub fn min_y(&self) -> i64 {
+        // chunks from 1.18 on record their lowest section, before that it was always 0
+        self.level().key("yPos").as_i32().map(|y| y as i64 * 16).unwrap_or(0)
+    }
+
+    /// The Y coordinate just above the highest non-air block in a column, worked out from the
+    /// blocks themselves, or [`min_y`] if the column is empty
+    ///
+    /// This is much slower than [`get_height`], and is for chunks that don't have heightmaps, such
+    /// as ones that haven't finished generating.
+    ///
+    /// [`min_y`]: #method.min_y
+    /// [`get_height`]: #method.get_height
+    pub fn compute_height(&self, pos: Coord<coords::Block, coords::Chunk>) -> i64 {
+        let top = self.get_sections()
+            .iter()
+            .filter_map(|s| s.key("Y").as_i8().ok())
+            .max()
+            .map(|y| (y as i64 + 1) * 16)
+            .unwrap_or(0);
+        let mut y = top - 1;
+        while y >= self.min_y() {
+            if !self.get_block(Coord::new(pos.x, y, pos.z)).is_air() {
+                return y + 1;
+            }
+            y -= 1;
+        }
+        self.min_y()
+    }
+
+    /// Total ticks that players have spent near this chunk, counting each player separately.  Only
+    /// saved by Minecraft 1.6 and later.
+    pub fn inhabited_time(&self) -> Option<i64> {
+        self.level().key("InhabitedTime").as_i64().ok()
+    }
+
+    /// The game tick (see [`LevelDat::time`]) at which this chunk was last saved
+    ///
+    /// [`LevelDat::time`]: struct.LevelDat.html#structfield.time
+    pub fn last_update(&self) -> Option<i64> {
+        self.level().key("LastUpdate").as_i64().ok()
+    }
+
+    /// The block at the given position
+    ///
+    /// Handles both numeric block IDs (before 1.13) and block state palettes (1.13 and later).
+    /// Positions in sections that don't exist are air.
+    pub fn get_block(&self, pos: Coord<coords::Block, coords::Chunk>) -> BlockState {
+        let (in_section, section) = pos.split::<coords::Section>();
+        let section = match self.get_section(section.y) {
+            Some(section) => section,
+            None => return BlockState::air(),
+        };
+        let index = section_index(in_section);
+
+        if let Ok(blocks) = section.key("Blocks").as_bytes() {
+            // the optional "Add" array holds the high 4 bits of the block ID
+            let mut id = blocks.get(index).cloned().unwrap_or(0) as u16;
+            if let Ok(add) = section.key("Add").as_bytes() {
+                id |= (get_nibble(add, index).unwrap_or(0) as u16) << 8;
+            }
+            let data = section.key("Data")
+                .as_bytes()
+                .ok()
+                .and_then(|data| get_nibble(data, index))
+                .unwrap_or(0);
+            return BlockState::from_legacy(id, data);
+        }
+
+        let (palette, data) = match block_palette(section) {
+            Some(palette) => palette,
+            None => return BlockState::air(),
+        };
+        palette.get(palette_index(palette.len(), data, index))
+            .map(parse_block_state)
+            .unwrap_or_else(BlockState::air)
+    }
+
+    /// Calls `f` with every block in every section that this chunk has
+    ///
+    /// This is much faster than calling [`get_block`] for every position, since each section's
+    /// palette is only decoded once.  Missing sections, which are all air, are skipped.
+    ///
+    /// [`get_block`]: #method.get_block
+    pub fn for_each_block<F>(&self, mut f: F)
+        where F: FnMut(Coord<coords::Block, coords::Chunk>, &BlockState)
+    {
+        for section in self.get_sections() {
+            let sy = match section.key("Y").as_i8() {
+                Ok(y) => y as i64,
+                Err(_) => continue,
+            };
+            let pos = |index: usize| {
+                Coord::new((index & 15) as i64,
+                           sy * 16 + (index >> 8) as i64,
+                           ((index >> 4) & 15) as i64)
+            };
+
+            if section.key("Blocks").is_ok() {
+                for index in 0..4096 {
+                    f(pos(index), &self.get_block(pos(index)));
+                }
+                continue;
+            }
+            if let Some((palette, data)) = block_palette(section) {
+                let palette: Vec<BlockState> = palette.iter().map(parse_block_state).collect();
+                for index in 0..4096 {
+                    if let Some(block) = palette.get(palette_index(palette.len(), data, index)) {
+                        f(pos(index), block);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Block light level (0-15) at the given position
+    ///
+    /// The Y coordinate may be anywhere in the chunk; the containing section is looked up for you.
+    /// Positions in sections that don't exist, or that have no light data, have no block light.
+    pub fn get_block_light(&self, pos: Coord<coords::Block, coords::Chunk>) -> u8 {
+        self.get_light("BlockLight", pos).unwrap_or(0)
+    }
+
+    /// Sky light level (0-15) at the given position
+    ///
+    /// Positions in sections that don't exist, or that have no light data, are treated as fully
+    /// exposed to the sky.
+    pub fn get_sky_light(&self, pos: Coord<coords::Block, coords::Chunk>) -> u8 {
+        self.get_light("SkyLight", pos).unwrap_or(15)
+    }
+
+    /// The biome at the given position
+    ///
+    /// Handles every way that Minecraft has stored biomes: one numeric ID per column (before 1.15),
+    /// one numeric ID per 4x4x4 cell (1.15 to 1.17), and per-section palettes of biome names
+    /// (1.18 and later).  Returns `None` if the chunk has no biome data or the biome is unknown.
+    pub fn get_biome(&self, pos: Coord<coords::Block, coords::Chunk>) -> Option<&'static Biome> {
+        let (in_section, section) = pos.split::<coords::Section>();
+        if let Some(biomes) = self.get_section(section.y).and_then(|s| s.key("biomes").ok()) {
+            let palette = match biomes.key("palette").as_list() {
+                Ok(palette) => palette,
+                Err(_) => return None,
+            };
+            let cell = ((in_section.y >> 2) << 4 | (in_section.z >> 2) << 2 |
+                        (in_section.x >> 2)) as usize;
+            // a palette with only one entry has no data array
+            let idx = match biomes.key("data").as_longs() {
+                Ok(data) => get_packed(data, palette_bits(palette.len(), 1), cell),
+                Err(_) => 0,
+            };
+            return palette.get(idx)
+                .and_then(|name| name.as_string().ok())
+                .and_then(|name| Biome::from_name(name));
+        }
+
+        let biomes = match self.level().key("Biomes") {
+            Ok(biomes) => biomes,
+            Err(_) => return None,
+        };
+        let column = (pos.z * 16 + pos.x) as usize;
+        if let Ok(data) = biomes.as_bytes() {
+            return data.get(column).and_then(|&id| Biome::from_id(id));
+        }
+        if let Ok(data) = biomes.as_ints() {
+            // 1.13 and 1.14 store ints, but still one per column
+            if data.len() == 256 {
+                return data.get(column).and_then(|&id| Biome::from_id(id as u8));
+            }
+            let layers = (data.len() / 16) as i64;
+            if layers == 0 {
+                return None;
+            }
+            let y = ::std::cmp::max(0, ::std::cmp::min(layers - 1, pos.y >> 2));
+            let cell = (y << 4 | (pos.z >> 2) << 2 | (pos.x >> 2)) as usize;
+            return data.get(cell).and_then(|&id| Biome::from_id(id as u8));
+        }
+
+        None
+    }
+
+    fn get_light(&self, name: &str, pos: Coord<coords::Block, coords::Chunk>) -> Option<u8> {
+        let (in_section, section) = pos.split::<coords::Section>();
+        self.get_section(section.y)
+            .and_then(|s| s.key(name).as_bytes().ok())
+            .and_then(|data| get_nibble(data, section_index(in_section)))
+    }
+
+    // Chunks saved before 1.18 keep everything inside a "Level" compound, newer chunks keep it at
+    // the root
+    fn level(&self) -> &Tag {
+        self.tag.key("Level").unwrap_or(&self.tag)
+    }
+
+    /// The raw NBT of every block entity (chests, signs and so on) in this chunk
+    pub fn get_block_entities(&self) -> Vec<&Tag> {
+        let level = self.level();
+        // renamed in 1.18
+        match level.key("TileEntities").or_else(|_| level.key("block_entities")).as_list() {
+            Ok(list) => list.iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// The raw NBT of every entity in this chunk
+    ///
+    /// Since 1.17 entities are kept in separate region files, which [`World::get_entities`] opens;
+    /// chunks from those files work here too.
+    ///
+    /// [`World::get_entities`]: struct.World.html#method.get_entities
+    pub fn get_entities(&self) -> Vec<&Tag> {
+        match self.level().key("Entities").as_list() {
+            Ok(list) => list.iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Every block entity in this chunk that could be decoded
+    pub fn block_entities(&self) -> Vec<BlockEntity> {
+        self.get_block_entities()
+            .into_iter()
+            .filter_map(|tag| match BlockEntity::from_tag(tag) {
+                Ok(block_entity) => Some(block_entity),
+                Err(e) => {
+                    debug!("Skipping block entity: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The block entity at a position in this chunk, if there is one
+    ///
+    /// The first lookup decodes every block entity in the chunk, so that the rest are cheap.
+    pub fn get_block_entity(&self, pos: Coord<coords::Block, coords::World>) -> Option<BlockEntity> {
+        let mut index = self.block_entity_index.borrow_mut();
+        if index.is_none() {
+            let block_entities = self.block_entities().into_iter();
+            *index = Some(block_entities.map(|b| ((b.x, b.y, b.z), b)).collect());
+        }
+        index.as_ref().unwrap().get(&(pos.x, pos.y, pos.z)).cloned()
+    }
+
+    /// Every entity in this chunk that could be decoded
+    ///
+    /// [`EntityReader`] also finds the entities kept in separate files since 1.17.
+    ///
+    /// [`EntityReader`]: ../entity/struct.EntityReader.html
+    pub fn entities(&self) -> Vec<Entity> {
+        self.get_entities()
+            .into_iter()
+            .filter_map(|tag| match Entity::from_tag(tag) {
+                Ok(entity) => Some(entity),
+                Err(e) => {
+                    debug!("Skipping entity: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the NBT for the section at the given section Y index, if it exists
+    fn get_section(&self, y: i64) -> Option<&Tag> {
+        self.get_sections().iter().find(|s| s.key("Y").as_i8().ok().map(|y| y as i64) == Some(y))
+    }
+
+    // every section's NBT, in the order they were saved
+    fn get_sections(&self) -> &[Tag] {
+        let level = self.level();
+        match level.key("Sections").or_else(|_| level.key("sections")).as_list() {
+            Ok(sections) => sections,
+            Err(_) => &[],
+        }
+    }
+}
+
+// index into a section's 4096-entry block arrays, which are stored YZX
+fn section_index(pos: Coord<coords::Block, coords::Section>) -> usize {
+    ((pos.y << 8) | (pos.z << 4) | pos.x) as usize
+}
+
+// the number of bits used for each entry in a palette's packed data array
+fn palette_bits(len: usize, min: usize) -> usize {
+    let mut bits = 0;
+    while (1 << bits) < len {
+        bits += 1;
+    }
+    ::std::cmp::max(bits, min)
+}
+
+// Reads entry `index` from a packed long array, where each long holds as many whole entries as
+// will fit, starting from the least significant bits (the layout used since 1.16)
+fn get_packed(data: &[i64], bits: usize, index: usize) -> usize {
+    let per_long = 64 / bits;
+    match data.get(index / per_long) {
+        Some(&long) => {
+            let shift = (index % per_long) * bits;
+            (((long as u64) >> shift) & ((1 << bits) - 1)) as usize
+        }
+        None => 0,
+    }
+}
+
+// Like get_packed, but for the pre-1.16 layout where entries are packed back to back and can be
+// split across two longs
+fn get_packed_spanning(data: &[i64], bits: usize, index: usize) -> usize {
+    let start = index * bits;
+    let (long, offset) = (start / 64, start % 64);
+    let mut value = match data.get(long) {
+        Some(&l) => (l as u64) >> offset,
+        None => return 0,
+    };
+    if offset + bits > 64 {
+        if let Some(&l) = data.get(long + 1) {
+            value |= (l as u64) << (64 - offset);
+        }
+    }
+    (value & ((1 << bits) - 1)) as usize
+}
+
+// A section's block state palette, and the packed indexes into it.  A palette with only one
+// entry has no data array.
+fn block_palette(section: &Tag) -> Option<(&Vec<Tag>, Option<&Vec<i64>>)> {
+    // 1.18 moved the palette and its data into a "block_states" compound
+    let (palette, data) = match section.key("block_states") {
+        Ok(states) => (states.key("palette"), states.key("data")),
+        Err(_) => (section.key("Palette"), section.key("BlockStates")),
+    };
+    palette.as_list().ok().map(|palette| (palette, data.as_longs().ok()))
+}
+
+fn palette_index(len: usize, data: Option<&Vec<i64>>, index: usize) -> usize {
+    match data {
+        Some(data) => {
+            let bits = palette_bits(len, 4);
+            // before 1.16, entries could span two longs
+            if 64 % bits != 0 && data.len() == 4096 * bits / 64 {
+                get_packed_spanning(data, bits, index)
+            } else {
+                get_packed(data, bits, index)
+            }
+        }
+        None => 0,
+    }
+}
+
+fn parse_block_state(tag: &Tag) -> BlockState {
+    let name = tag.key("Name").as_string().map(|s| s.as_ref()).unwrap_or("air");
+    let mut state = BlockState::new(name);
+    if let Ok(properties) = tag.key("Properties").as_map() {
+        for (key, value) in properties {
+            if let Ok(value) = value.as_string() {
+                state.properties.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    state
+}
+
+// light (and legacy block metadata) is packed two entries per byte, low nibble first
+fn get_nibble(data: &[u8], index: usize) -> Option<u8> {
+    data.get(index >> 1).map(|b| if index & 1 == 0 { b & 0x0f } else { b >> 4 })
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use blocks::BlockState;
     use coords::Coord;
+    use fixtures::{TempWorld, WorldBuilder};
+    use nbt::compound;
     use ::rio;
 
     fn build_fs() -> rio::Native {
@@ -244,58 +1197,282 @@ mod test {
         let world = World::new(&fs, "/").unwrap();
     }
 
+    // chunks in two regions, each stamped with a different modification time
+    fn sample_world() -> TempWorld {
+        let mut builder = WorldBuilder::new("Sample");
+        builder.spawn = (8, 70, -8);
+        builder.seed = -4172144997902289642;
+        for &(cx, cz, timestamp) in &[(0, 0, 1454033798), (4, 8, 1454034069), (-1, -33, 1)] {
+            builder.chunk(Dimension::Overworld, cx, cz).timestamp = timestamp;
+        }
+        TempWorld::new(&builder).unwrap()
+    }
+
     #[test]
     fn test_regionset() {
-        let fs: rio::Native = build_fs();
-        let rset = Regionset::new(&fs, "tests/data/OTD/world_189/region").unwrap();
-        assert_eq!(rset.regions.len(), 6);
+        let tmp = sample_world();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        assert_eq!(rset.regions.len(), 2);
+        assert!(rset.check().is_empty());
     }
 
     #[test]
     fn test_world_open() {
-        let fs: rio::Native = build_fs();
-        let world = World::new(&fs, "tests/data/OTD/world_189/").unwrap();
+        let tmp = sample_world();
+        let world = tmp.world().unwrap();
         assert_eq!(world.regionsets.len(), 1);
+        assert_eq!(world.get_dimensions(), vec![Dimension::Overworld]);
     }
 
     #[test]
     fn test_regionset_get_chunk() {
         use nbtrs::Taglike;
-        let fs: rio::Native = build_fs();
+        let tmp = sample_world();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
 
-        {
-            let mut rset = Regionset::new(&fs, "tests/data/OTD/world_189/region").unwrap();
-            let Chunk(chunk) = rset.get_chunk(Coord::new(0, 0, 0)).unwrap();
-            let x = &chunk.key("Level").key("xPos").as_i32().unwrap();
-            let z = &chunk.key("Level").key("zPos").as_i32().unwrap();
-            assert_eq!(x, &0);
-            assert_eq!(z, &0);
-        }
-        {
-            let mut rset = Regionset::new(&fs, "tests/data/OTD/world_189/region").unwrap();
-            let Chunk(chunk) = rset.get_chunk(Coord::new(4, 0, 8)).unwrap();
-            let x = &chunk.key("Level").key("xPos").as_i32().unwrap();
-            let z = &chunk.key("Level").key("zPos").as_i32().unwrap();
-            assert_eq!(x, &4);
-            assert_eq!(z, &8);
+        for &(x, z) in &[(0, 0), (4, 8), (-1, -33)] {
+            let chunk = rset.get_chunk(Coord::new(x, 0, z)).unwrap().tag;
+            assert_eq!(chunk.key("xPos").as_i32().unwrap() as i64, x);
+            assert_eq!(chunk.key("zPos").as_i32().unwrap() as i64, z);
         }
+        assert!(rset.get_chunk(Coord::new(1, 0, 0)).is_none());
+        assert!(!rset.chunk_exists(Coord::new(100, 0, 100)));
     }
 
     #[test]
     fn test_regionset_get_chunk_mtime() {
-        let fs: rio::Native = build_fs();
-        let mut rset = Regionset::new(&fs, "tests/data/OTD/world_189/region").unwrap();
+        let tmp = sample_world();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
         assert_eq!(rset.get_chunk_mtime(Coord::new(4, 0, 8)), Some(1454034069));
-        assert_eq!(rset.get_chunk_mtime(Coord::new(12, 0, 3)), Some(1454033798));
+        assert_eq!(rset.get_chunk_mtime(Coord::new(0, 0, 0)), Some(1454033798));
+    }
+
+    #[test]
+    fn test_regionset_get_chunks() {
+        let tmp = sample_world();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        let chunks: Vec<ChunkInfo> = rset.get_chunks().collect();
+        assert_eq!(chunks.len(), 3);
+        let info = chunks.iter().find(|c| c.pos.x == 4 && c.pos.z == 8).unwrap();
+        assert_eq!(info.mtime, 1454034069);
+    }
+
+    #[test]
+    fn test_regionset_empty_region_file() {
+        use rio::FSWrite;
+        // Minecraft sometimes leaves empty region files behind
+        let tmp = sample_world();
+        tmp.fs.create(tmp.path().join("region/r.5.5.mca")).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        assert_eq!(rset.get_regions().len(), 3);
+        assert_eq!(rset.get_chunks().count(), 3);
+        assert!(!rset.chunk_exists(Coord::new(160, 0, 160)));
+        assert!(rset.get_chunk(Coord::new(160, 0, 160)).is_none());
+        assert_eq!(rset.check().len(), 1);
+    }
+
+    #[test]
+    fn test_chunk_blocks() {
+        let mut builder = WorldBuilder::new("Blocks");
+        let mut stairs = BlockState::new("oak_stairs");
+        stairs.properties.insert("facing".to_owned(), "east".to_owned());
+        builder.set_block(Dimension::Overworld, 3, -64, 12, BlockState::new("bedrock"));
+        builder.set_block(Dimension::Overworld, 3, 70, 12, stairs.clone());
+        // enough different blocks to need more than four bits per entry
+        for i in 0..20 {
+            let block = BlockState::new(&format!("block_{}", i));
+            builder.set_block(Dimension::Overworld, i % 16, 16 + i / 16, 5, block);
+        }
+        let tmp = TempWorld::new(&builder).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        let chunk = rset.get_chunk(Coord::new(0, 0, 0)).unwrap();
+
+        assert_eq!(chunk.min_y(), -64);
+        assert_eq!(chunk.get_block(Coord::new(3, -64, 12)).name, "minecraft:bedrock");
+        assert_eq!(chunk.get_block(Coord::new(3, 70, 12)), stairs);
+        assert_eq!(chunk.get_block(Coord::new(3, 71, 12)), BlockState::air());
+        assert_eq!(chunk.get_block(Coord::new(3, 17, 5)).name, "minecraft:block_19");
+        assert_eq!(chunk.get_block(Coord::new(15, 16, 5)).name, "minecraft:block_15");
+    }
+
+    #[test]
+    fn test_chunk_block_entity() {
+        let mut builder = WorldBuilder::new("Block entities");
+        for &(x, z, id) in &[(20, 5, "minecraft:chest"), (21, 5, "minecraft:barrel")] {
+            let tag = compound(vec![("id", Tag::TagString(id.to_owned())),
+                                    ("x", Tag::TagInt(x)),
+                                    ("y", Tag::TagInt(64)),
+                                    ("z", Tag::TagInt(z))]);
+            builder.add_block_entity(Dimension::Overworld, x as i64, z as i64, tag);
+        }
+        let tmp = TempWorld::new(&builder).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        let chunk = rset.get_chunk(Coord::new(1, 0, 0)).unwrap();
+
+        assert_eq!(chunk.get_block_entity(Coord::new(20, 64, 5)).unwrap().id, "minecraft:chest");
+        assert_eq!(chunk.get_block_entity(Coord::new(21, 64, 5)).unwrap().id, "minecraft:barrel");
+        assert_eq!(chunk.get_block_entity(Coord::new(20, 65, 5)), None);
+    }
+
+    #[test]
+    fn test_chunk_biome() {
+        let mut builder = WorldBuilder::new("Desert");
+        builder.chunk(Dimension::Overworld, 4, 8).biome = "minecraft:desert".to_owned();
+        let tmp = TempWorld::new(&builder).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        let chunk = rset.get_chunk(Coord::new(4, 0, 8)).unwrap();
+        assert_eq!(chunk.get_biome(Coord::new(3, 64, 12)).unwrap().name(), "desert");
+        assert_eq!(chunk.get_biome(Coord::new(3, -60, 12)).unwrap().name(), "desert");
+    }
+
+    #[test]
+    fn test_chunk_light() {
+        let mut builder = WorldBuilder::new("Light");
+        builder.chunk(Dimension::Overworld, 0, 0).set_block_light(5, 40, 6, 14);
+        let tmp = TempWorld::new(&builder).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        let chunk = rset.get_chunk(Coord::new(0, 0, 0)).unwrap();
+        assert_eq!(chunk.get_block_light(Coord::new(5, 40, 6)), 14);
+        assert_eq!(chunk.get_block_light(Coord::new(6, 40, 6)), 0);
+        // the very top of the world is always in full sky light
+        assert_eq!(chunk.get_sky_light(Coord::new(0, 319, 0)), 15);
+        assert_eq!(rset.get_block_light(Coord::new(5, 40, 6)), 14);
+        assert_eq!(rset.get_block_light(Coord::new(6, 40, 6)), 0);
+        assert_eq!(rset.get_sky_light(Coord::new(5, 40, 1000)), 15);
+    }
+
+    #[test]
+    fn test_world_level_dat() {
+        let tmp = sample_world();
+        let world = tmp.world().unwrap();
+        let level = world.get_level_dat().unwrap();
+        assert_eq!(level.level_name, "Sample");
+        assert_eq!(level.seed, -4172144997902289642);
+        assert_eq!((level.spawn.x, level.spawn.y, level.spawn.z), (8, 70, -8));
+        assert_eq!(level.version_name, Some("1.20.1".to_owned()));
+    }
+
+    #[test]
+    fn test_world_players() {
+        use nbt::compound;
+
+        let mut builder = WorldBuilder::new("Players");
+        let uuid = "0f8fad5b-d9cb-469f-a165-70867728950e";
+        let pos = Tag::TagList(vec![Tag::TagDouble(1.5), Tag::TagDouble(80.0), Tag::TagDouble(-3.5)]);
+        builder.add_player(uuid, compound(vec![("Pos", pos)]));
+        builder.user_cache.push((uuid.to_owned(), "Alice".to_owned()));
+        let tmp = TempWorld::new(&builder).unwrap();
+
+        let players = tmp.world().unwrap().players().unwrap();
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].name, Some("Alice".to_owned()));
+        assert_eq!((players[0].pos.x, players[0].pos.y, players[0].pos.z), (1, 80, -4));
+    }
+
+    #[test]
+    fn test_nibbles() {
+        let data = [0x21, 0xf3];
+        assert_eq!(get_nibble(&data, 0), Some(1));
+        assert_eq!(get_nibble(&data, 1), Some(2));
+        assert_eq!(get_nibble(&data, 2), Some(3));
+        assert_eq!(get_nibble(&data, 3), Some(15));
+        assert_eq!(get_nibble(&data, 4), None);
+
+        assert_eq!(section_index(Coord::new(1, 2, 3)), 2 * 256 + 3 * 16 + 1);
+    }
+
+    #[test]
+    fn test_packed_longs() {
+        assert_eq!(palette_bits(1, 0), 0);
+        assert_eq!(palette_bits(2, 0), 1);
+        assert_eq!(palette_bits(5, 0), 3);
+        assert_eq!(palette_bits(5, 4), 4);
+
+        // 3-bit entries: 21 per long, with the last bit unused
+        let data = [(5 << 3) | 2, 7];
+        assert_eq!(get_packed(&data, 3, 0), 2);
+        assert_eq!(get_packed(&data, 3, 1), 5);
+        assert_eq!(get_packed(&data, 3, 21), 7);
+        assert_eq!(get_packed(&data, 3, 42), 0);
+
+        // 5-bit entries packed back to back: entry 12 spans the first two longs
+        let data = [(0b00011 << 60) | 9, 0b1];
+        assert_eq!(get_packed_spanning(&data, 5, 0), 9);
+        assert_eq!(get_packed_spanning(&data, 5, 12), 0b10011);
+    }
+
+    #[test]
+    fn test_user_cache() {
+        let cache = parse_user_cache(r#"[{"name":"Alice","uuid":"0f8fad5b-d9cb-469f-a165-70867728950e",
+                                          "expiresOn":"2024-01-01 00:00:00 +0000"},
+                                         {"name":"broken"}]"#);
+        assert_eq!(cache,
+                   vec![("0f8fad5b-d9cb-469f-a165-70867728950e".to_owned(), "Alice".to_owned())]);
+        assert!(parse_user_cache("not json").is_empty());
+    }
+
+    #[test]
+    fn test_player_from_tag() {
+        use std::collections::HashMap;
+
+        let mut tag = HashMap::new();
+        tag.insert("Pos".to_owned(),
+                   Tag::TagList(vec![Tag::TagDouble(10.5), Tag::TagDouble(64.0), Tag::TagDouble(-0.5)]));
+        tag.insert("Dimension".to_owned(), Tag::TagString("minecraft:the_nether".to_owned()));
+        tag.insert("SpawnX".to_owned(), Tag::TagInt(100));
+        tag.insert("SpawnY".to_owned(), Tag::TagInt(70));
+        tag.insert("SpawnZ".to_owned(), Tag::TagInt(-200));
+        let player = Player::from_tag(None, Some("Alice".to_owned()), &Tag::TagCompound(tag.clone()))
+            .unwrap();
+        assert_eq!(player.dimension, Some(Dimension::Nether));
+        assert_eq!((player.pos.x, player.pos.y, player.pos.z), (10, 64, -1));
+        let (dim, spawn) = player.spawn.unwrap();
+        assert_eq!(dim, Dimension::Overworld);
+        assert_eq!((spawn.x, spawn.y, spawn.z), (100, 70, -200));
+
+        // before 1.16, dimensions were numbers and there was no spawn dimension
+        tag.insert("Dimension".to_owned(), Tag::TagInt(1));
+        tag.remove("SpawnX");
+        let player = Player::from_tag(None, None, &Tag::TagCompound(tag)).unwrap();
+        assert_eq!(player.dimension, Some(Dimension::End));
+        assert!(player.spawn.is_none());
     }
 
     #[test]
     fn test_chunk_heightmap() {
-        return;
-        let fs: rio::Native = rio::Native::new("/");
-        let mut rset = Regionset::new(&fs, "/storage/home/achin/.minecraft/saves/world_189/region").unwrap();
+        let mut builder = WorldBuilder::new("Heights");
+        builder.set_block(Dimension::Overworld, 98, 62, -14, BlockState::new("stone"));
+        builder.set_block(Dimension::Overworld, 98, 63, -14, BlockState::new("grass_block"));
+        builder.set_block(Dimension::Overworld, 99, -20, -14, BlockState::new("stone"));
+        let tmp = TempWorld::new(&builder).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
         let chunk = rset.get_chunk(Coord::new(6, 0, -1)).unwrap();
-        chunk.get_heightmap();
+        assert_eq!(chunk.get_height(HeightmapType::WorldSurface, Coord::new(2, 0, 2)), Some(64));
+        assert_eq!(chunk.get_height(HeightmapType::MotionBlocking, Coord::new(3, 0, 2)), Some(-19));
+        // an empty column is as low as the world goes
+        assert_eq!(chunk.get_height(HeightmapType::WorldSurface, Coord::new(0, 0, 0)), Some(-64));
+    }
+
+    #[test]
+    fn test_chunk_without_heightmap() {
+        use render::{BlockSource, RegionsetView};
+
+        let mut builder = WorldBuilder::new("Proto");
+        builder.set_block(Dimension::Overworld, 2, 63, 2, BlockState::new("grass_block"));
+        builder.set_block(Dimension::Overworld, 3, -30, 2, BlockState::new("stone"));
+        builder.chunk(Dimension::Overworld, 0, 0).heightmaps = false;
+        let tmp = TempWorld::new(&builder).unwrap();
+        let rset = Regionset::new(&tmp.fs, tmp.path().join("region")).unwrap();
+        let chunk = rset.get_chunk(Coord::new(0, 0, 0)).unwrap();
+        assert_eq!(chunk.get_height(HeightmapType::MotionBlocking, Coord::new(2, 0, 2)), None);
+        assert_eq!(chunk.compute_height(Coord::new(2, 0, 2)), 64);
+        assert_eq!(chunk.compute_height(Coord::new(3, 0, 2)), -29);
+        assert_eq!(chunk.compute_height(Coord::new(0, 0, 0)), -64);
+
+        // renderers fall back to looking at the blocks, rather than taking Y 0 as the surface
+        let view = RegionsetView::new(&rset);
+        assert_eq!(view.get_height(3, 2), -29);
+        assert_eq!(view.get_height(100, 100), -64);
     }
 
 }
@@ -0,0 +1,16 @@
+#!/bin/sh
+# Downloads a Leaflet release, 1.9.4 by default, into this directory, where web.rs embeds it.
+# The files are checked in, so this is only needed to upgrade; commit what it fetches.
+set -e
+
+VERSION=${1:-1.9.4}
+BASE="https://unpkg.com/leaflet@$VERSION"
+cd "$(dirname "$0")"
+
+mkdir -p images
+curl -sSfL -o LICENSE.leaflet "$BASE/LICENSE"
+for f in leaflet.js leaflet.css \
+         images/layers.png images/layers-2x.png \
+         images/marker-icon.png images/marker-icon-2x.png images/marker-shadow.png; do
+    curl -sSfL -o "$f" "$BASE/dist/$f"
+done
@@ -0,0 +1,221 @@
+// The overviewer web viewer.  Everything it needs is in overviewer.json, next to this file.
+(function () {
+  'use strict';
+
+  var map = null;
+  var config = null;
+  var current = null;
+  var layers = null;
+
+  // World (x, z) to map (x, z), following the tileset's north direction.  Each quarter turn is
+  // clockwise, matching how the tiles were rotated.
+  var TURNS = {'upper-left': 0, 'upper-right': 1, 'lower-right': 2, 'lower-left': 3};
+
+  function rotate(tileset, x, z) {
+    for (var i = 0; i < TURNS[tileset.north_direction]; i++) {
+      var t = x;
+      x = -z;
+      z = t;
+    }
+    return [x, z];
+  }
+
+  function unrotate(tileset, x, z) {
+    for (var i = 0; i < TURNS[tileset.north_direction]; i++) {
+      var t = z;
+      z = -x;
+      x = t;
+    }
+    return [x, z];
+  }
+
+  function toLatLng(tileset, x, z) {
+    var p = rotate(tileset, x, z);
+    return L.latLng(p[1], p[0]);
+  }
+
+  function fromLatLng(tileset, latlng) {
+    return unrotate(tileset, latlng.lng, latlng.lat);
+  }
+
+  // One map unit is one block, and the deepest zoom level shows pixels_per_block pixels for each
+  function makeCRS(tileset) {
+    var scale = tileset.pixels_per_block / Math.pow(2, tileset.max_zoom);
+    return L.extend({}, L.CRS.Simple, {
+      transformation: new L.Transformation(scale, 0, scale, 0)
+    });
+  }
+
+  function parseHash() {
+    var parts = window.location.hash.replace(/^#/, '').split('/');
+    if (parts.length !== 4) {
+      return null;
+    }
+    return {name: decodeURIComponent(parts[0]), x: +parts[1], z: +parts[2], zoom: +parts[3]};
+  }
+
+  function updateHash() {
+    var pos = fromLatLng(current, map.getCenter());
+    var hash = '#' + encodeURIComponent(current.name) + '/' + Math.round(pos[0]) + '/' +
+        Math.round(pos[1]) + '/' + map.getZoom();
+    window.history.replaceState(null, '', hash);
+  }
+
+  function show(tileset, view) {
+    if (map) {
+      map.remove();
+    }
+    current = tileset;
+    layers = null;
+    map = L.map('map', {crs: makeCRS(tileset), attributionControl: false});
+    L.tileLayer(tileset.path + '{z}.{x}.{y}.' + tileset.extension, {
+      tileSize: tileset.tile_size,
+      minZoom: tileset.min_zoom,
+      maxNativeZoom: tileset.max_zoom,
+      maxZoom: tileset.max_zoom + 2,
+      noWrap: true
+    }).addTo(map);
+
+    if (tileset.spawn) {
+      L.marker(toLatLng(tileset, tileset.spawn[0] + 0.5, tileset.spawn[2] + 0.5))
+          .bindPopup('Spawn')
+          .addTo(map);
+    }
+
+    (tileset.overlays || []).forEach(function (overlay) {
+      var layer = L.tileLayer(tileset.path + overlay.path + '{z}.{x}.{y}.png', {
+        tileSize: tileset.tile_size,
+        minZoom: tileset.min_zoom,
+        maxNativeZoom: tileset.max_zoom,
+        maxZoom: tileset.max_zoom + 2,
+        noWrap: true
+      });
+      addOverlay(layer, overlay.title, true);
+    });
+    if (tileset.maps) {
+      loadMaps(tileset);
+    }
+    if (tileset.markers) {
+      loadMarkers(tileset);
+    }
+
+    var center = view || {
+      x: tileset.spawn ? tileset.spawn[0] : 0,
+      z: tileset.spawn ? tileset.spawn[2] : 0,
+      zoom: tileset.max_zoom
+    };
+    map.setView(toLatLng(tileset, center.x, center.z), center.zoom);
+
+    addControls();
+    map.on('moveend', updateHash);
+    updateHash();
+    document.title = tileset.title + ' - ' + tileset.world;
+  }
+
+  // Overlays that can be turned on and off share one control, made when the first one loads.
+  // Hidden overlays start out turned off.
+  function addOverlay(layer, name, hidden) {
+    if (!layers) {
+      layers = L.control.layers(null, null, {collapsed: false}).addTo(map);
+    }
+    layers.addOverlay(hidden ? layer : layer.addTo(map), name);
+  }
+
+  // maps.json lists the in-game maps, already rotated to match the tiles
+  function loadMaps(tileset) {
+    var request = new XMLHttpRequest();
+    request.open('GET', tileset.path + 'maps.json');
+    request.onload = function () {
+      if (tileset !== current || request.status !== 200) {
+        return;
+      }
+      var maps = JSON.parse(request.responseText).maps;
+      var layer = L.layerGroup();
+      maps.forEach(function (m) {
+        var bounds = L.latLngBounds(toLatLng(tileset, m.bounds[0], m.bounds[1]),
+                                    toLatLng(tileset, m.bounds[2], m.bounds[3]));
+        L.imageOverlay(tileset.path + 'map_' + m.id + '.png', bounds, {opacity: 0.8})
+            .addTo(layer);
+      });
+      addOverlay(layer, 'In-game maps (' + maps.length + ')');
+    };
+    request.send();
+  }
+
+  // markers.json holds groups of markers, each of which can be turned on and off
+  function loadMarkers(tileset) {
+    var request = new XMLHttpRequest();
+    request.open('GET', tileset.path + 'markers.json');
+    request.onload = function () {
+      if (tileset !== current || request.status !== 200) {
+        return;
+      }
+      JSON.parse(request.responseText).groups.forEach(function (group) {
+        var options = {};
+        if (group.icon) {
+          options.icon = L.icon({iconUrl: group.icon, iconSize: [24, 24]});
+        }
+        var layer = L.layerGroup();
+        group.markers.forEach(function (marker) {
+          var m = L.marker(toLatLng(tileset, marker.x + 0.5, marker.z + 0.5), options);
+          var popup = L.DomUtil.create('div', 'overviewer-marker');
+          popup.textContent = marker.text || marker.id;
+          m.bindPopup(popup).addTo(layer);
+        });
+        addOverlay(layer, group.title + ' (' + group.markers.length + ')');
+      });
+    };
+    request.send();
+  }
+
+  function addControls() {
+    var picker = L.control({position: 'topright'});
+    picker.onAdd = function () {
+      var select = L.DomUtil.create('select');
+      config.tilesets.forEach(function (tileset, i) {
+        var option = L.DomUtil.create('option', '', select);
+        option.value = i;
+        option.textContent = tileset.world + ' - ' + tileset.title;
+        option.selected = tileset === current;
+      });
+      L.DomEvent.disableClickPropagation(select);
+      L.DomEvent.on(select, 'change', function () {
+        show(config.tilesets[select.value]);
+      });
+      return select;
+    };
+    picker.addTo(map);
+
+    var coords = L.control({position: 'bottomleft'});
+    coords.onAdd = function () {
+      var div = L.DomUtil.create('div', 'overviewer-coords');
+      map.on('mousemove', function (e) {
+        var pos = fromLatLng(current, e.latlng);
+        div.textContent = 'X ' + Math.floor(pos[0]) + '  Z ' + Math.floor(pos[1]);
+      });
+      return div;
+    };
+    coords.addTo(map);
+  }
+
+  var request = new XMLHttpRequest();
+  request.open('GET', 'overviewer.json');
+  request.onload = function () {
+    config = JSON.parse(request.responseText);
+    if (config.tilesets.length === 0) {
+      document.getElementById('map').textContent = 'Nothing has been rendered yet.';
+      return;
+    }
+    var view = parseHash();
+    var tileset = config.tilesets[0];
+    if (view) {
+      config.tilesets.forEach(function (t) {
+        if (t.name === view.name) {
+          tileset = t;
+        }
+      });
+    }
+    show(tileset, view && view.name === tileset.name ? view : null);
+  };
+  request.send();
+}());
@@ -1,60 +1,122 @@
-extern crate image;
-#[macro_use]
+//! Exports a world's elevation data for use in terrain analysis tools
+//!
+//! ```text
+//! height_map <WORLD> [--dimension overworld] [--type motion_blocking]
+//!            [--crop X1,Z1,X2,Z2] [--format png|raw|asc] [--output FILE]
+//! ```
+
+extern crate clap;
 extern crate liboverviewer;
 extern crate rio;
 
-use liboverviewer::world::*;
-use liboverviewer::coords;
-use rio::FSWrite;
+use clap::{App, Arg};
+use liboverviewer::heightmap::{CropBox, Heightmap};
+use liboverviewer::world::{Dimension, HeightmapType, Regionset};
 
 use std::fs::File;
-use std::path::Path;
-use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::process;
 
-fn main() {
-    let fs = rio::Native::new("/");
-    let rset = Regionset::new(&fs, "/storage/home/achin/.minecraft/saves/hmap/region").unwrap();
-
-    let mut chunk_cache: HashMap<(i64, i64), Chunk> = HashMap::new();
-
-    let imgx = 512;
-    let imgy = 512;
-    let mut imgbuf = image::ImageBuffer::new(imgx, imgy);
-    let mut cur_x = 0;
-
-    // produce an imagemap for a single region, where each block column is 1 pixel.  so 512 by 512
-    for chunk_x in 0..32 {
-        for chunk_z in 0..32 {
-            let chunk_in_region = coord!(chunk_x, 0, chunk_z);
-            if let Some(chunk) = rset.get_chunk(chunk_in_region) {
-                let map = chunk.get_heightmap();
-
-                for block_x in 0..16 {
-                    for block_z in 0..16 {
-                        let block_in_chunk =
-                            coord!{coords::Block, coords::Chunk, block_x, 0, block_z};
-                        let h = map.get((block_x + (block_z * 16)) as usize).unwrap();
-
-                        let block_in_region = block_in_chunk.join(chunk_in_region);
-                        assert!(block_in_region.x >= 0);
-                        assert!(block_in_region.z >= 0);
-                        // *pixel = image::Luma([255u8 - *h as u8 ]);
-                        imgbuf.put_pixel(block_in_region.x as u32,
-                                         block_in_region.z as u32,
-                                         image::Luma([255u8 - *h as u8]));
-                    }
-                }
-            }
-
-        }
+fn parse_crop(s: &str) -> Result<CropBox, String> {
+    let parts: Result<Vec<i64>, _> = s.split(',').map(|p| p.trim().parse::<i64>()).collect();
+    match parts {
+        Ok(ref p) if p.len() == 4 => Ok(CropBox::new(p[0], p[1], p[2], p[3])),
+        _ => Err(format!("Crop box must be X1,Z1,X2,Z2, not {:?}", s)),
     }
+}
+
+fn run() -> Result<(), String> {
+    let matches = App::new("height_map")
+        .about("Exports the heightmap of a Minecraft world")
+        .arg(Arg::with_name("world")
+            .required(true)
+            .help("Path to the world directory (the one containing level.dat)"))
+        .arg(Arg::with_name("dimension")
+            .short("d")
+            .long("dimension")
+            .takes_value(true)
+            .default_value("overworld")
+            .help("overworld, nether or end"))
+        .arg(Arg::with_name("type")
+            .short("t")
+            .long("type")
+            .takes_value(true)
+            .default_value("motion_blocking")
+            .possible_values(&["motion_blocking",
+                               "motion_blocking_no_leaves",
+                               "ocean_floor",
+                               "world_surface"]))
+        .arg(Arg::with_name("crop")
+            .short("c")
+            .long("crop")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .help("Only export the blocks between two corners, given as X1,Z1,X2,Z2"))
+        .arg(Arg::with_name("format")
+            .short("f")
+            .long("format")
+            .takes_value(true)
+            .default_value("png")
+            .possible_values(&["png", "raw", "asc"])
+            .help("16-bit greyscale PNG, raw little-endian i16s, or an ESRI ASCII grid"))
+        .arg(Arg::with_name("base")
+            .long("base")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("-64")
+            .help("The height that maps to 0 in PNG output"))
+        .arg(Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .help("Output file [default: heightmap.<format>]"))
+        .get_matches();
 
+    let dimension = matches.value_of("dimension").unwrap();
+    let dimension = try!(Dimension::from_name(dimension)
+        .ok_or_else(|| format!("Unknown dimension {:?}", dimension)));
+    let kind = HeightmapType::from_name(matches.value_of("type").unwrap()).unwrap();
+    let crop = match matches.value_of("crop") {
+        Some(s) => Some(try!(parse_crop(s))),
+        None => None,
+    };
+    let format = matches.value_of("format").unwrap();
+    let base = try!(matches.value_of("base").unwrap().parse::<i64>().map_err(|e| e.to_string()));
+    let output = matches.value_of("output")
+        .map(|s| s.to_owned())
+        .unwrap_or_else(|| format!("heightmap.{}", format));
 
-    let pb = rio::PathBuf::from(std::env::current_dir().unwrap());
-    let mut fout = fs.create(pb.join("hmap.png")).unwrap();
+    let world = try!(std::fs::canonicalize(matches.value_of("world").unwrap())
+        .map_err(|e| format!("Can't find world: {}", e)));
+    let fs = rio::Native::new("/");
+    let region_dir = rio::PathBuf::from(world).join(dimension.region_dir());
+    let rset = try!(Regionset::new(&fs, region_dir).map_err(|e| e.to_string()));
 
-    // We must indicate the image’s color type and what format to save as
-    let _ = image::ImageLuma8(imgbuf).save(&mut fout, image::PNG);
+    let map = try!(Heightmap::from_regionset(&rset, kind, crop)
+        .ok_or("No chunks to export".to_owned()));
+    if let Some((lo, hi)) = map.range() {
+        println!("Exporting {}x{} columns, heights {} to {}",
+                 map.bounds.width(),
+                 map.bounds.depth(),
+                 lo,
+                 hi);
+    }
 
+    let f = try!(File::create(&output).map_err(|e| format!("Can't create {}: {}", output, e)));
+    let mut w = BufWriter::new(f);
+    let written = match format {
+        "png" => map.write_png16(&mut w, base),
+        "raw" => map.write_raw(&mut w),
+        _ => map.write_ascii_grid(&mut w),
+    };
+    try!(written.and_then(|_| w.flush()).map_err(|e| format!("Can't write {}: {}", output, e)));
+    println!("Wrote {}", output);
+    Ok(())
+}
 
+fn main() {
+    if let Err(e) = run() {
+        let _ = writeln!(std::io::stderr(), "height_map: {}", e);
+        process::exit(1);
+    }
 }
@@ -1,5 +1,877 @@
+//! The overviewer command line
+//!
+//! ```text
+//! overviewer render <WORLD> <OUTPUT>   render a world to a directory of tiles
+//! overviewer render --config FILE      run every render in a config file
+//! overviewer info <WORLD>              summarize a world
+//! overviewer check <WORLD>             look for damaged region files
+//! overviewer maps <WORLD> <OUTPUT>     save every in-game map item as a PNG
+//! overviewer search <WORLD> <ITEM>     find containers holding an item
+//! overviewer prune <WORLD>             delete chunks that players have barely visited
+//! overviewer analyze <WORLD>           count blocks, ores by Y level and biomes, as JSON or CSV
+//! overviewer diff <OLD> <NEW> <OUTPUT> list changed chunks as JSON
+//! ```
+//!
+//! Open `index.html` in the output directory of a render (through a web server) to browse it.
+//! `render --diff <OLD>` adds an overlay of what changed since an older copy of the world.
+//!
+//! Exits with 0 on success, 1 on errors, and 2 if `check` found problems.  Set `RUST_LOG` or pass
+//! `-v` for more output.
+
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+#[cfg(test)]
+extern crate image;
 extern crate liboverviewer;
+#[macro_use]
+extern crate log;
+extern crate rio;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use liboverviewer::activity::{Activity, ActivityOverlay};
+use liboverviewer::asset_manager::AssetManager;
+use liboverviewer::config::{Config, RenderConfig};
+use liboverviewer::diff::{self, DiffOverlay};
+use liboverviewer::error::OverviewerError;
+use liboverviewer::heightmap::CropBox;
+use liboverviewer::maps;
+use liboverviewer::poi::{self, Poi};
+use liboverviewer::prune::{self, PruneOptions};
+use liboverviewer::render::{self, BlockPalette, ImageFormat, MobSpawns, NorthDirection,
+                            OverlayLayout, RenderMode, SlimeChunks, TopDown};
+use liboverviewer::render::topdown::TILE_BLOCKS;
+use liboverviewer::stats::WorldStats;
+use liboverviewer::web::{self, Overlay, Tileset};
+use liboverviewer::tint::BiomeTinter;
+use liboverviewer::world::{Dimension, World};
+use rio::FSWrite;
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+const EXIT_ERROR: i32 = 1;
+const EXIT_PROBLEMS: i32 = 2;
+
+// rio paths are relative to the root of the filesystem we hand it, so make everything absolute
+fn native_path(p: &Path) -> Result<rio::PathBuf, OverviewerError> {
+    let abs = try!(std::fs::canonicalize(p));
+    Ok(rio::PathBuf::from(try!(utf8(&abs))))
+}
+
+// rio paths are strings, so it can't be handed a path that isn't UTF-8
+fn utf8(p: &Path) -> Result<&str, OverviewerError> {
+    p.to_str().ok_or_else(|| format!("The path {:?} isn't valid UTF-8", p).into())
+}
+
+// a required path argument, which may not be UTF-8
+fn path_arg<'a>(args: &'a ArgMatches, name: &str) -> &'a Path {
+    Path::new(args.value_of_os(name).unwrap())
+}
+
+fn open_world<'fs>(fs: &'fs rio::Native, p: &Path) -> Result<World<'fs, rio::Native>, OverviewerError> {
+    World::new(fs, try!(native_path(p)))
+}
+
+fn parse_dimension(args: &ArgMatches) -> Result<Dimension, OverviewerError> {
+    let name = args.value_of("dimension").unwrap();
+    Dimension::from_name(name).ok_or_else(|| format!("Unknown dimension {:?}", name).into())
+}
+
+// flags that only mean something to one render mode, and that mode
+const MODE_FLAGS: &[(&str, &str)] =
+    &[("cave-depth-tint", "cave"),
+      ("cross-section-y", "cross_section"),
+      ("cross-section-min-y", "cross_section"),
+      ("cross-section-highlight", "cross_section")];
+
+// the --mode of a render, with the flags for that mode applied
+fn parse_render_mode(args: &ArgMatches) -> Result<RenderMode, OverviewerError> {
+    let name = args.value_of("mode").unwrap();
+    let mut mode = try!(RenderMode::from_name(name)
+        .ok_or_else(|| format!("Unknown render mode {:?}", name)));
+    let allowed: &[&str] = match mode {
+        RenderMode::Cave { ref mut depth_tinting } => {
+            if let Some(range) = args.value_of("cave-depth-tint") {
+                *depth_tinting = Some(try!(parse_y_range(range)));
+            }
+            &["cave-depth-tint"]
+        }
+        RenderMode::CrossSection { ref mut max_y, ref mut min_y, ref mut highlight } => {
+            if args.is_present("cross-section-y") {
+                *max_y = value_t!(args, "cross-section-y", i64).unwrap_or_else(|e| e.exit());
+            }
+            if args.is_present("cross-section-min-y") {
+                *min_y = Some(value_t!(args, "cross-section-min-y", i64)
+                    .unwrap_or_else(|e| e.exit()));
+            }
+            if let Some(min_y) = *min_y {
+                if min_y > *max_y {
+                    return Err("--cross-section-min-y is above --cross-section-y".into());
+                }
+            }
+            match args.value_of("cross-section-highlight") {
+                Some("none") => *highlight = None,
+                Some(color) => {
+                    *highlight = Some(try!(render::parse_color(color).ok_or_else(|| {
+                        format!("Expected a highlight color as #rrggbb or none, not {:?}", color)
+                    })))
+                }
+                None => {}
+            }
+            &["cross-section-y", "cross-section-min-y", "cross-section-highlight"]
+        }
+        _ => &[],
+    };
+
+    for &(flag, mode_name) in MODE_FLAGS {
+        if args.is_present(flag) && !allowed.contains(&flag) {
+            return Err(format!("--{} needs --mode {}", flag, mode_name).into());
+        }
+    }
+    Ok(mode)
+}
+
+// a span of Y levels given as min,max
+fn parse_y_range(s: &str) -> Result<(i64, i64), OverviewerError> {
+    let levels: Vec<i64> = s.split(',')
+        .map(|y| y.trim().parse())
+        .collect::<Result<_, _>>()
+        .unwrap_or_default();
+    if levels.len() != 2 || levels[0] >= levels[1] {
+        return Err(format!("Expected Y levels as min,max, not {:?}", s).into());
+    }
+    Ok((levels[0], levels[1]))
+}
+
+// renders one render of a config into its own directory under `output`, drawing what changed
+// since `old_world` if there is one
+fn render_one(fs: &rio::Native,
+              world: &Path,
+              old_world: Option<&Path>,
+              output: &Path,
+              render: &RenderConfig)
+              -> Result<Tileset, OverviewerError> {
+    let world = try!(open_world(fs, world));
+    let level = try!(world.get_level_dat());
+    let rset = try!(world.get_dimension(render.dimension));
+    let regions: Vec<_> = rset.get_regions().iter().map(|r| (r.x, r.z)).collect();
+    let max_zoom = render.zoom_levels.unwrap_or_else(|| web::zoom_levels_for(&regions));
+
+    let mut renderer = match render.texture_pack {
+        Some(ref p) => {
+            let assets = try!(AssetManager::new(fs, try!(native_path(Path::new(p)))));
+            let mut renderer = TopDown::new(try!(BlockPalette::from_assets(&assets)));
+            renderer.tinter = Some(try!(BiomeTinter::from_assets(&assets)));
+            renderer
+        }
+        None => TopDown::new(BlockPalette::builtin()),
+    };
+    renderer.pixels_per_block = render.pixels_per_block;
+    renderer.primitives = render.render_mode.primitives();
+    if render.slime_chunks {
+        if render.dimension == Dimension::Overworld {
+            renderer.primitives.push(Box::new(SlimeChunks::new(level.seed)));
+        } else {
+            warn!("Render {:?} isn't of the overworld, so it has no slime chunks", render.title);
+        }
+    }
+    if render.mob_spawns {
+        renderer.primitives.push(Box::new(MobSpawns::for_data_version(level.data_version)));
+    }
+    renderer.crop = render.crop;
+    renderer.north = render.north_direction;
+    renderer.format = render.image_format;
+    renderer.zoom = max_zoom;
+
+    let output = output.join(&render.name);
+    try!(std::fs::create_dir_all(&output));
+    let out_dir = try!(native_path(&output));
+    info!("Rendering {:?} into {:?}", render.title, out_dir);
+    // inhabited times come from the chunks themselves, so pick them up while they're decoded
+    let mut inhabited = BTreeMap::new();
+    let tiles = try!(renderer.render_regionset_with(&rset, fs, &out_dir, |pos, chunk| {
+        if let Some(time) = chunk.inhabited_time() {
+            inhabited.insert((pos.x, pos.z), time);
+        }
+    }));
+    info!("Wrote {} tiles", tiles.len());
+
+    let tile_size = TILE_BLOCKS as u32 * render.pixels_per_block;
+    let zoomed =
+        try!(web::build_pyramid(fs, &out_dir, &tiles, max_zoom, render.image_format, tile_size));
+    info!("Wrote {} tiles for {} zoom levels", zoomed, max_zoom);
+
+    // overlays line up with the render's own tiles
+    let layout = OverlayLayout {
+        pixels_per_block: render.pixels_per_block,
+        crop: render.crop,
+        north: render.north_direction,
+        zoom: max_zoom,
+    };
+    let mut overlays = Vec::new();
+    for &activity in &render.overlays {
+        let mut overlay = match activity {
+            Activity::InhabitedTime => ActivityOverlay::from_values(activity, inhabited.clone()),
+            Activity::LastModified => ActivityOverlay::scan(&rset, activity),
+        };
+        overlay.layout = layout;
+
+        try!(std::fs::create_dir_all(output.join(activity.name())));
+        let overlay_dir = out_dir.join(activity.name());
+        let tiles = try!(overlay.render_tiles(fs, &overlay_dir));
+        try!(web::build_pyramid(fs, &overlay_dir, &tiles, max_zoom, ImageFormat::Png, tile_size));
+        info!("Wrote the {} overlay", activity.name());
+        overlays.push(Overlay {
+            name: activity.name().to_owned(),
+            title: activity.title().to_owned(),
+        });
+    }
+
+    if let Some(old_world) = old_world {
+        let old_world = try!(open_world(fs, old_world));
+        let diffs = diff::diff_regionsets(&try!(old_world.get_dimension(render.dimension)), &rset);
+        let mut overlay = DiffOverlay::new(&diffs);
+        overlay.layout = layout;
+
+        try!(std::fs::create_dir_all(output.join("diff")));
+        let overlay_dir = out_dir.join("diff");
+        let tiles = try!(overlay.render_tiles(fs, &overlay_dir));
+        try!(web::build_pyramid(fs, &overlay_dir, &tiles, max_zoom, ImageFormat::Png, tile_size));
+        info!("Wrote the diff overlay, with {} changed chunks", diffs.len());
+        overlays.push(Overlay {
+            name: "diff".to_owned(),
+            title: "Changes".to_owned(),
+        });
+    }
+
+    if !render.markers.is_empty() {
+        let entities = world.get_entities(render.dimension);
+        let players: Vec<_> = try!(world.players())
+            .iter()
+            .flat_map(|p| Poi::from_player(p, render.dimension))
+            .collect();
+        let groups = poi::collect_markers(&rset, entities.as_ref(), &players, &render.markers);
+        info!("Found {} markers", groups.iter().map(|g| g.len()).sum::<usize>());
+        let json = poi::markers_json(&render.markers, &groups);
+        try!(write!(try!(fs.create(out_dir.join("markers.json"))), "{}\n", json));
+    }
+
+    if render.maps {
+        let maps: Vec<_> = try!(world.maps())
+            .into_iter()
+            .filter(|m| m.dimension == Some(render.dimension))
+            .collect();
+        try!(maps::write_overlay(fs, &out_dir, &maps, render.north_direction));
+        info!("Wrote {} in-game maps", maps.len());
+    }
+
+    Ok(Tileset {
+        name: render.name.clone(),
+        title: render.title.clone(),
+        world_name: level.level_name,
+        dimension: render.dimension,
+        format: render.image_format,
+        tile_size: tile_size,
+        pixels_per_block: render.pixels_per_block,
+        max_zoom: max_zoom,
+        north: render.north_direction,
+        // spawn is always in the overworld
+        spawn: if render.dimension == Dimension::Overworld {
+            Some(level.spawn)
+        } else {
+            None
+        },
+        markers: !render.markers.is_empty(),
+        maps: render.maps,
+        overlays: overlays,
+    })
+}
+
+fn write_viewer(fs: &rio::Native, output: &Path, tilesets: &[Tileset]) -> Result<(), OverviewerError> {
+    let out_dir = try!(native_path(output));
+    try!(web::write_viewer(fs, &out_dir, tilesets));
+    info!("Open {:?} in a browser to see the map", out_dir.join("index.html"));
+    Ok(())
+}
+
+fn render(args: &ArgMatches) -> Result<i32, OverviewerError> {
+    let fs = rio::Native::new("/");
+
+    if let Some(config_path) = args.value_of_os("config").map(Path::new) {
+        let config = try!(Config::load(&fs, try!(native_path(config_path))));
+        // paths in the config file are relative to the config file
+        let base = config_path.parent().unwrap_or(Path::new(""));
+        let output = base.join(&config.output_dir);
+        let mut tilesets = Vec::new();
+        for render in &config.renders {
+            let world = base.join(&config.worlds[&render.world]);
+            let old_world = render.diff_against.as_ref().map(|w| base.join(&config.worlds[w]));
+            let mut render = render.clone();
+            if let Some(p) = render.texture_pack.take() {
+                render.texture_pack = Some(try!(utf8(&base.join(p))).to_owned());
+            }
+            tilesets.push(try!(render_one(&fs,
+                                          &world,
+                                          old_world.as_deref(),
+                                          &output,
+                                          &render)));
+        }
+        try!(write_viewer(&fs, &output, &tilesets));
+        return Ok(0);
+    }
+
+    let render = RenderConfig {
+        name: "map".to_owned(),
+        title: "map".to_owned(),
+        world: "world".to_owned(),
+        dimension: try!(parse_dimension(args)),
+        render_mode: try!(parse_render_mode(args)),
+        north_direction: NorthDirection::UpperLeft,
+        crop: None,
+        image_format: ImageFormat::Png,
+        zoom_levels: None,
+        texture_pack: match args.value_of_os("textures") {
+            Some(p) => Some(try!(utf8(Path::new(p))).to_owned()),
+            None => None,
+        },
+        pixels_per_block: value_t!(args, "scale", u32).unwrap_or_else(|e| e.exit()),
+        markers: Vec::new(),
+        maps: false,
+        overlays: args.values_of("overlay")
+            .map(|v| v.filter_map(Activity::from_name).collect())
+            .unwrap_or_default(),
+        diff_against: args.value_of_os("diff").map(|_| "old".to_owned()),
+        slime_chunks: args.is_present("slime"),
+        mob_spawns: args.is_present("spawns"),
+    };
+    let output = path_arg(args, "output");
+    let tileset = try!(render_one(&fs,
+                                  path_arg(args, "world"),
+                                  args.value_of_os("diff").map(Path::new),
+                                  output,
+                                  &render));
+    try!(write_viewer(&fs, output, &[tileset]));
+    Ok(0)
+}
+
+fn info(args: &ArgMatches) -> Result<i32, OverviewerError> {
+    let fs = rio::Native::new("/");
+    let world = try!(open_world(&fs, path_arg(args, "world")));
+    let level = try!(world.get_level_dat());
+
+    println!("Name:         {}", level.level_name);
+    println!("Version:      {}", level.version_name.as_ref().map_or("unknown", |s| &s[..]));
+    println!("Data version: {}", level.data_version.map_or("unknown".to_owned(), |v| v.to_string()));
+    println!("Seed:         {}", level.seed);
+    println!("Spawn:        {}, {}, {}", level.spawn.x, level.spawn.y, level.spawn.z);
+    for dim in world.get_dimensions() {
+        let rset = try!(world.get_dimension(dim));
+        println!("{:?}: {} regions, {} chunks",
+                 dim,
+                 rset.get_regions().len(),
+                 rset.get_chunks().count());
+    }
+    for player in try!(world.players()) {
+        let name = player.name.as_ref().or(player.uuid.as_ref()).map_or("unknown", |s| &s[..]);
+        println!("Player {}: {}, {}, {} in the {}",
+                 name,
+                 player.pos.x,
+                 player.pos.y,
+                 player.pos.z,
+                 player.dimension.map_or("unknown dimension", |d| d.name()));
+    }
+    Ok(0)
+}
+
+fn check(args: &ArgMatches) -> Result<i32, OverviewerError> {
+    let fs = rio::Native::new("/");
+    let world = try!(open_world(&fs, path_arg(args, "world")));
+
+    let mut total = 0;
+    for dim in world.get_dimensions() {
+        let rset = try!(world.get_dimension(dim));
+        info!("Checking {} regions in {:?}", rset.get_regions().len(), dim);
+        for problem in rset.check() {
+            total += 1;
+            match problem.chunk {
+                Some(c) => {
+                    println!("{:?} region {}, {} chunk {}, {}: {}",
+                             dim,
+                             problem.region.x,
+                             problem.region.z,
+                             c.x,
+                             c.z,
+                             problem.msg)
+                }
+                None => {
+                    println!("{:?} region {}, {}: {}",
+                             dim,
+                             problem.region.x,
+                             problem.region.z,
+                             problem.msg)
+                }
+            }
+        }
+    }
+
+    if total > 0 {
+        println!("Found {} problems", total);
+        Ok(EXIT_PROBLEMS)
+    } else {
+        println!("No problems found");
+        Ok(0)
+    }
+}
+
+fn search(args: &ArgMatches) -> Result<i32, OverviewerError> {
+    let fs = rio::Native::new("/");
+    let world = try!(open_world(&fs, path_arg(args, "world")));
+    let item = args.value_of("item").unwrap();
+    let dims = match args.value_of("dimension") {
+        Some(_) => vec![try!(parse_dimension(args))],
+        None => world.get_dimensions(),
+    };
+
+    let mut total = 0;
+    for dim in dims {
+        let rset = try!(world.get_dimension(dim));
+        for info in rset.get_chunks() {
+            let chunk = match rset.get_chunk(info.pos) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            for block_entity in chunk.block_entities() {
+                let found = block_entity.find_items(item);
+                if found.is_empty() {
+                    continue;
+                }
+                let count: i32 = found.iter().map(|i| i.count).sum();
+                total += count;
+                println!("{} {}, {}, {} in the {}: {}",
+                         block_entity.custom_name.as_ref().unwrap_or(&block_entity.id),
+                         block_entity.x,
+                         block_entity.y,
+                         block_entity.z,
+                         dim.name(),
+                         count);
+            }
+        }
+    }
+    println!("Found {} in total", total);
+    Ok(0)
+}
+
+fn export_maps(args: &ArgMatches) -> Result<i32, OverviewerError> {
+    let fs = rio::Native::new("/");
+    let world = try!(open_world(&fs, path_arg(args, "world")));
+    let output = path_arg(args, "output");
+    try!(std::fs::create_dir_all(output));
+    let out_dir = try!(native_path(output));
+
+    let maps = try!(world.maps());
+    for map in &maps {
+        let ((x1, z1), (x2, z2)) = map.bounds();
+        info!("Map {}: {}, {} to {}, {} in the {}",
+              map.id,
+              x1,
+              z1,
+              x2 - 1,
+              z2 - 1,
+              map.dimension.map_or("unknown dimension", |d| d.name()));
+        let mut f = try!(fs.create(out_dir.join(format!("map_{}.png", map.id))));
+        try!(ImageFormat::Png.save(map.to_image(), &mut f));
+    }
+    println!("Saved {} maps", maps.len());
+    Ok(0)
+}
+
+fn analyze(args: &ArgMatches) -> Result<i32, OverviewerError> {
+    let fs = rio::Native::new("/");
+    let world = try!(open_world(&fs, path_arg(args, "world")));
+    let dim = try!(parse_dimension(args));
+    let rset = try!(world.get_dimension(dim));
+    info!("Analyzing {} chunks in the {}", rset.get_chunks().count(), dim.name());
+    let stats = WorldStats::scan(&rset);
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    if args.value_of("format") == Some("csv") {
+        try!(stats.write_csv(&mut out));
+    } else {
+        try!(writeln!(out, "{}", stats.to_json()));
+    }
+    Ok(0)
+}
+
+fn diff_worlds(args: &ArgMatches) -> Result<i32, OverviewerError> {
+    let fs = rio::Native::new("/");
+    let old = try!(open_world(&fs, path_arg(args, "old")));
+    let new = try!(open_world(&fs, path_arg(args, "new")));
+    let dim = try!(parse_dimension(args));
+    let output = path_arg(args, "output");
+    try!(std::fs::create_dir_all(output));
+    let out_dir = try!(native_path(output));
+
+    let diffs = diff::diff_regionsets(&try!(old.get_dimension(dim)), &try!(new.get_dimension(dim)));
+    for d in &diffs {
+        println!("{} chunk {}, {} (blocks {}, {}): {} blocks changed",
+                 d.kind.name(),
+                 d.pos.x,
+                 d.pos.z,
+                 d.pos.x * 16,
+                 d.pos.z * 16,
+                 d.blocks.len());
+    }
+
+    let json = diff::diff_json(&diffs);
+    try!(write!(try!(fs.create(out_dir.join("diff.json"))), "{}\n", json));
+    println!("Found {} changed chunks", diffs.len());
+    Ok(0)
+}
+
+// a protected area given as x1,z1,x2,z2
+fn parse_box(s: &str) -> Result<CropBox, OverviewerError> {
+    // one bad number rejects the whole area, rather than shifting the others into its place
+    let corners: Vec<i64> = s.split(',')
+        .map(|c| c.trim().parse())
+        .collect::<Result<_, _>>()
+        .unwrap_or_default();
+    if corners.len() != 4 {
+        return Err(format!("Expected an area as x1,z1,x2,z2, not {:?}", s).into());
+    }
+    Ok(CropBox::new(corners[0], corners[1], corners[2], corners[3]))
+}
+
+fn prune(args: &ArgMatches) -> Result<i32, OverviewerError> {
+    let fs = rio::Native::new("/");
+    let world = try!(open_world(&fs, path_arg(args, "world")));
+    let min_time = value_t!(args, "min-inhabited", i64).unwrap_or_else(|e| e.exit());
+    let mut options = PruneOptions::new(min_time);
+    options.keep_named = !args.is_present("ignore-names");
+    for area in args.values_of("protect").into_iter().flatten() {
+        options.protected.push(try!(parse_box(area)));
+    }
+    let dims = match args.value_of("dimension") {
+        Some(_) => vec![try!(parse_dimension(args))],
+        None => world.get_dimensions(),
+    };
+    let dry_run = args.is_present("dry-run");
+
+    let mut total = 0;
+    for dim in dims {
+        let chunks = {
+            let rset = try!(world.get_dimension(dim));
+            info!("Looking for chunks to prune in {} chunks in the {}",
+                  rset.get_chunks().count(),
+                  dim.name());
+            prune::find_prunable(&rset, &options)
+        };
+        if dry_run {
+            for c in &chunks {
+                println!("{} chunk {}, {} (blocks {}, {})",
+                         dim.name(),
+                         c.x,
+                         c.z,
+                         c.x * 16,
+                         c.z * 16);
+            }
+            total += chunks.len();
+        } else {
+            total += try!(prune::delete_dimension_chunks(&fs, &world.world_dir, dim, &chunks));
+        }
+    }
+
+    if dry_run {
+        println!("Would delete {} chunks", total);
+    } else {
+        println!("Deleted {} chunks", total);
+    }
+    Ok(0)
+}
+
+fn init_logging(verbose: bool) {
+    let mut builder = env_logger::LogBuilder::new();
+    builder.filter(None,
+                   if verbose {
+                       log::LogLevelFilter::Debug
+                   } else {
+                       log::LogLevelFilter::Info
+                   });
+    if let Ok(spec) = std::env::var("RUST_LOG") {
+        builder.parse(&spec);
+    }
+    builder.init().unwrap();
+}
 
 fn main() {
-    println!("Hello world");
+    let world_arg = Arg::with_name("world")
+        .required(true)
+        .help("Path to the world directory (the one containing level.dat)");
+
+    let matches = App::new("overviewer")
+        .about("Renders Minecraft worlds")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("verbose").short("v").long("verbose").global(true))
+        .subcommand(SubCommand::with_name("render")
+            .about("Renders a world to a directory of tiles")
+            .arg(Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .takes_value(true)
+                .help("A config file describing the renders to do"))
+            .arg(world_arg.clone().required_unless("config"))
+            .arg(Arg::with_name("output")
+                .required_unless("config")
+                .help("Directory to write tiles to"))
+            .arg(Arg::with_name("dimension")
+                .short("d")
+                .long("dimension")
+                .takes_value(true)
+                .default_value("overworld"))
+            .arg(Arg::with_name("mode")
+                .short("m")
+                .long("mode")
+                .takes_value(true)
+                .default_value("normal")
+                .possible_values(&["normal",
+                                  "lighting",
+                                  "smooth_lighting",
+                                  "flat_lighting",
+                                  "night",
+                                  "cave",
+                                  "cross_section"]))
+            .arg(Arg::with_name("cave-depth-tint")
+                .long("cave-depth-tint")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .value_name("MIN,MAX")
+                .help("Tint caves from red at the MIN Y level to blue at MAX"))
+            .arg(Arg::with_name("cross-section-y")
+                .long("cross-section-y")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .value_name("Y")
+                .help("The highest Y level a cross section draws [default: 62]"))
+            .arg(Arg::with_name("cross-section-min-y")
+                .long("cross-section-min-y")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .value_name("Y")
+                .help("The lowest Y level a cross section draws"))
+            .arg(Arg::with_name("cross-section-highlight")
+                .long("cross-section-highlight")
+                .takes_value(true)
+                .value_name("COLOR")
+                .help("The #rrggbb color to mark the top of a cross section with, or none \
+                       [default: #ff0000]"))
+            .arg(Arg::with_name("scale")
+                .short("s")
+                .long("scale")
+                .takes_value(true)
+                .default_value("1")
+                .help("Pixels per block"))
+            .arg(Arg::with_name("textures")
+                .short("t")
+                .long("textures")
+                .takes_value(true)
+                .help("An unzipped client jar or resource pack to take block colors from"))
+            .arg(Arg::with_name("overlay")
+                .short("o")
+                .long("overlay")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .possible_values(&["last_modified", "inhabited_time"])
+                .help("Also draw an overlay of chunk activity that the viewer can turn on"))
+            .arg(Arg::with_name("diff")
+                .long("diff")
+                .takes_value(true)
+                .value_name("OLD")
+                .help("Also draw an overlay of what changed since an older copy of the world"))
+            .arg(Arg::with_name("slime")
+                .long("slime")
+                .help("Tint the chunks that slimes can spawn in"))
+            .arg(Arg::with_name("spawns")
+                .long("mob-spawns")
+                .help("Highlight blocks that are dark enough for hostile mobs to spawn on")))
+        .subcommand(SubCommand::with_name("info")
+            .about("Prints a summary of a world")
+            .arg(world_arg.clone()))
+        .subcommand(SubCommand::with_name("check")
+            .about("Reads every chunk of a world, reporting any that are damaged")
+            .arg(world_arg.clone()))
+        .subcommand(SubCommand::with_name("maps")
+            .about("Saves every in-game map item as a PNG")
+            .arg(world_arg.clone())
+            .arg(Arg::with_name("output")
+                .required(true)
+                .help("Directory to write images to")))
+        .subcommand(SubCommand::with_name("search")
+            .about("Lists the containers holding an item, including inside shulker boxes")
+            .arg(world_arg.clone())
+            .arg(Arg::with_name("item")
+                .required(true)
+                .help("The item ID to look for, such as diamond or minecraft:elytra"))
+            .arg(Arg::with_name("dimension")
+                .short("d")
+                .long("dimension")
+                .takes_value(true)
+                .help("Only search this dimension")))
+        .subcommand(SubCommand::with_name("prune")
+            .about("Deletes chunks that players have spent little time near, so that they are \
+                    generated afresh. Stop the server and take a backup first!")
+            .arg(world_arg.clone())
+            .arg(Arg::with_name("min-inhabited")
+                .short("t")
+                .long("min-inhabited")
+                .takes_value(true)
+                .default_value("1200")
+                .help("Keep chunks that players have spent at least this many ticks near (20 \
+                       ticks is one second)"))
+            .arg(Arg::with_name("protect")
+                .short("p")
+                .long("protect")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Never delete chunks in this area, given as x1,z1,x2,z2 in blocks"))
+            .arg(Arg::with_name("ignore-names")
+                .long("ignore-names")
+                .help("Also delete chunks with written signs or renamed containers"))
+            .arg(Arg::with_name("dimension")
+                .short("d")
+                .long("dimension")
+                .takes_value(true)
+                .help("Only prune this dimension"))
+            .arg(Arg::with_name("dry-run")
+                .short("n")
+                .long("dry-run")
+                .help("List the chunks that would be deleted without deleting them")))
+        .subcommand(SubCommand::with_name("analyze")
+            .about("Counts the blocks, ores at each Y level and surface biomes of a dimension")
+            .arg(world_arg.clone())
+            .arg(Arg::with_name("dimension")
+                .short("d")
+                .long("dimension")
+                .takes_value(true)
+                .default_value("overworld"))
+            .arg(Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .default_value("json")
+                .possible_values(&["json", "csv"])))
+        .subcommand(SubCommand::with_name("diff")
+            .about("Compares two copies of a world, such as two backups, and draws what changed")
+            .arg(Arg::with_name("old")
+                .required(true)
+                .help("The older copy of the world"))
+            .arg(Arg::with_name("new")
+                .required(true)
+                .help("The newer copy of the world"))
+            .arg(Arg::with_name("output")
+                .required(true)
+                .help("Directory to write diff.json to"))
+            .arg(Arg::with_name("dimension")
+                .short("d")
+                .long("dimension")
+                .takes_value(true)
+                .default_value("overworld")))
+        .get_matches();
+
+    init_logging(matches.is_present("verbose"));
+
+    let result = match matches.subcommand() {
+        ("render", Some(args)) => render(args),
+        ("info", Some(args)) => info(args),
+        ("check", Some(args)) => check(args),
+        ("maps", Some(args)) => export_maps(args),
+        ("search", Some(args)) => search(args),
+        ("prune", Some(args)) => prune(args),
+        ("analyze", Some(args)) => analyze(args),
+        ("diff", Some(args)) => diff_worlds(args),
+        _ => unreachable!(),
+    };
+
+    match result {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            error!("{}", e);
+            process::exit(EXIT_ERROR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use liboverviewer::blocks::BlockState;
+    use liboverviewer::fixtures::WorldBuilder;
+    use image::{self, GenericImage};
+
+    #[test]
+    #[cfg(unix)]
+    fn test_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"/tmp/w\xf6rld"));
+        assert!(utf8(path).is_err());
+        assert_eq!(utf8(Path::new("/tmp/world")).unwrap(), "/tmp/world");
+    }
+
+    #[test]
+    fn test_render_cave_depth_tint() {
+        let dir = std::env::temp_dir().join(format!("overviewer-test-{}", process::id()));
+        let world_dir = dir.join("world");
+        std::fs::create_dir_all(&world_dir).unwrap();
+
+        // a stone pillar standing in a cave that the sky can't reach
+        let mut builder = WorldBuilder::new("Caves");
+        {
+            let chunk = builder.chunk(Dimension::Overworld, 0, 0);
+            for y in 30..71 {
+                chunk.set_block(8, y, 8, BlockState::new("stone"));
+            }
+            chunk.set_sky_light(0, 40, 0, 0);
+        }
+        let fs = rio::Native::new("/");
+        builder.write(&fs, &native_path(&world_dir).unwrap()).unwrap();
+
+        let mut render = RenderConfig {
+            name: "plain".to_owned(),
+            title: "plain".to_owned(),
+            world: "world".to_owned(),
+            dimension: Dimension::Overworld,
+            render_mode: RenderMode::Cave { depth_tinting: None },
+            north_direction: NorthDirection::UpperLeft,
+            crop: None,
+            image_format: ImageFormat::Png,
+            zoom_levels: None,
+            texture_pack: None,
+            pixels_per_block: 1,
+            markers: Vec::new(),
+            maps: false,
+            overlays: Vec::new(),
+            diff_against: None,
+            slime_chunks: false,
+            mob_spawns: false,
+        };
+        render_one(&fs, &world_dir, None, &dir, &render).unwrap();
+        render.name = "tinted".to_owned();
+        render.render_mode = RenderMode::Cave { depth_tinting: Some((0, 64)) };
+        render_one(&fs, &world_dir, None, &dir, &render).unwrap();
+
+        let pixel = |name: &str| {
+            image::open(dir.join(name).join("0.0.0.png")).unwrap().get_pixel(8, 8)
+        };
+        let (plain, tinted) = (pixel("plain"), pixel("tinted"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(plain[3], 255);
+        assert_eq!(tinted[3], 255);
+        assert!(plain != tinted);
+    }
 }